@@ -1,16 +1,37 @@
-import { invoke } from '@tauri-apps/api/core'
+import { convertFileSrc, invoke } from '@tauri-apps/api/core'
 import type {
+  ApiAttachmentBatchResultDto,
   ApiAttachmentDto,
+  ApiAttachmentPreviewDto,
+  ApiCacheStatsDto,
   ApiAuditDto,
   ApiChannelDto,
   ApiChannelMemberDto,
+  ApiChannelReadStateDto,
   ApiMessageDto,
+  ApiLocalSearchHitDto,
+  ApiMentionSuggestionDto,
   ApiMessageListDto,
+  ApiMessageSearchFilters,
+  ApiMessageSearchResultDto,
+  ApiNotificationSettingsDto,
+  ApiReactionSummaryDto,
   ApiThreadSummaryDto,
   ApiUserDto,
   ApiWorkspaceDto,
   ApiWorkspaceMemberDto,
-  AuthSessionDto
+  AuthSessionDto,
+  ApiDraftDto,
+  ApiScheduledMessageDto,
+  ApiPinDto,
+  ApiPinListDto,
+  ApiSavedItemDto,
+  ApiDmConversationDto,
+  Role,
+  ApiPermissionsDto,
+  ApiImportReportDto,
+  ApiInviteDto,
+  ApiSessionDto
 } from '~/types/galynx'
 
 const ensureTauri = () => {
@@ -20,9 +41,11 @@ const ensureTauri = () => {
 }
 
 export const useGalynxApi = () => {
-  const authLogin = (email: string, password: string, workspaceId?: string) => {
+  const authLogin = (email: string, password: string, workspaceId?: string, deviceName?: string) => {
     ensureTauri()
-    return invoke<AuthSessionDto>('auth_login', { payload: { email, password, workspace_id: workspaceId } })
+    return invoke<AuthSessionDto>('auth_login', {
+      payload: { email, password, workspace_id: workspaceId, device_name: deviceName }
+    })
   }
 
   const authMe = () => {
@@ -35,9 +58,51 @@ export const useGalynxApi = () => {
     return invoke<void>('auth_logout')
   }
 
-  const channelsList = () => {
+  const authAcceptInvite = (token: string, password: string, name?: string) => {
+    ensureTauri()
+    return invoke<AuthSessionDto>('auth_accept_invite', { payload: { token, name, password } })
+  }
+
+  const authPendingInvite = () => {
+    ensureTauri()
+    return invoke<string | null>('auth_pending_invite')
+  }
+
+  const authChangePassword = (currentPassword: string, newPassword: string) => {
+    ensureTauri()
+    return invoke<void>('auth_change_password', {
+      payload: { current_password: currentPassword, new_password: newPassword }
+    })
+  }
+
+  const authRequestReset = (email: string) => {
+    ensureTauri()
+    return invoke<void>('auth_request_reset', { payload: { email } })
+  }
+
+  const authConfirmReset = (token: string, newPassword: string) => {
     ensureTauri()
-    return invoke<ApiChannelDto[]>('channels_list')
+    return invoke<void>('auth_confirm_reset', { payload: { token, new_password: newPassword } })
+  }
+
+  const sessionsList = () => {
+    ensureTauri()
+    return invoke<ApiSessionDto[]>('sessions_list')
+  }
+
+  const sessionsRevoke = (sessionId: string) => {
+    ensureTauri()
+    return invoke<ApiSessionDto[]>('sessions_revoke', { payload: { session_id: sessionId } })
+  }
+
+  const sessionsRevokeOthers = () => {
+    ensureTauri()
+    return invoke<ApiSessionDto[]>('sessions_revoke_others')
+  }
+
+  const channelsList = (includeArchived = false) => {
+    ensureTauri()
+    return invoke<ApiChannelDto[]>('channels_list', { payload: { include_archived: includeArchived } })
   }
 
   const channelsCreate = (name: string, isPrivate: boolean) => {
@@ -50,6 +115,33 @@ export const useGalynxApi = () => {
     return invoke<void>('channels_delete', { payload: { channel_id: channelId } })
   }
 
+  const channelsRename = (channelId: string, name: string) => {
+    ensureTauri()
+    return invoke<ApiChannelDto>('channels_rename', { payload: { channel_id: channelId, name } })
+  }
+
+  const channelsSetTopic = (channelId: string, topic: string | null) => {
+    ensureTauri()
+    return invoke<ApiChannelDto>('channels_set_topic', { payload: { channel_id: channelId, topic } })
+  }
+
+  const channelsArchive = (channelId: string) => {
+    ensureTauri()
+    return invoke<ApiChannelDto>('channels_archive', { payload: { channel_id: channelId } })
+  }
+
+  const channelsUnarchive = (channelId: string) => {
+    ensureTauri()
+    return invoke<ApiChannelDto>('channels_unarchive', { payload: { channel_id: channelId } })
+  }
+
+  const channelsMarkRead = (channelId: string, messageId?: string) => {
+    ensureTauri()
+    return invoke<ApiChannelReadStateDto>('channels_mark_read', {
+      payload: { channel_id: channelId, message_id: messageId }
+    })
+  }
+
   const channelMembersList = (channelId: string) => {
     ensureTauri()
     return invoke<ApiChannelMemberDto[]>('channel_members_list', { payload: { channel_id: channelId } })
@@ -86,6 +178,28 @@ export const useGalynxApi = () => {
     })
   }
 
+  const messagesSendWithAttachments = (
+    channelId: string,
+    bodyMd: string,
+    files: Array<{ filename: string; contentType: string; bytes: Uint8Array }>,
+    concurrency?: number
+  ) => {
+    ensureTauri()
+    return invoke<ApiMessageDto>('messages_send_with_attachments', {
+      payload: {
+        channel_id: channelId,
+        body_md: bodyMd,
+        files: files.map((file) => ({
+          filename: file.filename,
+          content_type: file.contentType,
+          size_bytes: file.bytes.byteLength,
+          bytes: Array.from(file.bytes)
+        })),
+        concurrency
+      }
+    })
+  }
+
   const attachmentsUploadCommit = (
     channelId: string,
     messageId: string,
@@ -106,6 +220,29 @@ export const useGalynxApi = () => {
     })
   }
 
+  const attachmentsUploadBatch = (
+    channelId: string,
+    messageId: string,
+    files: Array<{ filename: string; contentType: string; bytes: Uint8Array }>,
+    options: { batchId?: string; concurrency?: number } = {}
+  ) => {
+    ensureTauri()
+    return invoke<ApiAttachmentBatchResultDto>('attachments_upload_batch', {
+      payload: {
+        channel_id: channelId,
+        message_id: messageId,
+        files: files.map((file) => ({
+          filename: file.filename,
+          content_type: file.contentType,
+          size_bytes: file.bytes.byteLength,
+          bytes: Array.from(file.bytes)
+        })),
+        batch_id: options.batchId,
+        concurrency: options.concurrency
+      }
+    })
+  }
+
   const messagesEdit = (messageId: string, bodyMd: string) => {
     ensureTauri()
     return invoke<ApiMessageDto>('messages_edit', {
@@ -125,6 +262,55 @@ export const useGalynxApi = () => {
     })
   }
 
+  const messagesSearch = (
+    query: string,
+    filters: ApiMessageSearchFilters = {},
+    limit = 20,
+    cursor?: string
+  ) => {
+    ensureTauri()
+    return invoke<ApiMessageSearchResultDto>('messages_search', {
+      payload: {
+        query,
+        filters,
+        limit,
+        cursor
+      }
+    })
+  }
+
+  const localSearch = (query: string, channelId?: string, limit = 50) => {
+    ensureTauri()
+    return invoke<ApiLocalSearchHitDto[]>('local_search', {
+      payload: {
+        query,
+        channel_id: channelId,
+        limit
+      }
+    })
+  }
+
+  const reactionsAdd = (messageId: string, emoji: string) => {
+    ensureTauri()
+    return invoke<ApiReactionSummaryDto[]>('reactions_add', {
+      payload: { message_id: messageId, emoji }
+    })
+  }
+
+  const reactionsRemove = (messageId: string, emoji: string) => {
+    ensureTauri()
+    return invoke<ApiReactionSummaryDto[]>('reactions_remove', {
+      payload: { message_id: messageId, emoji }
+    })
+  }
+
+  const reactionsList = (messageId: string) => {
+    ensureTauri()
+    return invoke<ApiReactionSummaryDto[]>('reactions_list', {
+      payload: { message_id: messageId }
+    })
+  }
+
   const threadGet = (rootId: string) => {
     ensureTauri()
     return invoke<ApiThreadSummaryDto>('thread_get', { payload: { root_id: rootId } })
@@ -181,11 +367,80 @@ export const useGalynxApi = () => {
     })
   }
 
+  const workspaceMembersSetRole = (workspaceId: string, userId: string, role: Role) => {
+    ensureTauri()
+    return invoke<void>('workspace_members_set_role', {
+      payload: { workspace_id: workspaceId, user_id: userId, role }
+    })
+  }
+
+  const workspaceMembersRemove = (workspaceId: string, userId: string) => {
+    ensureTauri()
+    return invoke<void>('workspace_members_remove', { payload: { workspace_id: workspaceId, user_id: userId } })
+  }
+
+  const usersDisable = (userId: string) => {
+    ensureTauri()
+    return invoke<void>('users_disable', { payload: { user_id: userId } })
+  }
+
+  const usersEnable = (userId: string) => {
+    ensureTauri()
+    return invoke<void>('users_enable', { payload: { user_id: userId } })
+  }
+
+  const invitesCreate = (email: string, role?: 'admin' | 'member', expiresInHours?: number) => {
+    ensureTauri()
+    return invoke<ApiInviteDto>('invites_create', {
+      payload: { email, role, expires_in_hours: expiresInHours }
+    })
+  }
+
+  const invitesList = () => {
+    ensureTauri()
+    return invoke<ApiInviteDto[]>('invites_list')
+  }
+
+  const invitesRevoke = (inviteId: string) => {
+    ensureTauri()
+    return invoke<void>('invites_revoke', { payload: { invite_id: inviteId } })
+  }
+
   const usersList = () => {
     ensureTauri()
     return invoke<ApiUserDto[]>('users_list')
   }
 
+  const usersResolve = (userIds: string[], workspaceId?: string) => {
+    ensureTauri()
+    return invoke<ApiUserDto[]>('users_resolve', {
+      payload: {
+        user_ids: userIds,
+        workspace_id: workspaceId
+      }
+    })
+  }
+
+  const mentionsSuggest = (prefix: string, channelId?: string, limit?: number) => {
+    ensureTauri()
+    return invoke<ApiMentionSuggestionDto[]>('mentions_suggest', {
+      payload: {
+        prefix,
+        channel_id: channelId,
+        limit
+      }
+    })
+  }
+
+  const markdownRender = (bodyMd: string) => {
+    ensureTauri()
+    return invoke<string>('markdown_render', {
+      payload: {
+        body_md: bodyMd
+      }
+    })
+  }
+
   const usersCreate = (payload: {
     email: string
     name: string
@@ -208,6 +463,123 @@ export const useGalynxApi = () => {
     return invoke<ApiAttachmentDto>('attachment_get', { payload: { attachment_id: attachmentId } })
   }
 
+  const attachmentResolveUrl = (attachmentId: string, forceRefresh = false) => {
+    ensureTauri()
+    return invoke<string>('attachment_resolve_url', {
+      payload: { attachment_id: attachmentId, force_refresh: forceRefresh }
+    })
+  }
+
+  const attachmentPreview = (attachmentId: string) => {
+    ensureTauri()
+    return invoke<ApiAttachmentPreviewDto | null>('attachment_preview', {
+      payload: { attachment_id: attachmentId }
+    })
+  }
+
+  const attachmentCachedUrl = (attachmentId: string) => {
+    ensureTauri()
+    return convertFileSrc(attachmentId, 'galynx-attachment')
+  }
+
+  const cacheStats = () => {
+    ensureTauri()
+    return invoke<ApiCacheStatsDto>('cache_stats')
+  }
+
+  const cacheClear = () => {
+    ensureTauri()
+    return invoke<ApiCacheStatsDto>('cache_clear')
+  }
+
+  const draftsSave = (channelId: string, bodyMd: string, rootId?: string) => {
+    ensureTauri()
+    return invoke<ApiDraftDto | null>('drafts_save', {
+      payload: { channel_id: channelId, root_id: rootId, body_md: bodyMd }
+    })
+  }
+
+  const draftsGet = (channelId: string, rootId?: string) => {
+    ensureTauri()
+    return invoke<ApiDraftDto | null>('drafts_get', { payload: { channel_id: channelId, root_id: rootId } })
+  }
+
+  const draftsList = () => {
+    ensureTauri()
+    return invoke<ApiDraftDto[]>('drafts_list')
+  }
+
+  const messagesSchedule = (channelId: string, bodyMd: string, sendAt: number) => {
+    ensureTauri()
+    return invoke<ApiScheduledMessageDto>('messages_schedule', {
+      payload: { channel_id: channelId, body_md: bodyMd, send_at: sendAt }
+    })
+  }
+
+  const messagesScheduledList = () => {
+    ensureTauri()
+    return invoke<ApiScheduledMessageDto[]>('messages_scheduled_list')
+  }
+
+  const messagesScheduleCancel = (id: string) => {
+    ensureTauri()
+    return invoke<boolean>('messages_schedule_cancel', { payload: { id } })
+  }
+
+  const pinsAdd = (channelId: string, messageId: string) => {
+    ensureTauri()
+    return invoke<ApiPinDto>('pins_add', { payload: { channel_id: channelId, message_id: messageId } })
+  }
+
+  const pinsRemove = (channelId: string, messageId: string) => {
+    ensureTauri()
+    return invoke<void>('pins_remove', { payload: { channel_id: channelId, message_id: messageId } })
+  }
+
+  const pinsList = (channelId: string) => {
+    ensureTauri()
+    return invoke<ApiPinListDto>('pins_list', { payload: { channel_id: channelId } })
+  }
+
+  const savedItemsAdd = (messageId: string) => {
+    ensureTauri()
+    return invoke<ApiSavedItemDto>('saved_items_add', { payload: { message_id: messageId } })
+  }
+
+  const savedItemsRemove = (messageId: string) => {
+    ensureTauri()
+    return invoke<boolean>('saved_items_remove', { payload: { message_id: messageId } })
+  }
+
+  const savedItemsList = () => {
+    ensureTauri()
+    return invoke<ApiSavedItemDto[]>('saved_items_list')
+  }
+
+  const dmOpen = (userIds: string[]) => {
+    ensureTauri()
+    return invoke<ApiDmConversationDto>('dm_open', { payload: { user_ids: userIds } })
+  }
+
+  const dmList = () => {
+    ensureTauri()
+    return invoke<ApiDmConversationDto[]>('dm_list')
+  }
+
+  const permissionsForContext = (context: { channelId?: string; messageId?: string } = {}) => {
+    ensureTauri()
+    return invoke<ApiPermissionsDto>('permissions_for_context', {
+      payload: { channel_id: context.channelId, message_id: context.messageId }
+    })
+  }
+
+  const usersImportCsv = (csv: string, dryRun = false, concurrency?: number) => {
+    ensureTauri()
+    return invoke<ApiImportReportDto>('users_import_csv', {
+      payload: { csv, dry_run: dryRun, concurrency }
+    })
+  }
+
   const realtimeConnect = () => {
     ensureTauri()
     return invoke<void>('realtime_connect')
@@ -228,21 +600,73 @@ export const useGalynxApi = () => {
     return invoke<string>('settings_set_api_base', { payload: { api_base: apiBase } })
   }
 
+  const settingsGetNotifications = () => {
+    ensureTauri()
+    return invoke<ApiNotificationSettingsDto>('settings_get_notifications')
+  }
+
+  const settingsSetNotifications = (settings: ApiNotificationSettingsDto) => {
+    ensureTauri()
+    const dnd = settings.dnd
+      ? { ...settings.dnd, utc_offset_minutes: -new Date().getTimezoneOffset() }
+      : null
+    return invoke<ApiNotificationSettingsDto>('settings_set_notifications', {
+      payload: { ...settings, dnd }
+    })
+  }
+
+  const settingsGetAttachmentDenylist = () => {
+    ensureTauri()
+    return invoke<string[]>('settings_get_attachment_denylist')
+  }
+
+  const settingsSetAttachmentDenylist = (entries: string[]) => {
+    ensureTauri()
+    return invoke<string[]>('settings_set_attachment_denylist', { payload: { entries } })
+  }
+
+  const settingsSetAttachmentCacheBudget = (budgetBytes: number) => {
+    ensureTauri()
+    return invoke<ApiCacheStatsDto>('settings_set_attachment_cache_budget', {
+      payload: { budget_bytes: budgetBytes }
+    })
+  }
+
   return {
     authLogin,
     authMe,
     authLogout,
+    authAcceptInvite,
+    authPendingInvite,
+    authChangePassword,
+    authRequestReset,
+    authConfirmReset,
+    sessionsList,
+    sessionsRevoke,
+    sessionsRevokeOthers,
     channelsList,
     channelsCreate,
     channelsDelete,
+    channelsRename,
+    channelsSetTopic,
+    channelsArchive,
+    channelsUnarchive,
+    channelsMarkRead,
     channelMembersList,
     channelMembersAdd,
     channelMembersRemove,
     messagesList,
     messagesSend,
+    messagesSendWithAttachments,
     attachmentsUploadCommit,
+    attachmentsUploadBatch,
     messagesEdit,
     messagesDelete,
+    messagesSearch,
+    localSearch,
+    reactionsAdd,
+    reactionsRemove,
+    reactionsList,
     threadGet,
     threadRepliesList,
     threadReplySend,
@@ -250,12 +674,48 @@ export const useGalynxApi = () => {
     workspacesCreate,
     workspaceMembersList,
     workspaceMembersUpsert,
+    workspaceMembersSetRole,
+    workspaceMembersRemove,
+    usersDisable,
+    usersEnable,
+    invitesCreate,
+    invitesList,
+    invitesRevoke,
     usersList,
+    usersResolve,
+    mentionsSuggest,
+    markdownRender,
     usersCreate,
     auditList,
     attachmentGet,
+    attachmentResolveUrl,
+    attachmentPreview,
+    attachmentCachedUrl,
+    cacheStats,
+    cacheClear,
     settingsGetApiBase,
     settingsSetApiBase,
+    settingsGetNotifications,
+    settingsSetNotifications,
+    settingsGetAttachmentDenylist,
+    settingsSetAttachmentDenylist,
+    settingsSetAttachmentCacheBudget,
+    draftsSave,
+    draftsGet,
+    draftsList,
+    messagesSchedule,
+    messagesScheduledList,
+    messagesScheduleCancel,
+    pinsAdd,
+    pinsRemove,
+    pinsList,
+    savedItemsAdd,
+    savedItemsRemove,
+    savedItemsList,
+    dmOpen,
+    dmList,
+    permissionsForContext,
+    usersImportCsv,
     realtimeConnect,
     realtimeDisconnect
   }
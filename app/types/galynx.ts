@@ -101,6 +101,8 @@ export type ApiUserDto = {
   name: string
   workspace_id: string
   role: Role
+  avatar_url?: string | null
+  status?: UserStatus | null
 }
 
 export type AuthSessionDto = AuthTokensDto & {
@@ -114,6 +116,18 @@ export type ApiChannelDto = {
   is_private: boolean
   created_by: string
   created_at: number
+  unread_count?: number
+  mention_count?: number
+  last_read_at?: number | null
+  topic?: string | null
+  archived?: boolean
+}
+
+export type ApiChannelReadStateDto = {
+  last_read_message_id: string | null
+  last_read_at: number | null
+  unread_count: number
+  mention_count: number
 }
 
 export type ApiMessageDto = {
@@ -127,6 +141,51 @@ export type ApiMessageDto = {
   edited_at: number | null
   deleted_at: number | null
   attachments?: ApiAttachmentDto[]
+  reactions?: ApiReactionSummaryDto[]
+  sender?: ApiUserDto | null
+  body_html?: string | null
+}
+
+export type ApiReactionSummaryDto = {
+  emoji: string
+  count: number
+  me: boolean
+  user_ids: string[]
+}
+
+export type ApiMessageSearchFilters = {
+  channel_id?: string
+  sender_id?: string
+  from?: number
+  to?: number
+  has_attachment?: boolean
+  in_thread?: boolean
+}
+
+export type ApiHighlightRange = {
+  start: number
+  end: number
+}
+
+export type ApiMessageSearchHitDto = {
+  message: ApiMessageDto
+  highlights: ApiHighlightRange[]
+}
+
+export type ApiMessageSearchResultDto = {
+  items: ApiMessageSearchHitDto[]
+  next_cursor: string | null
+  source: 'server' | 'local'
+}
+
+export type ApiLocalSearchHitDto = {
+  message_id: string
+  channel_id: string
+  sender_id: string
+  thread_root_id: string | null
+  created_at: number
+  body_md: string
+  highlights: ApiHighlightRange[]
 }
 
 export type ApiMessageListDto = {
@@ -148,6 +207,50 @@ export type ApiAttachmentDto = {
   content_type: string | null
   storage_key: string | null
   download_url: string | null
+  width?: number | null
+  height?: number | null
+}
+
+export type ApiAttachmentPreviewDto = {
+  attachment_id: string
+  kind: 'image' | 'text' | 'pdf'
+  path: string
+  size_bytes: number
+  width: number | null
+  height: number | null
+  page_count: number | null
+}
+
+export type ApiAttachmentBatchItemDto = {
+  index: number
+  filename: string
+  attachment: ApiAttachmentDto | null
+  error: ApiError | null
+}
+
+export type ApiAttachmentBatchResultDto = {
+  batch_id: string
+  message_id: string
+  uploaded: number
+  failed: number
+  items: ApiAttachmentBatchItemDto[]
+}
+
+export type ApiAttachmentBatchProgressDto = {
+  batch_id: string
+  message_id: string
+  total: number
+  completed: number
+  failed: number
+  bytes_total: number
+  bytes_completed: number
+}
+
+export type ApiCacheStatsDto = {
+  entries: number
+  total_bytes: number
+  budget_bytes: number
+  dir: string
 }
 
 export type ApiWorkspaceDto = Record<string, unknown>
@@ -181,3 +284,147 @@ export type RealtimeEnvelope = {
   server_ts?: number
   payload?: Record<string, unknown>
 }
+
+export type ApiDndSchedule = {
+  start_minute: number
+  end_minute: number
+  utc_offset_minutes: number
+}
+
+export type ApiNotificationSettingsDto = {
+  enabled: boolean
+  mentions: boolean
+  private_channels: boolean
+  keywords: string[]
+  muted_channels: string[]
+  dnd: ApiDndSchedule | null
+}
+
+export type ApiMentionSuggestionDto = {
+  kind: 'user' | 'channel' | 'broadcast'
+  id: string
+  label: string
+  detail: string | null
+  insert_text: string
+}
+
+export type ApiDraftDto = {
+  channel_id: string
+  root_id: string | null
+  body_md: string
+  updated_at: number
+}
+
+export type ApiScheduledMessageDto = {
+  id: string
+  channel_id: string
+  body_md: string
+  send_at: number
+  created_at: number
+  attempts: number
+  last_error: string | null
+  next_attempt_at: number | null
+}
+
+export type ApiScheduledMessageSentDto = {
+  scheduled: ApiScheduledMessageDto
+  message: ApiMessageDto
+  late: boolean
+}
+
+export type ApiPinDto = {
+  message_id: string
+  channel_id: string
+  pinned_by: string | null
+  pinned_at: number
+  message: ApiMessageDto | null
+}
+
+export type ApiPinListDto = {
+  items: ApiPinDto[]
+  source: 'server' | 'local'
+}
+
+export type ApiSavedItemDto = {
+  message_id: string
+  channel_id: string
+  saved_at: number
+  message: ApiMessageDto
+  deleted: boolean
+}
+
+export type ApiDmConversationDto = {
+  channel: ApiChannelDto
+  participants: ApiUserDto[]
+  member_ids: string[]
+  title: string
+}
+
+export type ApiPermission =
+  | 'create_channels'
+  | 'invite_users'
+  | 'send_messages'
+  | 'edit_own_messages'
+  | 'delete_messages'
+  | 'delete_channels'
+  | 'manage_channels'
+  | 'manage_members'
+  | 'disable_users'
+
+export type ApiPermissionsDto = {
+  role: Role
+  allowed: ApiPermission[]
+}
+
+export type ApiImportRowResultDto = {
+  line: number
+  email: string | null
+  status: 'invalid' | 'ready' | 'imported' | 'failed'
+  action: 'create' | 'update' | null
+  user: ApiUserDto | null
+  temporary_password: string | null
+  error: ApiError | null
+}
+
+export type ApiImportReportDto = {
+  dry_run: boolean
+  applied: boolean
+  total: number
+  invalid: number
+  created: number
+  updated: number
+  failed: number
+  rows: ApiImportRowResultDto[]
+}
+
+export type ApiImportProgressDto = {
+  total: number
+  completed: number
+  failed: number
+}
+
+export type ApiInviteDto = {
+  id: string
+  workspace_id: string
+  email: string
+  role: Role
+  created_by: string
+  created_at: number
+  expires_at: number
+  accepted_at: number | null
+  revoked_at: number | null
+  token: string | null
+  link: string | null
+  status: 'pending' | 'accepted' | 'revoked' | 'expired'
+}
+
+export type ApiSessionDto = {
+  id: string
+  device_id: string | null
+  device_name: string | null
+  ip: string | null
+  user_agent: string | null
+  created_at: number
+  last_used_at: number | null
+  current: boolean
+}
@@ -0,0 +1,449 @@
+use crate::ApiError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tauri::{AppHandle, Manager};
+
+pub(crate) const ATTACHMENT_SCHEME: &str = "galynx-attachment";
+pub(crate) const DEFAULT_CACHE_BUDGET_BYTES: u64 = 512 * 1024 * 1024;
+const INDEX_FILE: &str = "index.json";
+
+pub(crate) type SharedAttachmentCache = Arc<Mutex<AttachmentCache>>;
+
+/// A body on disk, named by the SHA-256 of its content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BlobEntry {
+    size_bytes: u64,
+    last_used: u64,
+}
+
+/// Which body an attachment id resolves to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttachmentRef {
+    hash: String,
+    storage_key: Option<String>,
+    content_type: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheIndex {
+    #[serde(default)]
+    blobs: HashMap<String, BlobEntry>,
+    #[serde(default)]
+    attachments: HashMap<String, AttachmentRef>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CacheStatsDto {
+    entries: usize,
+    total_bytes: u64,
+    budget_bytes: u64,
+    dir: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CachedAttachment {
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) content_type: Option<String>,
+}
+
+/// Attachment bodies on disk, stored once per content hash and found through
+/// an attachment id index, so the same file forwarded to several channels is
+/// kept once and a corrupted body is noticed on read. Bodies are evicted least
+/// recently used first once `budget_bytes` is exceeded. Reads only bump the
+/// in-memory recency; the index is written on inserts and by `flush_index`.
+#[derive(Debug)]
+pub(crate) struct AttachmentCache {
+    dir: PathBuf,
+    budget_bytes: u64,
+    index: CacheIndex,
+    clock: u64,
+    index_dirty: bool,
+}
+
+pub(crate) fn cache_dir(app: &AppHandle) -> Result<PathBuf, ApiError> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("attachments"))
+        .map_err(|err| ApiError::Storage(format!("could not resolve cache dir: {err}")))
+}
+
+/// Attachment ids come from the API as UUIDs; anything else is refused so a
+/// request can never escape the cache dir.
+pub(crate) fn is_valid_key(attachment_id: &str) -> bool {
+    !attachment_id.is_empty()
+        && attachment_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn is_content_hash(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+impl AttachmentCache {
+    pub(crate) fn open(dir: PathBuf, budget_bytes: u64) -> Self {
+        let mut index: CacheIndex = fs::read(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_slice(&raw).ok())
+            .unwrap_or_default();
+        index
+            .blobs
+            .retain(|hash, _| is_content_hash(hash) && dir.join(hash).is_file());
+        let blobs = &index.blobs;
+        index
+            .attachments
+            .retain(|id, item| is_valid_key(id) && blobs.contains_key(&item.hash));
+
+        // Files the index does not know, e.g. bodies from an older layout.
+        if let Ok(files) = fs::read_dir(&dir) {
+            for file in files.flatten() {
+                let name = file.file_name();
+                let name = name.to_string_lossy();
+                if name != INDEX_FILE && !index.blobs.contains_key(name.as_ref()) {
+                    let _ = fs::remove_file(file.path());
+                }
+            }
+        }
+
+        let clock = index
+            .blobs
+            .values()
+            .map(|entry| entry.last_used)
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            dir,
+            budget_bytes,
+            index,
+            clock,
+            index_dirty: false,
+        }
+    }
+
+    pub(crate) fn shared(self) -> SharedAttachmentCache {
+        Arc::new(Mutex::new(self))
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.index
+            .blobs
+            .values()
+            .map(|entry| entry.size_bytes)
+            .sum()
+    }
+
+    pub(crate) fn stats(&self) -> CacheStatsDto {
+        CacheStatsDto {
+            entries: self.index.attachments.len(),
+            total_bytes: self.total_bytes(),
+            budget_bytes: self.budget_bytes,
+            dir: self.dir.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Returns the cached body, dropping it when the caller knows a different
+    /// `storage_key` than the one it was cached under, or when the file no
+    /// longer matches its hash.
+    pub(crate) fn get(
+        &mut self,
+        attachment_id: &str,
+        storage_key: Option<&str>,
+    ) -> Option<CachedAttachment> {
+        let item = self.index.attachments.get(attachment_id)?.clone();
+        if storage_key.is_some() && item.storage_key.as_deref() != storage_key {
+            self.remove_attachment(attachment_id);
+            return None;
+        }
+
+        let bytes = match fs::read(self.dir.join(&item.hash)) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                self.remove_blob(&item.hash);
+                return None;
+            }
+        };
+        if content_hash(&bytes) != item.hash {
+            log::warn!("dropping corrupted cached attachment {attachment_id}");
+            self.remove_blob(&item.hash);
+            return None;
+        }
+
+        let now = self.tick();
+        if let Some(entry) = self.index.blobs.get_mut(&item.hash) {
+            entry.last_used = now;
+        }
+        self.index_dirty = true;
+        Some(CachedAttachment {
+            bytes,
+            content_type: item.content_type,
+        })
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        attachment_id: &str,
+        storage_key: Option<String>,
+        content_type: Option<String>,
+        bytes: &[u8],
+    ) -> Result<(), ApiError> {
+        if !is_valid_key(attachment_id) {
+            return Err(ApiError::Storage(format!(
+                "invalid attachment cache key: {attachment_id}"
+            )));
+        }
+        let size_bytes = bytes.len() as u64;
+        if size_bytes > self.budget_bytes {
+            return Ok(());
+        }
+
+        let hash = content_hash(bytes);
+        let path = self.dir.join(&hash);
+        if !self.index.blobs.contains_key(&hash) || !path.is_file() {
+            fs::create_dir_all(&self.dir)
+                .map_err(|err| ApiError::Storage(format!("could not create cache dir: {err}")))?;
+            fs::write(&path, bytes).map_err(|err| {
+                ApiError::Storage(format!("could not write cached attachment: {err}"))
+            })?;
+        }
+
+        let last_used = self.tick();
+        self.index.blobs.insert(
+            hash.clone(),
+            BlobEntry {
+                size_bytes,
+                last_used,
+            },
+        );
+        let previous = self.index.attachments.insert(
+            attachment_id.to_string(),
+            AttachmentRef {
+                hash,
+                storage_key,
+                content_type,
+            },
+        );
+        if let Some(previous) = previous {
+            self.release_blob(&previous.hash);
+        }
+        self.evict();
+        self.save_index();
+        Ok(())
+    }
+
+    pub(crate) fn set_budget(&mut self, budget_bytes: u64) {
+        self.budget_bytes = budget_bytes;
+        self.evict();
+        self.save_index();
+    }
+
+    pub(crate) fn clear(&mut self) -> Result<(), ApiError> {
+        self.index = CacheIndex::default();
+        self.index_dirty = false;
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)
+                .map_err(|err| ApiError::Storage(format!("could not clear cache: {err}")))?;
+        }
+        Ok(())
+    }
+
+    fn remove_attachment(&mut self, attachment_id: &str) {
+        if let Some(item) = self.index.attachments.remove(attachment_id) {
+            self.index_dirty = true;
+            self.release_blob(&item.hash);
+        }
+    }
+
+    /// Deletes the body at `hash` once no attachment id points at it.
+    fn release_blob(&mut self, hash: &str) {
+        if !self
+            .index
+            .attachments
+            .values()
+            .any(|item| item.hash == hash)
+        {
+            self.remove_blob(hash);
+        }
+    }
+
+    /// Deletes the body at `hash` along with every attachment id using it.
+    fn remove_blob(&mut self, hash: &str) {
+        self.index.blobs.remove(hash);
+        self.index.attachments.retain(|_, item| item.hash != hash);
+        self.index_dirty = true;
+        let _ = fs::remove_file(self.dir.join(hash));
+    }
+
+    fn evict(&mut self) {
+        let mut total = self.total_bytes();
+        while total > self.budget_bytes {
+            let Some(oldest) = self
+                .index
+                .blobs
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(hash, _)| hash.clone())
+            else {
+                break;
+            };
+            total -= self.index.blobs[&oldest].size_bytes;
+            self.remove_blob(&oldest);
+        }
+    }
+
+    /// Writes the index if reads or removals changed it since the last write.
+    pub(crate) fn flush_index(&mut self) {
+        if self.index_dirty {
+            self.save_index();
+        }
+    }
+
+    fn save_index(&mut self) {
+        match write_index(&self.dir, &self.index) {
+            Ok(()) => self.index_dirty = false,
+            Err(err) => log::warn!("could not persist attachment cache index: {err}"),
+        }
+    }
+}
+
+fn write_index(dir: &Path, index: &CacheIndex) -> Result<(), ApiError> {
+    if index.blobs.is_empty() && !dir.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir)
+        .map_err(|err| ApiError::Storage(format!("could not create cache dir: {err}")))?;
+    let raw = serde_json::to_vec(index)
+        .map_err(|err| ApiError::Storage(format!("could not serialize cache index: {err}")))?;
+    fs::write(dir.join(INDEX_FILE), raw)
+        .map_err(|err| ApiError::Storage(format!("could not write cache index: {err}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str, budget_bytes: u64) -> AttachmentCache {
+        let dir = std::env::temp_dir().join(format!(
+            "galynx-attachment-cache-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        AttachmentCache::open(dir, budget_bytes)
+    }
+
+    #[test]
+    fn evicts_least_recently_used_over_budget() {
+        let mut cache = temp_cache("lru", 10);
+        cache.insert("a", None, None, b"aaaa").unwrap();
+        cache.insert("b", None, None, b"bbbb").unwrap();
+        assert!(cache.get("a", None).is_some());
+
+        cache.insert("c", None, None, b"cccc").unwrap();
+        assert!(cache.get("b", None).is_none());
+        assert!(cache.get("a", None).is_some());
+        assert!(cache.get("c", None).is_some());
+        assert_eq!(cache.stats().total_bytes, 8);
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn index_survives_reopen_and_storage_key_change_invalidates() {
+        let mut cache = temp_cache("reopen", 1024);
+        cache
+            .insert(
+                "att-1",
+                Some("ws/a.png".to_string()),
+                Some("image/png".to_string()),
+                b"png",
+            )
+            .unwrap();
+
+        let mut reopened = AttachmentCache::open(cache.dir.clone(), 1024);
+        let hit = reopened.get("att-1", Some("ws/a.png")).unwrap();
+        assert_eq!(hit.bytes, b"png");
+        assert_eq!(hit.content_type.as_deref(), Some("image/png"));
+
+        assert!(reopened.get("att-1", Some("ws/b.png")).is_none());
+        assert_eq!(reopened.stats().entries, 0);
+        reopened.clear().unwrap();
+    }
+
+    #[test]
+    fn reads_reach_the_index_only_when_flushed() {
+        let mut cache = temp_cache("flush", 10);
+        cache.insert("a", None, None, b"aaaa").unwrap();
+        cache.insert("b", None, None, b"bbbb").unwrap();
+        assert!(cache.get("a", None).is_some());
+
+        // Without a flush the reopened index still has `a` as the oldest.
+        let mut reopened = AttachmentCache::open(cache.dir.clone(), 10);
+        reopened.insert("c", None, None, b"cccc").unwrap();
+        assert!(reopened.get("a", None).is_none());
+        assert!(reopened.get("b", None).is_some());
+        reopened.clear().unwrap();
+
+        let mut cache = temp_cache("flush", 10);
+        cache.insert("a", None, None, b"aaaa").unwrap();
+        cache.insert("b", None, None, b"bbbb").unwrap();
+        assert!(cache.get("a", None).is_some());
+        cache.flush_index();
+        assert!(!cache.index_dirty);
+
+        let mut reopened = AttachmentCache::open(cache.dir.clone(), 10);
+        reopened.insert("c", None, None, b"cccc").unwrap();
+        assert!(reopened.get("b", None).is_none());
+        assert!(reopened.get("a", None).is_some());
+        reopened.clear().unwrap();
+    }
+
+    #[test]
+    fn shares_identical_bodies_and_drops_corrupted_ones() {
+        let mut cache = temp_cache("content", 1024);
+        cache.insert("att-1", None, None, b"same").unwrap();
+        cache.insert("att-2", None, None, b"same").unwrap();
+        assert_eq!(cache.stats().entries, 2);
+        assert_eq!(cache.stats().total_bytes, 4);
+
+        // Re-pointing one id keeps the body the other still uses.
+        cache.insert("att-1", None, None, b"other").unwrap();
+        assert_eq!(cache.get("att-2", None).unwrap().bytes, b"same");
+
+        fs::write(cache.dir.join(content_hash(b"same")), b"tampered").unwrap();
+        assert!(cache.get("att-2", None).is_none());
+        assert!(!cache.dir.join(content_hash(b"same")).exists());
+        assert_eq!(cache.get("att-1", None).unwrap().bytes, b"other");
+
+        fs::write(cache.dir.join("att-1"), b"old layout").unwrap();
+        let reopened = AttachmentCache::open(cache.dir.clone(), 1024);
+        assert!(!reopened.dir.join("att-1").exists());
+        assert_eq!(reopened.stats().entries, 1);
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn rejects_keys_outside_cache_dir_and_oversized_bodies() {
+        let mut cache = temp_cache("keys", 2);
+        assert!(cache.insert("../etc", None, None, b"x").is_err());
+        cache.insert("big", None, None, b"xyz").unwrap();
+        assert_eq!(cache.stats().entries, 0);
+        cache.clear().unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{dm::DM_CHANNEL_PREFIX, ApiError, ChannelDto};
+use serde_json::Value as JsonValue;
+
+pub(crate) const CHANNEL_NAME_MAX_CHARS: usize = 80;
+pub(crate) const CHANNEL_TOPIC_MAX_CHARS: usize = 250;
+
+/// Trims `name` and drops a leading `#` typed out of habit.
+pub(crate) fn normalize_name(name: &str) -> Result<String, ApiError> {
+    let name = name.trim().trim_start_matches('#').trim();
+    if name.is_empty() {
+        return Err(ApiError::validation(
+            "bad_request",
+            "channel name must not be empty",
+        ));
+    }
+    if name.chars().count() > CHANNEL_NAME_MAX_CHARS {
+        return Err(ApiError::validation(
+            "bad_request",
+            format!("channel name is longer than {CHANNEL_NAME_MAX_CHARS} characters"),
+        ));
+    }
+    // Conversations are private channels with this prefix; a hand-made one
+    // would show up as a direct message.
+    if name.to_lowercase().starts_with(DM_CHANNEL_PREFIX) {
+        return Err(ApiError::validation(
+            "reserved_channel_name",
+            format!("channel names starting with \"{DM_CHANNEL_PREFIX}\" are reserved"),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// A blank topic clears it.
+pub(crate) fn normalize_topic(topic: Option<&str>) -> Result<Option<String>, ApiError> {
+    let Some(topic) = topic.map(str::trim).filter(|topic| !topic.is_empty()) else {
+        return Ok(None);
+    };
+    if topic.chars().count() > CHANNEL_TOPIC_MAX_CHARS {
+        return Err(ApiError::validation(
+            "bad_request",
+            format!("channel topic is longer than {CHANNEL_TOPIC_MAX_CHARS} characters"),
+        ));
+    }
+    Ok(Some(topic.to_string()))
+}
+
+/// Archived channels only show up when asked for.
+pub(crate) fn visible(channels: Vec<ChannelDto>, include_archived: bool) -> Vec<ChannelDto> {
+    channels
+        .into_iter()
+        .filter(|channel| include_archived || !channel.archived)
+        .collect()
+}
+
+/// Archived channels are read-only; refuse before hitting the API.
+pub(crate) fn ensure_writable(channel: Option<&ChannelDto>) -> Result<(), ApiError> {
+    match channel {
+        Some(channel) if channel.archived => Err(ApiError::Http {
+            status: 403,
+            error: "channel_archived".to_string(),
+            message: format!("#{} is archived", channel.name),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The channel after a `CHANNEL_UPDATED`, `CHANNEL_ARCHIVED` or
+/// `CHANNEL_UNARCHIVED` event. Full channel payloads replace the cached copy;
+/// partial ones (`{ channel_id, name?, topic?, archived? }`) patch it.
+pub(crate) fn apply_event(
+    event_type: &str,
+    payload: &JsonValue,
+    cached: Option<&ChannelDto>,
+) -> Option<ChannelDto> {
+    let body = payload.get("channel").unwrap_or(payload);
+    let mut channel = match serde_json::from_value::<ChannelDto>(body.clone()) {
+        Ok(channel) => channel,
+        Err(_) => {
+            let mut channel = cached?.clone();
+            let channel_id = body
+                .get("channel_id")
+                .or_else(|| body.get("id"))
+                .and_then(JsonValue::as_str)?;
+            if channel.id != channel_id {
+                return None;
+            }
+            if let Some(name) = body.get("name").and_then(JsonValue::as_str) {
+                channel.name = name.to_string();
+            }
+            if let Some(topic) = body.get("topic") {
+                channel.topic = topic.as_str().map(ToString::to_string);
+            }
+            if let Some(archived) = body.get("archived").and_then(JsonValue::as_bool) {
+                channel.archived = archived;
+            }
+            channel
+        }
+    };
+    match event_type {
+        "CHANNEL_ARCHIVED" => channel.archived = true,
+        "CHANNEL_UNARCHIVED" => channel.archived = false,
+        _ => {}
+    }
+    Some(channel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn channel(archived: bool) -> ChannelDto {
+        ChannelDto {
+            id: "c-1".to_string(),
+            workspace_id: "ws".to_string(),
+            name: "general".to_string(),
+            is_private: false,
+            created_by: "u-1".to_string(),
+            created_at: 1,
+            unread_count: 0,
+            mention_count: 0,
+            last_read_at: None,
+            topic: None,
+            archived,
+        }
+    }
+
+    #[test]
+    fn normalizes_names_and_topics() {
+        assert_eq!(normalize_name("  #diseño ").unwrap(), "diseño");
+        assert!(normalize_name(" # ").is_err());
+        assert!(normalize_name("#DM-0123456789abcdef").is_err());
+        assert_eq!(normalize_name("dms").unwrap(), "dms");
+        assert!(normalize_name(&"x".repeat(CHANNEL_NAME_MAX_CHARS + 1)).is_err());
+        assert_eq!(normalize_topic(Some("  ")).unwrap(), None);
+        assert_eq!(
+            normalize_topic(Some(" Releases los jueves ")).unwrap(),
+            Some("Releases los jueves".to_string())
+        );
+        assert!(normalize_topic(Some(&"x".repeat(CHANNEL_TOPIC_MAX_CHARS + 1))).is_err());
+    }
+
+    #[test]
+    fn hides_archived_channels_and_refuses_writes() {
+        let channels = vec![channel(false), channel(true)];
+        assert_eq!(visible(channels.clone(), false).len(), 1);
+        assert_eq!(visible(channels, true).len(), 2);
+        assert!(ensure_writable(Some(&channel(false))).is_ok());
+        assert!(ensure_writable(None).is_ok());
+        assert!(matches!(
+            ensure_writable(Some(&channel(true))),
+            Err(ApiError::Http { status: 403, .. })
+        ));
+    }
+
+    #[test]
+    fn applies_full_and_partial_events() {
+        let cached = channel(false);
+        let patched = apply_event(
+            "CHANNEL_UPDATED",
+            &json!({ "channel_id": "c-1", "topic": "Deploys" }),
+            Some(&cached),
+        )
+        .unwrap();
+        assert_eq!(patched.topic.as_deref(), Some("Deploys"));
+        assert_eq!(patched.name, "general");
+
+        let archived = apply_event(
+            "CHANNEL_ARCHIVED",
+            &json!({ "channel_id": "c-1" }),
+            Some(&cached),
+        )
+        .unwrap();
+        assert!(archived.archived);
+
+        let mut full = serde_json::to_value(channel(true)).unwrap();
+        full["name"] = json!("general-old");
+        let replaced = apply_event("CHANNEL_UPDATED", &json!({ "channel": full }), None).unwrap();
+        assert_eq!(replaced.name, "general-old");
+        assert!(replaced.archived);
+
+        assert!(apply_event(
+            "CHANNEL_UPDATED",
+            &json!({ "channel_id": "c-2" }),
+            Some(&cached)
+        )
+        .is_none());
+    }
+}
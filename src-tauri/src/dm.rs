@@ -0,0 +1,211 @@
+use crate::{ChannelDto, UserDto};
+use serde::{Deserialize, Serialize};
+
+/// Private channels named with this prefix are direct conversations.
+pub(crate) const DM_CHANNEL_PREFIX: &str = "dm-";
+/// Member lists fetched at once when matching or listing conversations.
+pub(crate) const DM_MEMBER_FETCH_CONCURRENCY: usize = 6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct DmConversationDto {
+    pub(crate) channel: ChannelDto,
+    /// Members other than the current user; empty for a note-to-self.
+    pub(crate) participants: Vec<UserDto>,
+    pub(crate) member_ids: Vec<String>,
+    pub(crate) title: String,
+}
+
+/// Sorted, deduplicated member ids of a conversation, always including `me`.
+pub(crate) fn member_set(me: &str, user_ids: &[String]) -> Vec<String> {
+    let mut members: Vec<String> = user_ids
+        .iter()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect();
+    members.push(me.to_string());
+    members.sort();
+    members.dedup();
+    members
+}
+
+/// Stable channel name for a member set. Only a hint for other clients:
+/// anyone can create a channel with this name, so conversations are matched
+/// with `same_members`.
+pub(crate) fn channel_name(members: &[String]) -> String {
+    let mut h: u64 = 1469598103934665603;
+    for byte in members.join(",").bytes() {
+        h ^= u64::from(byte);
+        h = h.wrapping_mul(1099511628211);
+    }
+    format!("{DM_CHANNEL_PREFIX}{h:016x}")
+}
+
+/// Whether `channel` is a conversation: private and named exactly like
+/// `channel_name` output.
+pub(crate) fn is_dm_channel(channel: &ChannelDto) -> bool {
+    channel.is_private
+        && channel
+            .name
+            .strip_prefix(DM_CHANNEL_PREFIX)
+            .is_some_and(|hash| hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Whether a channel's members are exactly `members`, as built by
+/// `member_set`. A hidden member list never matches.
+pub(crate) fn same_members(member_ids: Option<&[String]>, members: &[String]) -> bool {
+    let Some(member_ids) = member_ids else {
+        return false;
+    };
+    let mut member_ids = member_ids.to_vec();
+    member_ids.sort();
+    member_ids.dedup();
+    member_ids == members
+}
+
+/// Builds the conversation view of `channel`. `member_ids` is `None` when the
+/// member list is hidden from the current user.
+pub(crate) fn conversation(
+    channel: ChannelDto,
+    member_ids: Option<Vec<String>>,
+    me: &str,
+    users: &[UserDto],
+) -> DmConversationDto {
+    let member_ids = member_ids.unwrap_or_default();
+    let participants: Vec<UserDto> = member_ids
+        .iter()
+        .filter(|id| id.as_str() != me)
+        .map(|id| {
+            users
+                .iter()
+                .find(|user| &user.id == id)
+                .cloned()
+                .unwrap_or_else(|| UserDto {
+                    id: id.clone(),
+                    email: String::new(),
+                    name: id.clone(),
+                    workspace_id: channel.workspace_id.clone(),
+                    role: String::new(),
+                    avatar_url: None,
+                    status: None,
+                })
+        })
+        .collect();
+
+    let title = if participants.is_empty() && member_ids.iter().any(|id| id == me) {
+        users
+            .iter()
+            .find(|user| user.id == me)
+            .map_or_else(|| me.to_string(), |user| user.name.clone())
+    } else if participants.is_empty() {
+        channel.name.clone()
+    } else {
+        let mut names: Vec<&str> = participants.iter().map(|user| user.name.as_str()).collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names.join(", ")
+    };
+
+    DmConversationDto {
+        channel,
+        participants,
+        member_ids,
+        title,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str, name: &str) -> UserDto {
+        UserDto {
+            id: id.to_string(),
+            email: format!("{id}@galynx.local"),
+            name: name.to_string(),
+            workspace_id: "ws".to_string(),
+            role: "member".to_string(),
+            avatar_url: None,
+            status: None,
+        }
+    }
+
+    fn channel(name: &str, is_private: bool) -> ChannelDto {
+        ChannelDto {
+            id: "c-1".to_string(),
+            workspace_id: "ws".to_string(),
+            name: name.to_string(),
+            is_private,
+            created_by: "me".to_string(),
+            created_at: 1,
+            unread_count: 0,
+            mention_count: 0,
+            last_read_at: None,
+            topic: None,
+            archived: false,
+        }
+    }
+
+    #[test]
+    fn member_set_is_order_independent() {
+        let a = member_set(
+            "me",
+            &["u2".to_string(), "u1".to_string(), "u2".to_string()],
+        );
+        let b = member_set(
+            "me",
+            &["u1".to_string(), " u2 ".to_string(), "me".to_string()],
+        );
+        assert_eq!(a, vec!["me", "u1", "u2"]);
+        assert_eq!(channel_name(&a), channel_name(&b));
+        assert_ne!(channel_name(&a), channel_name(&member_set("me", &[])));
+        assert!(is_dm_channel(&channel(&channel_name(&a), true)));
+        assert!(!is_dm_channel(&channel(&channel_name(&a), false)));
+        assert!(!is_dm_channel(&channel("dm-team-notes", true)));
+        assert!(!is_dm_channel(&channel("dm-0123456789abcdef0", true)));
+        assert!(!is_dm_channel(&channel("dm-0123456789abcdeg", true)));
+    }
+
+    #[test]
+    fn matches_conversations_by_members() {
+        let members = member_set("me", &["u1".to_string()]);
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert!(same_members(Some(&ids(&["u1", "me", "u1"])), &members));
+        assert!(!same_members(
+            Some(&ids(&["u1", "me", "intruder"])),
+            &members
+        ));
+        assert!(!same_members(Some(&ids(&["u1"])), &members));
+        assert!(!same_members(None, &members));
+    }
+
+    #[test]
+    fn titles_by_participant_names() {
+        let users = [user("me", "Lucía"), user("u1", "Bruno"), user("u2", "ana")];
+        let dm = conversation(
+            channel("dm-x", true),
+            Some(vec!["me".to_string(), "u1".to_string(), "u2".to_string()]),
+            "me",
+            &users,
+        );
+        assert_eq!(dm.title, "ana, Bruno");
+        assert_eq!(dm.participants.len(), 2);
+
+        let note = conversation(
+            channel("dm-y", true),
+            Some(vec!["me".to_string()]),
+            "me",
+            &users,
+        );
+        assert_eq!(note.title, "Lucía");
+
+        let hidden = conversation(channel("dm-z", true), None, "me", &users);
+        assert_eq!(hidden.title, "dm-z");
+
+        let unknown = conversation(
+            channel("dm-w", true),
+            Some(vec!["me".to_string(), "u9".to_string()]),
+            "me",
+            &users,
+        );
+        assert_eq!(unknown.title, "u9");
+    }
+}
@@ -0,0 +1,159 @@
+use crate::snapshot::Snapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct DraftDto {
+    pub(crate) channel_id: String,
+    pub(crate) root_id: Option<String>,
+    pub(crate) body_md: String,
+    pub(crate) updated_at: i64,
+}
+
+/// Unsent composer text per channel and per thread, kept per signed-in user
+/// like [`crate::read_state::ReadStateBook`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct DraftBook {
+    users: HashMap<String, HashMap<String, DraftDto>>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+fn draft_key(channel_id: &str, root_id: Option<&str>) -> String {
+    match root_id {
+        Some(root_id) => format!("thread:{root_id}"),
+        None => format!("channel:{channel_id}"),
+    }
+}
+
+impl DraftBook {
+    pub(crate) fn get(
+        &self,
+        user_id: &str,
+        channel_id: &str,
+        root_id: Option<&str>,
+    ) -> Option<DraftDto> {
+        self.users
+            .get(user_id)?
+            .get(&draft_key(channel_id, root_id))
+            .cloned()
+    }
+
+    /// Most recently edited first.
+    pub(crate) fn list(&self, user_id: &str) -> Vec<DraftDto> {
+        let mut drafts: Vec<DraftDto> = self
+            .users
+            .get(user_id)
+            .map(|drafts| drafts.values().cloned().collect())
+            .unwrap_or_default();
+        drafts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        drafts
+    }
+
+    /// Stores the draft, or drops it when `body_md` is only whitespace.
+    pub(crate) fn save(
+        &mut self,
+        user_id: &str,
+        channel_id: &str,
+        root_id: Option<&str>,
+        body_md: String,
+        updated_at: i64,
+    ) -> Option<DraftDto> {
+        let key = draft_key(channel_id, root_id);
+        if body_md.trim().is_empty() {
+            self.remove(user_id, &key);
+            return None;
+        }
+
+        let draft = DraftDto {
+            channel_id: channel_id.to_string(),
+            root_id: root_id.map(ToString::to_string),
+            body_md,
+            updated_at,
+        };
+        self.users
+            .entry(user_id.to_string())
+            .or_default()
+            .insert(key, draft.clone());
+        self.dirty = true;
+        Some(draft)
+    }
+
+    pub(crate) fn clear_channel(&mut self, user_id: &str, channel_id: &str) {
+        self.remove(user_id, &draft_key(channel_id, None));
+    }
+
+    pub(crate) fn clear_thread(&mut self, user_id: &str, root_id: &str) {
+        self.remove(user_id, &draft_key("", Some(root_id)));
+    }
+
+    fn remove(&mut self, user_id: &str, key: &str) {
+        let removed = self
+            .users
+            .get_mut(user_id)
+            .and_then(|drafts| drafts.remove(key))
+            .is_some();
+        if removed {
+            self.users.retain(|_, drafts| !drafts.is_empty());
+            self.dirty = true;
+        }
+    }
+}
+
+impl Snapshot for DraftBook {
+    fn dirty_flag(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+
+    #[test]
+    fn keeps_channel_and_thread_drafts_apart() {
+        let mut book = DraftBook::default();
+        book.save("me", "general", None, "hola".to_string(), 10);
+        book.save("me", "general", Some("m1"), "respuesta".to_string(), 20);
+
+        assert_eq!(book.get("me", "general", None).unwrap().body_md, "hola");
+        assert_eq!(
+            book.get("me", "general", Some("m1")).unwrap().body_md,
+            "respuesta"
+        );
+        assert!(book.get("other", "general", None).is_none());
+        let listed: Vec<_> = book
+            .list("me")
+            .into_iter()
+            .map(|draft| draft.updated_at)
+            .collect();
+        assert_eq!(listed, vec![20, 10]);
+
+        book.clear_thread("me", "m1");
+        assert!(book.get("me", "general", Some("m1")).is_none());
+        book.clear_channel("me", "general");
+        assert!(book.list("me").is_empty());
+    }
+
+    #[test]
+    fn blank_body_removes_draft() {
+        let mut book = DraftBook::default();
+        book.save("me", "general", None, "hola".to_string(), 10);
+        assert!(book
+            .save("me", "general", None, "  \n".to_string(), 11)
+            .is_none());
+        assert!(book.get("me", "general", None).is_none());
+    }
+
+    #[test]
+    fn snapshot_only_when_dirty_and_round_trips() {
+        let mut book = DraftBook::default();
+        let restored = snapshot::assert_round_trips(&mut book, |book| {
+            book.save("me", "general", None, "hola".to_string(), 42);
+        });
+        book.clear_channel("me", "missing");
+        assert!(book.take_snapshot().is_none());
+        assert_eq!(restored.get("me", "general", None).unwrap().updated_at, 42);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{roles::Role, ApiError};
+use serde::{Deserialize, Serialize};
+
+/// Scheme registered for the app; invite links look like
+/// `galynx://invite/<token>`.
+pub(crate) const DEEP_LINK_SCHEME: &str = "galynx";
+pub(crate) const INVITE_DEFAULT_EXPIRY_HOURS: u32 = 72;
+pub(crate) const INVITE_MAX_EXPIRY_HOURS: u32 = 30 * 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum InviteStatus {
+    Pending,
+    Accepted,
+    Revoked,
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct InviteDto {
+    pub(crate) id: String,
+    pub(crate) workspace_id: String,
+    pub(crate) email: String,
+    pub(crate) role: Role,
+    pub(crate) created_by: String,
+    pub(crate) created_at: i64,
+    pub(crate) expires_at: i64,
+    #[serde(default)]
+    pub(crate) accepted_at: Option<i64>,
+    #[serde(default)]
+    pub(crate) revoked_at: Option<i64>,
+    /// Only returned by the server when the invite is created.
+    #[serde(default)]
+    pub(crate) token: Option<String>,
+    /// Deep link built from `token`, ready to be shared.
+    #[serde(default)]
+    pub(crate) link: Option<String>,
+    #[serde(default = "pending")]
+    pub(crate) status: InviteStatus,
+}
+
+fn pending() -> InviteStatus {
+    InviteStatus::Pending
+}
+
+impl InviteDto {
+    /// Fills in `status` and `link` from the stored fields.
+    pub(crate) fn resolve(mut self, now_millis: i64) -> Self {
+        self.status = if self.revoked_at.is_some() {
+            InviteStatus::Revoked
+        } else if self.accepted_at.is_some() {
+            InviteStatus::Accepted
+        } else if self.expires_at <= now_millis {
+            InviteStatus::Expired
+        } else {
+            InviteStatus::Pending
+        };
+        self.link = self.token.as_deref().map(invite_link);
+        self
+    }
+}
+
+/// Owners are never invited; ownership is handed over from inside the
+/// workspace.
+pub(crate) fn invite_role(role: Option<&str>) -> Result<Role, ApiError> {
+    match role.map(Role::parse) {
+        None => Ok(Role::Member),
+        Some(Some(Role::Owner)) => Err(ApiError::validation(
+            "invalid_role",
+            "owners cannot be invited",
+        )),
+        Some(Some(role)) => Ok(role),
+        Some(None) => Err(ApiError::validation(
+            "invalid_role",
+            "role must be admin or member",
+        )),
+    }
+}
+
+/// `expires_in_hours` from now, defaulting to three days.
+pub(crate) fn expires_at(expires_in_hours: Option<u32>, now_millis: i64) -> Result<i64, ApiError> {
+    let hours = expires_in_hours.unwrap_or(INVITE_DEFAULT_EXPIRY_HOURS);
+    if hours == 0 || hours > INVITE_MAX_EXPIRY_HOURS {
+        return Err(ApiError::validation(
+            "bad_request",
+            format!("invites must expire within 1 and {INVITE_MAX_EXPIRY_HOURS} hours"),
+        ));
+    }
+    Ok(now_millis + i64::from(hours) * 3_600_000)
+}
+
+fn is_token(token: &str) -> bool {
+    !token.is_empty()
+        && token
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | '~'))
+}
+
+pub(crate) fn invite_link(token: &str) -> String {
+    format!("{DEEP_LINK_SCHEME}://invite/{token}")
+}
+
+/// The token of a `galynx://invite/<token>` link.
+pub(crate) fn parse_invite_link(link: &str) -> Option<String> {
+    let url = url::Url::parse(link.trim()).ok()?;
+    if url.scheme() != DEEP_LINK_SCHEME || url.host_str() != Some("invite") {
+        return None;
+    }
+    let token = url.path().trim_matches('/');
+    is_token(token).then(|| token.to_string())
+}
+
+/// Scheme and host of a deep link, the only part safe to log: the path may
+/// carry an invite token.
+pub(crate) fn link_origin(link: &str) -> String {
+    match url::Url::parse(link.trim()) {
+        Ok(url) => format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default()),
+        Err(_) => "an unparseable link".to_string(),
+    }
+}
+
+/// Accepts either an invite link or the bare token pasted into the form.
+pub(crate) fn parse_invite_token(input: &str) -> Option<String> {
+    let input = input.trim();
+    if is_token(input) {
+        return Some(input.to_string());
+    }
+    parse_invite_link(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invite() -> InviteDto {
+        InviteDto {
+            id: "i-1".to_string(),
+            workspace_id: "ws".to_string(),
+            email: "ana@galynx.local".to_string(),
+            role: Role::Member,
+            created_by: "o1".to_string(),
+            created_at: 1,
+            expires_at: 100,
+            accepted_at: None,
+            revoked_at: None,
+            token: Some("tok_123".to_string()),
+            link: None,
+            status: InviteStatus::Pending,
+        }
+    }
+
+    #[test]
+    fn parses_links_and_bare_tokens() {
+        assert_eq!(
+            parse_invite_token("galynx://invite/tok_123").as_deref(),
+            Some("tok_123")
+        );
+        assert_eq!(
+            parse_invite_token(" galynx://invite/tok_123/ ").as_deref(),
+            Some("tok_123")
+        );
+        assert_eq!(parse_invite_token("tok_123").as_deref(), Some("tok_123"));
+        assert_eq!(parse_invite_link("tok_123"), None);
+        assert_eq!(parse_invite_token("galynx://channel/tok_123"), None);
+        assert_eq!(parse_invite_token("https://invite/tok_123"), None);
+        assert_eq!(parse_invite_token("galynx://invite/"), None);
+        assert_eq!(parse_invite_token("galynx://invite/a/b"), None);
+        assert_eq!(
+            parse_invite_token(&invite_link("tok_123")).as_deref(),
+            Some("tok_123")
+        );
+    }
+
+    #[test]
+    fn link_origin_drops_the_token() {
+        assert_eq!(link_origin("galynx://invite/tok_123"), "galynx://invite");
+        assert_eq!(
+            link_origin("https://galynx.local/reset?token=abc"),
+            "https://galynx.local"
+        );
+        assert_eq!(link_origin("not a link tok_123"), "an unparseable link");
+    }
+
+    #[test]
+    fn resolves_status() {
+        assert_eq!(invite().resolve(50).status, InviteStatus::Pending);
+        assert_eq!(invite().resolve(100).status, InviteStatus::Expired);
+        let mut accepted = invite();
+        accepted.accepted_at = Some(60);
+        assert_eq!(accepted.resolve(200).status, InviteStatus::Accepted);
+        let mut revoked = invite();
+        revoked.revoked_at = Some(60);
+        revoked.accepted_at = Some(70);
+        assert_eq!(revoked.resolve(50).status, InviteStatus::Revoked);
+        assert_eq!(
+            invite().resolve(50).link.as_deref(),
+            Some("galynx://invite/tok_123")
+        );
+    }
+
+    #[test]
+    fn validates_role_and_expiry() {
+        assert_eq!(invite_role(None).unwrap(), Role::Member);
+        assert_eq!(invite_role(Some("Admin")).unwrap(), Role::Admin);
+        assert!(invite_role(Some("owner")).is_err());
+        assert!(invite_role(Some("guest")).is_err());
+        assert_eq!(expires_at(None, 0).unwrap(), 72 * 3_600_000);
+        assert!(expires_at(Some(0), 0).is_err());
+        assert!(expires_at(Some(INVITE_MAX_EXPIRY_HOURS + 1), 0).is_err());
+    }
+}
@@ -1,24 +1,77 @@
-use futures_util::StreamExt;
+use futures_util::{SinkExt, StreamExt};
 use reqwest::{Method, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
+use snapshot::Snapshot;
 use std::{
-    collections::HashMap,
-    sync::{Arc, OnceLock},
-    time::Duration,
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
 };
 use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_notification::NotificationExt;
 use tauri_plugin_store::StoreExt;
 use thiserror::Error;
-use tokio::sync::{oneshot, Mutex, RwLock};
-use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+
+mod attachment_cache;
+mod channels;
+mod dm;
+mod drafts;
+mod invites;
+mod markdown;
+mod mentions;
+mod message_cache;
+mod notifications;
+mod passwords;
+mod permissions;
+mod pins;
+mod previews;
+mod reactions;
+mod read_state;
+mod roles;
+mod saved_items;
+mod scheduled;
+mod search;
+mod search_index;
+mod sessions;
+mod snapshot;
+mod upload_validation;
+mod user_directory;
+mod user_import;
 
 const DEFAULT_API_BASE: &str = "http://localhost:3000/api/v1";
 const TOKEN_STORE_FILE: &str = "secure-tokens.bin";
 const TOKEN_STORE_KEY: &str = "auth_tokens";
 const API_BASE_STORE_KEY: &str = "api_base";
+const ATTACHMENT_DENYLIST_STORE_KEY: &str = "attachment_denylist";
+const ATTACHMENT_CACHE_BUDGET_STORE_KEY: &str = "attachment_cache_budget";
+const READ_STATE_STORE_KEY: &str = "read_state";
+const NOTIFICATION_SETTINGS_STORE_KEY: &str = "notification_settings";
+const DRAFTS_STORE_KEY: &str = "drafts";
+const SCHEDULED_MESSAGES_STORE_KEY: &str = "scheduled_messages";
+const LOCAL_PINS_STORE_KEY: &str = "local_pins";
+const SAVED_ITEMS_STORE_KEY: &str = "saved_items";
+const DEVICE_STORE_KEY: &str = "device";
 const ENCRYPTION_KEY_FALLBACK: &[u8] = b"galynx-desktop-store-v1";
+const DOWNLOAD_URL_TTL: Duration = Duration::from_secs(600);
+const DOWNLOAD_URL_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+const BATCH_UPLOAD_DEFAULT_CONCURRENCY: usize = 3;
+const BATCH_UPLOAD_MAX_CONCURRENCY: usize = 6;
+const WS_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+const LOCAL_STATE_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+const SCHEDULED_MESSAGES_POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// Pause before retrying an import row that is still rate limited after
+/// `send_json`'s own backoff; grows with each attempt.
+const USER_IMPORT_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(2);
+const USER_IMPORT_RATE_LIMIT_RETRIES: u32 = 3;
 static ENCRYPTION_KEY_BYTES: OnceLock<Vec<u8>> = OnceLock::new();
+static CLIENT_MSG_SEQ: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct TokenBundle {
@@ -35,6 +88,10 @@ struct UserDto {
     name: String,
     workspace_id: String,
     role: String,
+    #[serde(default)]
+    avatar_url: Option<String>,
+    #[serde(default)]
+    status: Option<roles::UserStatus>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +111,16 @@ struct ChannelDto {
     is_private: bool,
     created_by: String,
     created_at: i64,
+    #[serde(default)]
+    unread_count: u32,
+    #[serde(default)]
+    mention_count: u32,
+    #[serde(default)]
+    last_read_at: Option<i64>,
+    #[serde(default)]
+    topic: Option<String>,
+    #[serde(default)]
+    archived: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,6 +136,16 @@ struct MessageDto {
     deleted_at: Option<i64>,
     #[serde(default)]
     attachments: Vec<AttachmentDto>,
+    /// `None` when the server left the field out, which is not the same as
+    /// a message without reactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reactions: Option<Vec<reactions::ReactionSummaryDto>>,
+    #[serde(default)]
+    sender: Option<UserDto>,
+    /// Only ever produced by `render_bodies`; a value sent by the server or
+    /// read back from the store is dropped.
+    #[serde(skip_deserializing)]
+    body_html: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +167,50 @@ struct LoginPayload {
     email: String,
     password: String,
     workspace_id: Option<String>,
+    /// Renames this device for the sessions list; kept for later logins.
+    device_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AcceptInvitePayload {
+    /// The token or the whole `galynx://invite/<token>` link.
+    token: String,
+    name: Option<String>,
+    password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InviteCreatePayload {
+    email: String,
+    role: Option<String>,
+    expires_in_hours: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InviteIdPayload {
+    invite_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChangePasswordPayload {
+    current_password: String,
+    new_password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RequestResetPayload {
+    email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ConfirmResetPayload {
+    token: String,
+    new_password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionIdPayload {
+    session_id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -104,6 +225,14 @@ struct SendMessagePayload {
     body_md: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SendMessageWithAttachmentsPayload {
+    channel_id: String,
+    body_md: String,
+    files: Vec<AttachmentFilePayload>,
+    concurrency: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct EditMessagePayload {
     message_id: String,
@@ -126,6 +255,61 @@ struct AttachmentUploadPayload {
     bytes: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttachmentFilePayload {
+    filename: String,
+    content_type: String,
+    size_bytes: i64,
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttachmentBatchUploadPayload {
+    channel_id: String,
+    message_id: String,
+    files: Vec<AttachmentFilePayload>,
+    batch_id: Option<String>,
+    concurrency: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AttachmentBatchItemDto {
+    index: usize,
+    filename: String,
+    attachment: Option<AttachmentDto>,
+    error: Option<ApiErrorDto>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AttachmentBatchResultDto {
+    batch_id: String,
+    message_id: String,
+    uploaded: usize,
+    failed: usize,
+    items: Vec<AttachmentBatchItemDto>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AttachmentBatchProgressDto {
+    batch_id: String,
+    message_id: String,
+    total: usize,
+    completed: usize,
+    failed: usize,
+    bytes_total: i64,
+    bytes_completed: i64,
+}
+
+#[derive(Debug)]
+struct StagedUpload {
+    upload_id: String,
+    key: Option<String>,
+    filename: String,
+    size_bytes: i64,
+    content_type: String,
+    preview: Option<previews::GeneratedPreview>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct AttachmentPresignResponse {
     upload_id: String,
@@ -141,6 +325,10 @@ struct AttachmentDto {
     content_type: Option<String>,
     storage_key: Option<String>,
     download_url: Option<String>,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -162,6 +350,24 @@ struct DeleteChannelPayload {
     channel_id: String,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ChannelsListPayload {
+    #[serde(default)]
+    include_archived: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChannelRenamePayload {
+    channel_id: String,
+    name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChannelTopicPayload {
+    channel_id: String,
+    topic: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct DeleteMessagePayload {
     message_id: String,
@@ -191,6 +397,30 @@ struct WorkspaceMembersUpsertPayload {
     role: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorkspaceMemberRolePayload {
+    workspace_id: String,
+    user_id: String,
+    role: roles::Role,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorkspaceMemberPayload {
+    workspace_id: String,
+    user_id: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PermissionsContextPayload {
+    channel_id: Option<String>,
+    message_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UserIdPayload {
+    user_id: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct UsersCreatePayload {
     email: String,
@@ -199,12 +429,87 @@ struct UsersCreatePayload {
     role: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UsersImportCsvPayload {
+    csv: String,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UsersResolvePayload {
+    user_ids: Vec<String>,
+    workspace_id: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ChannelMemberPayload {
     channel_id: String,
     user_id: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChannelMarkReadPayload {
+    channel_id: String,
+    message_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DraftSavePayload {
+    channel_id: String,
+    root_id: Option<String>,
+    body_md: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DraftGetPayload {
+    channel_id: String,
+    root_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MessagesSchedulePayload {
+    channel_id: String,
+    body_md: String,
+    send_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScheduledMessageIdPayload {
+    id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScheduledMessageSentDto {
+    scheduled: scheduled::ScheduledMessageDto,
+    message: MessageDto,
+    /// The message was due while the app was closed and went out on launch.
+    late: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DmOpenPayload {
+    user_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PinPayload {
+    channel_id: String,
+    message_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MarkdownRenderPayload {
+    body_md: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MentionsSuggestPayload {
+    channel_id: Option<String>,
+    prefix: String,
+    limit: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ChannelIdPayload {
     channel_id: String,
@@ -221,11 +526,54 @@ struct ThreadGetPayload {
     root_id: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MessageSearchPayload {
+    query: String,
+    #[serde(default)]
+    filters: search::MessageSearchFilters,
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LocalSearchPayload {
+    query: String,
+    channel_id: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MessageIdPayload {
+    message_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReactionPayload {
+    message_id: String,
+    emoji: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttachmentResolveUrlPayload {
+    attachment_id: String,
+    force_refresh: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ApiBasePayload {
     api_base: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttachmentDenylistPayload {
+    entries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttachmentCacheBudgetPayload {
+    budget_bytes: u64,
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct ApiErrorDto {
     status: u16,
@@ -251,6 +599,26 @@ enum ApiError {
     Storage(String),
     #[error("realtime error: {0}")]
     Realtime(String),
+    #[error("attachment is {size_bytes} bytes, the limit is {max_bytes} bytes")]
+    AttachmentTooLarge { size_bytes: i64, max_bytes: i64 },
+    #[error("attachment declared {declared} bytes but contains {actual} bytes")]
+    AttachmentSizeMismatch { declared: i64, actual: i64 },
+    #[error("attachment declared as {declared} but its content is {detected}")]
+    AttachmentTypeMismatch { declared: String, detected: String },
+    #[error("attachments of type {0} are not allowed")]
+    AttachmentBlocked(String),
+    /// Input rejected locally, before any request was made.
+    #[error("{message}")]
+    Validation { code: &'static str, message: String },
+}
+
+impl ApiError {
+    fn validation(code: &'static str, message: impl Into<String>) -> Self {
+        Self::Validation {
+            code,
+            message: message.into(),
+        }
+    }
 }
 
 impl From<ApiError> for ApiErrorDto {
@@ -270,6 +638,31 @@ impl From<ApiError> for ApiErrorDto {
                 error: "unauthorized".to_string(),
                 message: "You must sign in again.".to_string(),
             },
+            ApiError::AttachmentTooLarge { .. } => Self {
+                status: 413,
+                error: "attachment_too_large".to_string(),
+                message: value.to_string(),
+            },
+            ApiError::AttachmentSizeMismatch { .. } => Self {
+                status: 400,
+                error: "attachment_size_mismatch".to_string(),
+                message: value.to_string(),
+            },
+            ApiError::AttachmentTypeMismatch { .. } => Self {
+                status: 400,
+                error: "attachment_type_mismatch".to_string(),
+                message: value.to_string(),
+            },
+            ApiError::AttachmentBlocked(_) => Self {
+                status: 415,
+                error: "attachment_type_blocked".to_string(),
+                message: value.to_string(),
+            },
+            ApiError::Validation { code, message } => Self {
+                status: 400,
+                error: code.to_string(),
+                message,
+            },
             other => Self {
                 status: 500,
                 error: "internal_error".to_string(),
@@ -280,6 +673,22 @@ impl From<ApiError> for ApiErrorDto {
 }
 
 type CmdResult<T> = Result<T, ApiErrorDto>;
+type PendingWsCommand = oneshot::Sender<Result<JsonValue, ApiError>>;
+/// Member ids per channel and when they were fetched; `None` when hidden.
+type CachedChannelMembers = (Option<Vec<String>>, Instant);
+
+#[derive(Debug, Clone)]
+struct IssuedDownloadUrl {
+    url: String,
+    issued_at: Instant,
+}
+
+impl IssuedDownloadUrl {
+    fn is_fresh(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.issued_at) + DOWNLOAD_URL_REFRESH_MARGIN
+            < DOWNLOAD_URL_TTL
+    }
+}
 
 #[derive(Clone)]
 struct AppState {
@@ -289,6 +698,30 @@ struct AppState {
     tokens: Arc<RwLock<Option<TokenBundle>>>,
     refresh_lock: Arc<Mutex<()>>,
     ws_shutdown: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    download_urls: Arc<RwLock<HashMap<String, IssuedDownloadUrl>>>,
+    attachment_denylist: Arc<RwLock<Vec<String>>>,
+    attachment_cache: attachment_cache::SharedAttachmentCache,
+    current_user: Arc<RwLock<Option<UserDto>>>,
+    message_cache: Arc<RwLock<message_cache::MessageCache>>,
+    search_index: Arc<RwLock<search_index::SearchIndex>>,
+    user_directory: Arc<RwLock<user_directory::UserDirectory>>,
+    /// Set while a background directory refresh started by the realtime loop
+    /// is running.
+    user_directory_backfill: Arc<AtomicBool>,
+    read_state: Arc<RwLock<read_state::ReadStateBook>>,
+    read_sync_supported: Arc<AtomicBool>,
+    drafts: Arc<RwLock<drafts::DraftBook>>,
+    scheduled_messages: Arc<RwLock<scheduled::ScheduledQueue>>,
+    local_pins: Arc<RwLock<pins::LocalPinBook>>,
+    saved_items: Arc<RwLock<saved_items::SavedItemBook>>,
+    channels: Arc<RwLock<HashMap<String, ChannelDto>>>,
+    channel_members: Arc<RwLock<HashMap<String, CachedChannelMembers>>>,
+    notification_settings: Arc<RwLock<notifications::NotificationSettingsDto>>,
+    device: Arc<RwLock<sessions::DeviceDto>>,
+    /// Invite token from a deep link the frontend has not picked up yet.
+    pending_invite: Arc<Mutex<Option<String>>>,
+    ws_outbox: Arc<Mutex<Option<mpsc::UnboundedSender<String>>>>,
+    ws_pending: Arc<Mutex<HashMap<String, PendingWsCommand>>>,
 }
 
 fn normalize_api_base(value: &str) -> Option<String> {
@@ -417,22 +850,171 @@ fn map_attachment_commit_response(
         content_type,
         storage_key,
         download_url,
+        width: None,
+        height: None,
     }
 }
 
-fn load_api_base_from_store_for_app(app: &AppHandle) -> Option<String> {
-    let store = app
-        .store_builder(TOKEN_STORE_FILE)
+fn map_attachment_get_response(value: JsonValue, attachment_id: String) -> AttachmentDto {
+    let name = value
+        .get("filename")
+        .or_else(|| value.get("name"))
+        .and_then(JsonValue::as_str)
+        .unwrap_or("file")
+        .to_string();
+    let size_bytes = value
+        .get("size_bytes")
+        .or_else(|| value.get("size"))
+        .and_then(JsonValue::as_i64)
+        .unwrap_or(0);
+    let content_type = value
+        .get("content_type")
+        .and_then(JsonValue::as_str)
+        .map(ToString::to_string);
+    let storage_key = value
+        .get("storage_key")
+        .or_else(|| value.get("key"))
+        .and_then(JsonValue::as_str)
+        .map(ToString::to_string);
+    let download_url = value
+        .get("download_url")
+        .and_then(JsonValue::as_str)
+        .map(ToString::to_string);
+
+    AttachmentDto {
+        id: attachment_id,
+        name,
+        size_bytes,
+        content_type,
+        storage_key,
+        download_url,
+        width: None,
+        height: None,
+    }
+}
+
+fn open_secure_store(
+    app: &AppHandle,
+) -> Result<Arc<tauri_plugin_store::Store<tauri::Wry>>, tauri_plugin_store::Error> {
+    app.store_builder(TOKEN_STORE_FILE)
         .serialize(serialize_encrypted)
         .deserialize(deserialize_encrypted)
         .build()
-        .ok()?;
+}
+
+fn load_api_base_from_store_for_app(app: &AppHandle) -> Option<String> {
+    let store = open_secure_store(app).ok()?;
     let raw = store
         .get(API_BASE_STORE_KEY)
         .and_then(|value| value.as_str().map(ToString::to_string))?;
     normalize_api_base(&raw)
 }
 
+/// The search index is message text, so it is kept encrypted like the store.
+/// A plaintext index left by an earlier version is deleted instead of read.
+fn load_search_index_for_app(app: &AppHandle) -> Result<search_index::SearchIndex, ApiError> {
+    if let Ok(legacy) = search_index::legacy_index_path(app) {
+        let _ = std::fs::remove_file(legacy);
+    }
+    let raw = std::fs::read(search_index::index_path(app)?)
+        .ok()
+        .map(|raw| xor_crypt(&raw));
+    Ok(search_index::SearchIndex::from_snapshot(
+        raw.as_deref(),
+        search_index::SEARCH_INDEX_CAPACITY,
+    ))
+}
+
+fn load_attachment_cache_budget_from_store_for_app(app: &AppHandle) -> Option<u64> {
+    let store = open_secure_store(app).ok()?;
+    store.get(ATTACHMENT_CACHE_BUDGET_STORE_KEY)?.as_u64()
+}
+
+fn load_attachment_denylist_from_store_for_app(app: &AppHandle) -> Option<Vec<String>> {
+    let store = open_secure_store(app).ok()?;
+    let entries = serde_json::from_value(store.get(ATTACHMENT_DENYLIST_STORE_KEY)?).ok()?;
+    Some(upload_validation::normalize_denylist(entries))
+}
+
+fn load_read_state_from_store_for_app(app: &AppHandle) -> Option<read_state::ReadStateBook> {
+    let store = open_secure_store(app).ok()?;
+    serde_json::from_value(store.get(READ_STATE_STORE_KEY)?).ok()
+}
+
+fn load_drafts_from_store_for_app(app: &AppHandle) -> Option<drafts::DraftBook> {
+    let store = open_secure_store(app).ok()?;
+    serde_json::from_value(store.get(DRAFTS_STORE_KEY)?).ok()
+}
+
+fn load_scheduled_messages_from_store_for_app(
+    app: &AppHandle,
+) -> Option<scheduled::ScheduledQueue> {
+    let store = open_secure_store(app).ok()?;
+    serde_json::from_value(store.get(SCHEDULED_MESSAGES_STORE_KEY)?).ok()
+}
+
+fn load_local_pins_from_store_for_app(app: &AppHandle) -> Option<pins::LocalPinBook> {
+    let store = open_secure_store(app).ok()?;
+    serde_json::from_value(store.get(LOCAL_PINS_STORE_KEY)?).ok()
+}
+
+fn load_saved_items_from_store_for_app(app: &AppHandle) -> Option<saved_items::SavedItemBook> {
+    let store = open_secure_store(app).ok()?;
+    serde_json::from_value(store.get(SAVED_ITEMS_STORE_KEY)?).ok()
+}
+
+/// The identity this install logs in with, created on first launch.
+fn load_or_create_device_for_app(app: &AppHandle) -> Result<sessions::DeviceDto, ApiError> {
+    let store = open_secure_store(app).map_err(|err| ApiError::Storage(err.to_string()))?;
+    if let Some(device) = store
+        .get(DEVICE_STORE_KEY)
+        .and_then(|value| serde_json::from_value(value).ok())
+    {
+        return Ok(device);
+    }
+    let device = sessions::DeviceDto::generate()?;
+    store.set(DEVICE_STORE_KEY, json!(device));
+    if let Err(err) = store.save() {
+        log::warn!("could not persist device id: {err}");
+    }
+    Ok(device)
+}
+
+fn load_notification_settings_from_store_for_app(
+    app: &AppHandle,
+) -> Option<notifications::NotificationSettingsDto> {
+    let store = open_secure_store(app).ok()?;
+    serde_json::from_value(store.get(NOTIFICATION_SETTINGS_STORE_KEY)?).ok()
+}
+
+fn render_message_bodies<'a>(
+    users: &[UserDto],
+    channels: &[ChannelDto],
+    messages: impl IntoIterator<Item = &'a mut MessageDto>,
+) {
+    let context = markdown::MentionContext { users, channels };
+    for message in messages {
+        message.body_html = Some(markdown::render(&message.body_md, context));
+    }
+}
+
+/// Puts the cached, rendered copy of a message back into the realtime
+/// envelope forwarded to the webview, so it never shows a `body_html` it did
+/// not get from `render_bodies`.
+fn replace_event_message(event: &mut JsonValue, message: &MessageDto) {
+    let Some(payload) = event.get_mut("payload") else {
+        return;
+    };
+    let slot = if payload.get("message").is_some() {
+        &mut payload["message"]
+    } else {
+        payload
+    };
+    if let Ok(value) = serde_json::to_value(message) {
+        *slot = value;
+    }
+}
+
 impl AppState {
     async fn current_api_base(&self) -> String {
         self.api_base.read().await.clone()
@@ -443,12 +1025,7 @@ impl AppState {
     }
 
     fn get_secure_store(&self) -> Result<Arc<tauri_plugin_store::Store<tauri::Wry>>, ApiError> {
-        self.app
-            .store_builder(TOKEN_STORE_FILE)
-            .serialize(serialize_encrypted)
-            .deserialize(deserialize_encrypted)
-            .build()
-            .map_err(|err| ApiError::Storage(err.to_string()))
+        open_secure_store(&self.app).map_err(|err| ApiError::Storage(err.to_string()))
     }
 
     async fn persist_api_base(&self, api_base: &str) -> Result<(), ApiError> {
@@ -468,6 +1045,47 @@ impl AppState {
         Ok(normalized)
     }
 
+    async fn set_attachment_denylist(&self, entries: Vec<String>) -> Result<Vec<String>, ApiError> {
+        let normalized = upload_validation::normalize_denylist(entries);
+        let store = self.get_secure_store()?;
+        store.set(ATTACHMENT_DENYLIST_STORE_KEY, json!(normalized));
+        store.save().map_err(|err| {
+            ApiError::Storage(format!("could not save attachment denylist: {err}"))
+        })?;
+        *self.attachment_denylist.write().await = normalized.clone();
+        Ok(normalized)
+    }
+
+    async fn set_notification_settings(
+        &self,
+        settings: notifications::NotificationSettingsDto,
+    ) -> Result<notifications::NotificationSettingsDto, ApiError> {
+        let normalized = settings.normalized();
+        let store = self.get_secure_store()?;
+        store.set(NOTIFICATION_SETTINGS_STORE_KEY, json!(normalized));
+        store.save().map_err(|err| {
+            ApiError::Storage(format!("could not save notification settings: {err}"))
+        })?;
+        *self.notification_settings.write().await = normalized.clone();
+        Ok(normalized)
+    }
+
+    async fn set_attachment_cache_budget(
+        &self,
+        budget_bytes: u64,
+    ) -> Result<attachment_cache::CacheStatsDto, ApiError> {
+        let store = self.get_secure_store()?;
+        store.set(ATTACHMENT_CACHE_BUDGET_STORE_KEY, json!(budget_bytes));
+        store.save().map_err(|err| {
+            ApiError::Storage(format!("could not save attachment cache budget: {err}"))
+        })?;
+        self.with_attachment_cache(move |cache| {
+            cache.set_budget(budget_bytes);
+            Ok(cache.stats())
+        })
+        .await
+    }
+
     async fn load_tokens_from_store(&self) -> Result<Option<TokenBundle>, ApiError> {
         let store = self.get_secure_store()?;
 
@@ -494,6 +1112,284 @@ impl AppState {
         Ok(())
     }
 
+    /// Stores the tokens of a fresh login and loads the signed-in user.
+    async fn start_session(&self, tokens_value: JsonValue) -> Result<AuthSessionDto, ApiError> {
+        let tokens: TokenBundle = serde_json::from_value(tokens_value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+
+        self.persist_tokens(&tokens).await?;
+        *self.tokens.write().await = Some(tokens.clone());
+
+        let me_value = self.send_json(Method::GET, "/me", None, true).await?;
+        let user: UserDto = serde_json::from_value(me_value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        *self.current_user.write().await = Some(user.clone());
+
+        Ok(AuthSessionDto {
+            access_token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+            access_expires_at: tokens.access_expires_at,
+            refresh_expires_at: tokens.refresh_expires_at,
+            user,
+        })
+    }
+
+    async fn create_invite(
+        &self,
+        payload: InviteCreatePayload,
+    ) -> Result<invites::InviteDto, ApiError> {
+        let email = payload.email.trim().to_lowercase();
+        if !user_import::is_valid_email(&email) {
+            return Err(ApiError::validation(
+                "invalid_email",
+                "email is not a valid address",
+            ));
+        }
+        let role = invites::invite_role(payload.role.as_deref())?;
+        let now = now_millis();
+        let expires_at = invites::expires_at(payload.expires_in_hours, now)?;
+
+        let value = self
+            .send_json(
+                Method::POST,
+                "/invites",
+                Some(json!({
+                    "email": email,
+                    "role": role,
+                    "expires_at": expires_at
+                })),
+                true,
+            )
+            .await?;
+        let invite: invites::InviteDto = serde_json::from_value(value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        Ok(invite.resolve(now))
+    }
+
+    /// Every invite of the current workspace, newest first.
+    async fn list_invites(&self) -> Result<Vec<invites::InviteDto>, ApiError> {
+        let value = self.send_json(Method::GET, "/invites", None, true).await?;
+        let invites: Vec<invites::InviteDto> = serde_json::from_value(value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        let now = now_millis();
+        let mut invites: Vec<_> = invites
+            .into_iter()
+            .map(|invite| invite.resolve(now))
+            .collect();
+        invites.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(invites)
+    }
+
+    async fn revoke_invite(&self, invite_id: &str) -> Result<(), ApiError> {
+        self.send_json(Method::DELETE, &format!("/invites/{invite_id}"), None, true)
+            .await?;
+        Ok(())
+    }
+
+    /// Redeems an invite with a password chosen by the invitee and signs
+    /// them in.
+    async fn accept_invite(
+        &self,
+        payload: AcceptInvitePayload,
+    ) -> Result<AuthSessionDto, ApiError> {
+        let token = invites::parse_invite_token(&payload.token).ok_or_else(|| {
+            ApiError::validation("invalid_invite_token", "the invite link is not valid")
+        })?;
+        passwords::check_strength(&payload.password, &[payload.name.as_deref().unwrap_or("")])?;
+        let device = self.device_for_login(None).await;
+        let mut body = json!({
+            "token": token,
+            "password": payload.password,
+            "device_id": device.device_id,
+            "device_name": device.device_name
+        });
+        if let Some(name) = payload.name.map(|name| name.trim().to_string()) {
+            if !name.is_empty() {
+                body["name"] = JsonValue::String(name);
+            }
+        }
+
+        let tokens_value = self
+            .send_json(Method::POST, "/auth/invites/accept", Some(body), false)
+            .await?;
+        self.pending_invite.lock().await.take();
+        self.start_session(tokens_value).await
+    }
+
+    /// Routes a `galynx://` link opened from outside the app. Invite links
+    /// are kept until the frontend asks for them and announced right away
+    /// for a frontend that is already running.
+    async fn open_deep_link(&self, url: &str) {
+        let Some(token) = invites::parse_invite_link(url) else {
+            log::info!(
+                "ignoring unsupported deep link to {}",
+                invites::link_origin(url)
+            );
+            return;
+        };
+        *self.pending_invite.lock().await = Some(token.clone());
+        ws_emit(&self.app, "auth:invite_link", json!({ "token": token })).await;
+    }
+
+    /// Changes the signed-in user's password and signs out every other
+    /// session. The server answers with fresh tokens for this one.
+    async fn change_password(&self, payload: ChangePasswordPayload) -> Result<(), ApiError> {
+        let me = self
+            .current_user
+            .read()
+            .await
+            .clone()
+            .ok_or(ApiError::Unauthenticated)?;
+        if payload.new_password == payload.current_password {
+            return Err(ApiError::validation(
+                "weak_password",
+                "the new password must be different from the current one",
+            ));
+        }
+        passwords::check_strength(&payload.new_password, &[&me.email, &me.name])?;
+
+        let value = self
+            .send_json(
+                Method::POST,
+                "/auth/password",
+                Some(json!({
+                    "current_password": payload.current_password,
+                    "new_password": payload.new_password,
+                    "revoke_other_sessions": true
+                })),
+                true,
+            )
+            .await
+            .map_err(|err| passwords::map_error(err, passwords::PasswordFlow::Change))?;
+
+        if let Ok(tokens) = serde_json::from_value::<TokenBundle>(value) {
+            self.persist_tokens(&tokens).await?;
+            *self.tokens.write().await = Some(tokens);
+        }
+        Ok(())
+    }
+
+    /// Asks for a reset email. Unknown addresses succeed too, so the form
+    /// cannot be used to find out who has an account.
+    async fn request_password_reset(&self, email: &str) -> Result<(), ApiError> {
+        let email = email.trim().to_lowercase();
+        if !user_import::is_valid_email(&email) {
+            return Err(ApiError::validation(
+                "invalid_email",
+                "email is not a valid address",
+            ));
+        }
+        match self
+            .send_json(
+                Method::POST,
+                "/auth/password/reset",
+                Some(json!({ "email": email })),
+                false,
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) if passwords::is_unknown_account(&err) => Ok(()),
+            Err(err) => Err(passwords::map_error(
+                err,
+                passwords::PasswordFlow::RequestReset,
+            )),
+        }
+    }
+
+    /// Sets a new password with the token from the reset email. The server
+    /// revokes every session of the account, so the user signs in again.
+    async fn confirm_password_reset(&self, payload: ConfirmResetPayload) -> Result<(), ApiError> {
+        let token = payload.token.trim();
+        if token.is_empty() {
+            return Err(ApiError::validation(
+                "invalid_reset_token",
+                "this reset link is invalid or has expired, request a new one",
+            ));
+        }
+        passwords::check_strength(&payload.new_password, &[])?;
+
+        self.send_json(
+            Method::POST,
+            "/auth/password/reset/confirm",
+            Some(json!({
+                "token": token,
+                "new_password": payload.new_password
+            })),
+            false,
+        )
+        .await
+        .map_err(|err| passwords::map_error(err, passwords::PasswordFlow::ConfirmReset))?;
+        Ok(())
+    }
+
+    /// This device's identity for a login request, renamed first when the
+    /// user picked a new name.
+    async fn device_for_login(&self, device_name: Option<&str>) -> sessions::DeviceDto {
+        let Some(name) = sessions::normalize_device_name(device_name) else {
+            return self.device.read().await.clone();
+        };
+        let device = {
+            let mut device = self.device.write().await;
+            device.device_name = name;
+            device.clone()
+        };
+        let saved = self.get_secure_store().and_then(|store| {
+            store.set(DEVICE_STORE_KEY, json!(device));
+            store
+                .save()
+                .map_err(|err| ApiError::Storage(format!("could not save device: {err}")))
+        });
+        if let Err(err) = saved {
+            log::warn!("could not persist device name: {err}");
+        }
+        device
+    }
+
+    /// Active sessions of the signed-in user, this device first.
+    async fn list_sessions(&self) -> Result<Vec<sessions::SessionDto>, ApiError> {
+        let value = self
+            .send_json(Method::GET, "/auth/sessions", None, true)
+            .await?;
+        let list: Vec<sessions::SessionDto> = serde_json::from_value(value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        let device_id = self.device.read().await.device_id.clone();
+        Ok(sessions::arrange(list, &device_id))
+    }
+
+    async fn revoke_session(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<sessions::SessionDto>, ApiError> {
+        sessions::ensure_not_current(&self.list_sessions().await?, session_id)?;
+        self.send_json(
+            Method::DELETE,
+            &format!("/auth/sessions/{session_id}"),
+            None,
+            true,
+        )
+        .await?;
+        self.list_sessions().await
+    }
+
+    /// Signs out every other device. The refresh token tells the server
+    /// which session to keep.
+    async fn revoke_other_sessions(&self) -> Result<Vec<sessions::SessionDto>, ApiError> {
+        let tokens = self.require_tokens().await?;
+        let device_id = self.device.read().await.device_id.clone();
+        self.send_json(
+            Method::POST,
+            "/auth/sessions/revoke_others",
+            Some(json!({
+                "refresh_token": tokens.refresh_token,
+                "device_id": device_id
+            })),
+            true,
+        )
+        .await?;
+        self.list_sessions().await
+    }
+
     async fn clear_tokens(&self) -> Result<(), ApiError> {
         let store = self.get_secure_store()?;
 
@@ -502,6 +1398,7 @@ impl AppState {
             .save()
             .map_err(|err| ApiError::Storage(format!("could not save token store: {err}")))?;
         *self.tokens.write().await = None;
+        *self.current_user.write().await = None;
         Ok(())
     }
 
@@ -672,9 +1569,16 @@ impl AppState {
             return;
         }
 
-        if let Err(err) = self.send_json(Method::GET, "/me", None, true).await {
-            log::warn!("stored session invalid, clearing tokens: {err}");
-            let _ = self.clear_tokens().await;
+        match self.send_json(Method::GET, "/me", None, true).await {
+            Ok(value) => {
+                if let Ok(user) = serde_json::from_value::<UserDto>(value) {
+                    *self.current_user.write().await = Some(user);
+                }
+            }
+            Err(err) => {
+                log::warn!("stored session invalid, clearing tokens: {err}");
+                let _ = self.clear_tokens().await;
+            }
         }
     }
 
@@ -682,15 +1586,48 @@ impl AppState {
         &self,
         payload: AttachmentUploadPayload,
     ) -> Result<AttachmentDto, ApiError> {
+        let staged = self
+            .stage_attachment_upload(
+                &payload.channel_id,
+                AttachmentFilePayload {
+                    filename: payload.filename,
+                    content_type: payload.content_type,
+                    size_bytes: payload.size_bytes,
+                    bytes: payload.bytes,
+                },
+            )
+            .await?;
+        self.commit_staged_upload(staged, Some(&payload.message_id))
+            .await
+    }
+
+    /// Validates, presigns and uploads the binary without attaching it to a
+    /// message yet.
+    async fn stage_attachment_upload(
+        &self,
+        channel_id: &str,
+        file: AttachmentFilePayload,
+    ) -> Result<StagedUpload, ApiError> {
+        let validated = {
+            let denylist = self.attachment_denylist.read().await;
+            upload_validation::validate_upload(
+                &file.filename,
+                &file.content_type,
+                file.size_bytes,
+                &file.bytes,
+                &denylist,
+            )?
+        };
+
         let presign_raw = self
             .send_json(
                 Method::POST,
                 "/attachments/presign",
                 Some(json!({
-                    "channel_id": payload.channel_id,
-                    "filename": payload.filename,
-                    "content_type": payload.content_type,
-                    "size_bytes": payload.size_bytes
+                    "channel_id": channel_id,
+                    "filename": file.filename,
+                    "content_type": validated.content_type,
+                    "size_bytes": file.size_bytes
                 })),
                 true,
             )
@@ -699,16 +1636,37 @@ impl AppState {
         let presign: AttachmentPresignResponse = serde_json::from_value(presign_raw)
             .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
 
+        let (bytes, preview) = if previews::is_previewable(&validated.content_type) {
+            let content_type = validated.content_type.clone();
+            let bytes = file.bytes;
+            tokio::task::spawn_blocking(move || {
+                let preview = previews::generate_preview(&content_type, &bytes);
+                (bytes, preview)
+            })
+            .await
+            .map_err(|err| ApiError::Storage(format!("preview generation failed: {err}")))?
+        } else {
+            (file.bytes, None)
+        };
+
         let upload_resp = self
             .client
             .request(Method::PUT, presign.upload_url.clone())
-            .header("Content-Type", payload.content_type.clone())
-            .body(payload.bytes)
+            .header("Content-Type", validated.content_type.clone())
+            .body(bytes)
             .send()
-            .await
-            .map_err(|err| ApiError::Network(err.to_string()))?;
+            .await;
+        // The presigned upload is never confirmed, so release it on the server.
+        let upload_resp = match upload_resp {
+            Ok(resp) => resp,
+            Err(err) => {
+                self.discard_upload(&presign.upload_id).await;
+                return Err(ApiError::Network(err.to_string()));
+            }
+        };
 
         if !upload_resp.status().is_success() {
+            self.discard_upload(&presign.upload_id).await;
             return Err(ApiError::Http {
                 status: upload_resp.status().as_u16(),
                 error: "upload_failed".to_string(),
@@ -716,127 +1674,2305 @@ impl AppState {
             });
         }
 
+        Ok(StagedUpload {
+            upload_id: presign.upload_id,
+            key: presign.key,
+            filename: file.filename,
+            size_bytes: file.size_bytes,
+            content_type: validated.content_type,
+            preview,
+        })
+    }
+
+    async fn commit_staged_upload(
+        &self,
+        staged: StagedUpload,
+        message_id: Option<&str>,
+    ) -> Result<AttachmentDto, ApiError> {
         let commit_raw = self
             .send_json(
                 Method::POST,
                 "/attachments/commit",
                 Some(json!({
-                    "upload_id": presign.upload_id,
-                    "message_id": payload.message_id
+                    "upload_id": staged.upload_id,
+                    "message_id": message_id
                 })),
                 true,
             )
             .await?;
-        Ok(map_attachment_commit_response(
+        let mut attachment = map_attachment_commit_response(
             commit_raw,
-            payload.filename,
-            payload.size_bytes,
-            payload.content_type,
-            presign.key,
-        ))
-    }
-}
-
-#[tauri::command]
-async fn auth_login(
-    state: State<'_, AppState>,
-    payload: LoginPayload,
-) -> CmdResult<AuthSessionDto> {
-    let mut body = json!({
-      "email": payload.email,
-      "password": payload.password
-    });
-    if let Some(workspace_id) = payload.workspace_id {
-        body["workspace_id"] = JsonValue::String(workspace_id);
+            staged.filename,
+            staged.size_bytes,
+            staged.content_type,
+            staged.key,
+        );
+        if let Some(preview) = staged.preview {
+            attachment.width = preview.width;
+            attachment.height = preview.height;
+            if let Err(err) = self.store_preview(&attachment.id, preview).await {
+                log::warn!("could not cache preview for {}: {err}", attachment.id);
+            }
+        }
+        self.remember_download_urls(std::slice::from_ref(&attachment))
+            .await;
+        Ok(attachment)
     }
 
-    let tokens_value = state
-        .send_json(Method::POST, "/auth/login", Some(body), false)
-        .await
-        .map_err(ApiErrorDto::from)?;
+    /// Uploads and commits every file first, then creates the message with
+    /// the attachment ids in the same request, so a failure never leaves a
+    /// half-attached message behind. Uploads already staged or committed when
+    /// something fails are discarded again.
+    async fn send_message_with_attachments(
+        &self,
+        payload: SendMessageWithAttachmentsPayload,
+    ) -> Result<MessageDto, ApiError> {
+        let concurrency = batch_upload_concurrency(payload.concurrency);
+        let channel_id = payload.channel_id.as_str();
+        self.ensure_channel_writable(channel_id).await?;
+
+        let results = futures_util::stream::iter(payload.files.into_iter().enumerate().map(
+            |(index, file)| async move {
+                self.stage_attachment_upload(channel_id, file)
+                    .await
+                    .map(|upload| (index, upload))
+            },
+        ))
+        .buffer_unordered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+        let mut staged = Vec::with_capacity(results.len());
+        let mut failure = None;
+        for result in results {
+            match result {
+                Ok(upload) => staged.push(upload),
+                Err(err) => {
+                    failure.get_or_insert(err);
+                }
+            }
+        }
+        if let Some(err) = failure {
+            for (_, upload) in &staged {
+                self.discard_upload(&upload.upload_id).await;
+            }
+            return Err(err);
+        }
+        staged.sort_by_key(|(index, _)| *index);
 
-    let tokens: TokenBundle = serde_json::from_value(tokens_value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+        let mut attachments = Vec::with_capacity(staged.len());
+        for (_, upload) in staged {
+            let upload_id = upload.upload_id.clone();
+            if failure.is_some() {
+                self.discard_upload(&upload_id).await;
+                continue;
+            }
+            match self.commit_staged_upload(upload, None).await {
+                Ok(attachment) => attachments.push(attachment),
+                Err(err) => {
+                    self.discard_upload(&upload_id).await;
+                    failure = Some(err);
+                }
+            }
+        }
+        if let Some(err) = failure {
+            for attachment in &attachments {
+                self.discard_upload(&attachment.id).await;
+            }
+            return Err(err);
+        }
 
-    state
-        .persist_tokens(&tokens)
-        .await
-        .map_err(ApiErrorDto::from)?;
-    *state.tokens.write().await = Some(tokens.clone());
+        let attachment_ids: Vec<String> = attachments
+            .iter()
+            .map(|attachment| attachment.id.clone())
+            .collect();
+        match self
+            .send_channel_message(channel_id, &payload.body_md, attachments)
+            .await
+        {
+            Ok(message) => Ok(message),
+            Err(err) => {
+                for attachment_id in &attachment_ids {
+                    self.discard_upload(attachment_id).await;
+                }
+                Err(err)
+            }
+        }
+    }
 
-    let me_value = state
-        .send_json(Method::GET, "/me", None, true)
-        .await
-        .map_err(ApiErrorDto::from)?;
-    let user: UserDto = serde_json::from_value(me_value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+    /// Best-effort removal of an upload or attachment that ended up unused.
+    /// Servers without the endpoint expire uncommitted uploads on their own.
+    async fn discard_upload(&self, id: &str) {
+        match self
+            .send_json(Method::DELETE, &format!("/attachments/{id}"), None, true)
+            .await
+        {
+            Ok(_)
+            | Err(ApiError::Http {
+                status: 404 | 405, ..
+            }) => {}
+            Err(err) => log::warn!("could not discard unused upload {id}: {err}"),
+        }
+    }
 
-    Ok(AuthSessionDto {
-        access_token: tokens.access_token,
-        refresh_token: tokens.refresh_token,
-        access_expires_at: tokens.access_expires_at,
-        refresh_expires_at: tokens.refresh_expires_at,
-        user,
-    })
-}
+    async fn remember_download_urls(&self, attachments: &[AttachmentDto]) {
+        let now = Instant::now();
+        let mut urls = self.download_urls.write().await;
+        for attachment in attachments {
+            if let Some(url) = &attachment.download_url {
+                urls.insert(
+                    attachment.id.clone(),
+                    IssuedDownloadUrl {
+                        url: url.clone(),
+                        issued_at: now,
+                    },
+                );
+            }
+        }
+    }
 
-#[tauri::command]
-async fn auth_me(state: State<'_, AppState>) -> CmdResult<UserDto> {
-    let me_value = state
-        .send_json(Method::GET, "/me", None, true)
-        .await
-        .map_err(ApiErrorDto::from)?;
-    serde_json::from_value(me_value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
-}
+    async fn remember_message_download_urls(&self, messages: &[MessageDto]) {
+        for message in messages {
+            self.remember_download_urls(&message.attachments).await;
+        }
+    }
 
-#[tauri::command]
-async fn auth_logout(state: State<'_, AppState>) -> CmdResult<()> {
-    if let Ok(tokens) = state.require_tokens().await {
-        let _ = state
+    async fn fetch_attachment(&self, attachment_id: &str) -> Result<AttachmentDto, ApiError> {
+        let value = self
             .send_json(
-                Method::POST,
-                "/auth/logout",
-                Some(json!({ "refresh_token": tokens.refresh_token })),
-                false,
+                Method::GET,
+                &format!("/attachments/{attachment_id}"),
+                None,
+                true,
             )
+            .await?;
+        let mut attachment = map_attachment_get_response(value, attachment_id.to_string());
+        if let Some(preview) = self.cached_preview(attachment_id) {
+            attachment.width = preview.width;
+            attachment.height = preview.height;
+        }
+        self.remember_download_urls(std::slice::from_ref(&attachment))
             .await;
+        Ok(attachment)
     }
 
-    state.clear_tokens().await.map_err(ApiErrorDto::from)?;
-    Ok(())
-}
+    async fn resolve_download_url(
+        &self,
+        attachment_id: &str,
+        force_refresh: bool,
+    ) -> Result<String, ApiError> {
+        if !force_refresh {
+            if let Some(issued) = self.download_urls.read().await.get(attachment_id) {
+                if issued.is_fresh(Instant::now()) {
+                    return Ok(issued.url.clone());
+                }
+            }
+        }
 
-#[tauri::command]
-async fn channels_list(state: State<'_, AppState>) -> CmdResult<Vec<ChannelDto>> {
-    let value = state
-        .send_json(Method::GET, "/channels", None, true)
-        .await
-        .map_err(ApiErrorDto::from)?;
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
-}
+        self.download_urls.write().await.remove(attachment_id);
+        self.fetch_attachment(attachment_id)
+            .await?
+            .download_url
+            .ok_or_else(|| {
+                ApiError::InvalidResponse(format!("attachment {attachment_id} has no download_url"))
+            })
+    }
 
-#[tauri::command]
-async fn channels_create(
-    state: State<'_, AppState>,
-    payload: CreateChannelPayload,
-) -> CmdResult<ChannelDto> {
-    let value = state
-        .send_json(
-            Method::POST,
-            "/channels",
-            Some(json!({ "name": payload.name, "is_private": payload.is_private })),
-            true,
-        )
-        .await
-        .map_err(ApiErrorDto::from)?;
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
-}
+    async fn download_attachment_bytes(&self, attachment_id: &str) -> Result<Vec<u8>, ApiError> {
+        let mut force_refresh = false;
 
-#[tauri::command]
-async fn channels_delete(
+        loop {
+            let url = self
+                .resolve_download_url(attachment_id, force_refresh)
+                .await?;
+            let resp = self
+                .client
+                .get(url)
+                .send()
+                .await
+                .map_err(|err| ApiError::Network(err.to_string()))?;
+
+            if resp.status() == StatusCode::FORBIDDEN && !force_refresh {
+                force_refresh = true;
+                continue;
+            }
+
+            if !resp.status().is_success() {
+                return Err(ApiError::Http {
+                    status: resp.status().as_u16(),
+                    error: "download_failed".to_string(),
+                    message: "attachment download failed".to_string(),
+                });
+            }
+
+            return resp
+                .bytes()
+                .await
+                .map(|bytes| bytes.to_vec())
+                .map_err(|err| ApiError::Network(err.to_string()));
+        }
+    }
+
+    fn cached_preview(&self, attachment_id: &str) -> Option<previews::AttachmentPreviewDto> {
+        let dir = previews::previews_dir(&self.app).ok()?;
+        previews::cached_preview(&dir, attachment_id)
+    }
+
+    async fn store_preview(
+        &self,
+        attachment_id: &str,
+        preview: previews::GeneratedPreview,
+    ) -> Result<previews::AttachmentPreviewDto, ApiError> {
+        let dir = previews::previews_dir(&self.app)?;
+        let attachment_id = attachment_id.to_string();
+        tokio::task::spawn_blocking(move || previews::store_preview(&dir, &attachment_id, preview))
+            .await
+            .map_err(|err| ApiError::Storage(format!("preview write failed: {err}")))?
+    }
+
+    async fn attachment_preview(
+        &self,
+        attachment_id: &str,
+    ) -> Result<Option<previews::AttachmentPreviewDto>, ApiError> {
+        if let Some(preview) = self.cached_preview(attachment_id) {
+            return Ok(Some(preview));
+        }
+
+        let attachment = self.fetch_attachment(attachment_id).await?;
+        let content_type = attachment.content_type.clone().unwrap_or_default();
+        if !previews::is_previewable(&content_type) {
+            return Ok(None);
+        }
+
+        let bytes = self.cached_attachment_bytes(&attachment).await?.bytes;
+        let preview =
+            tokio::task::spawn_blocking(move || previews::generate_preview(&content_type, &bytes))
+                .await
+                .map_err(|err| ApiError::Storage(format!("preview generation failed: {err}")))?;
+
+        match preview {
+            Some(preview) => self.store_preview(attachment_id, preview).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn with_attachment_cache<T, F>(&self, op: F) -> Result<T, ApiError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut attachment_cache::AttachmentCache) -> Result<T, ApiError> + Send + 'static,
+    {
+        let cache = self.attachment_cache.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut guard = cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            op(&mut guard)
+        })
+        .await
+        .map_err(|err| ApiError::Storage(format!("attachment cache task failed: {err}")))?
+    }
+
+    async fn cached_attachment_bytes(
+        &self,
+        attachment: &AttachmentDto,
+    ) -> Result<attachment_cache::CachedAttachment, ApiError> {
+        let attachment_id = attachment.id.clone();
+        let storage_key = attachment.storage_key.clone();
+        let hit = self
+            .with_attachment_cache(move |cache| {
+                Ok(cache.get(&attachment_id, storage_key.as_deref()))
+            })
+            .await?;
+        if let Some(hit) = hit {
+            return Ok(hit);
+        }
+
+        let bytes = self.download_attachment_bytes(&attachment.id).await?;
+        let attachment_id = attachment.id.clone();
+        let storage_key = attachment.storage_key.clone();
+        let content_type = attachment.content_type.clone();
+        self.with_attachment_cache(move |cache| {
+            if let Err(err) =
+                cache.insert(&attachment_id, storage_key, content_type.clone(), &bytes)
+            {
+                log::warn!("could not cache attachment {attachment_id}: {err}");
+            }
+            Ok(attachment_cache::CachedAttachment {
+                bytes,
+                content_type,
+            })
+        })
+        .await
+    }
+
+    /// Writes the recency that cache reads recorded since the last tick.
+    async fn flush_attachment_cache_index(&self) {
+        let flushed = self
+            .with_attachment_cache(|cache| {
+                cache.flush_index();
+                Ok(())
+            })
+            .await;
+        if let Err(err) = flushed {
+            log::warn!("could not flush attachment cache index: {err}");
+        }
+    }
+
+    async fn load_attachment_for_protocol(
+        &self,
+        attachment_id: &str,
+    ) -> Result<attachment_cache::CachedAttachment, ApiError> {
+        let id = attachment_id.to_string();
+        let hit = self
+            .with_attachment_cache(move |cache| Ok(cache.get(&id, None)))
+            .await?;
+        if let Some(hit) = hit {
+            return Ok(hit);
+        }
+
+        let attachment = self.fetch_attachment(attachment_id).await?;
+        self.cached_attachment_bytes(&attachment).await
+    }
+
+    async fn my_user_id(&self) -> Option<String> {
+        self.current_user
+            .read()
+            .await
+            .as_ref()
+            .map(|user| user.id.clone())
+    }
+
+    async fn cache_message(&self, message: MessageDto) -> MessageDto {
+        self.search_index.write().await.upsert(&message);
+        self.saved_items.write().await.update_snapshot(&message);
+        self.message_cache.write().await.upsert(message)
+    }
+
+    async fn cache_messages(&self, messages: Vec<MessageDto>) -> Vec<MessageDto> {
+        {
+            let mut index = self.search_index.write().await;
+            let mut saved = self.saved_items.write().await;
+            for message in &messages {
+                index.upsert(message);
+                saved.update_snapshot(message);
+            }
+        }
+        self.message_cache.write().await.upsert_many(messages)
+    }
+
+    async fn refresh_user_directory(&self, workspace_id: &str) {
+        let me = self.current_user.read().await.clone();
+        let users = if me
+            .as_ref()
+            .is_some_and(|me| me.workspace_id == workspace_id)
+        {
+            self.send_json(Method::GET, "/users", None, true)
+                .await
+                .and_then(|value| {
+                    serde_json::from_value::<Vec<UserDto>>(value)
+                        .map_err(|err| ApiError::InvalidResponse(err.to_string()))
+                })
+        } else {
+            self.send_json(
+                Method::GET,
+                &format!("/workspaces/{workspace_id}/members"),
+                None,
+                true,
+            )
+            .await
+            .and_then(|value| {
+                serde_json::from_value::<Vec<JsonValue>>(value)
+                    .map_err(|err| ApiError::InvalidResponse(err.to_string()))
+            })
+            .map(|members| {
+                members
+                    .iter()
+                    .filter_map(|member| user_directory::user_from_member(workspace_id, member))
+                    .collect()
+            })
+        };
+
+        let mut directory = self.user_directory.write().await;
+        directory.mark_refreshed(workspace_id, Instant::now());
+        if let Some(me) = me.filter(|me| me.workspace_id == workspace_id) {
+            directory.merge(workspace_id, [me]);
+        }
+        match users {
+            Ok(users) => directory.merge(workspace_id, users),
+            Err(err) => log::info!("could not refresh user directory: {err}"),
+        }
+    }
+
+    async fn resolve_users(&self, workspace_id: &str, user_ids: &[String]) -> Vec<UserDto> {
+        let stale =
+            self.user_directory
+                .read()
+                .await
+                .needs_refresh(workspace_id, user_ids, Instant::now());
+        if stale {
+            self.refresh_user_directory(workspace_id).await;
+        }
+        self.user_directory
+            .read()
+            .await
+            .resolve(workspace_id, user_ids)
+    }
+
+    /// Refreshes the directory in the background when `user_ids` are not all
+    /// known, for callers that cannot wait on `/users`. At most one such
+    /// refresh runs at a time.
+    async fn backfill_user_directory(&self, workspace_id: &str, user_ids: &[String]) {
+        let stale =
+            self.user_directory
+                .read()
+                .await
+                .needs_refresh(workspace_id, user_ids, Instant::now());
+        if !stale || self.user_directory_backfill.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let state = self.clone();
+        let workspace_id = workspace_id.to_string();
+        tauri::async_runtime::spawn(async move {
+            state.refresh_user_directory(&workspace_id).await;
+            state
+                .user_directory_backfill
+                .store(false, Ordering::Release);
+        });
+    }
+
+    async fn attach_senders(&self, messages: &mut [MessageDto]) {
+        let mut by_workspace: HashMap<String, Vec<String>> = HashMap::new();
+        for message in messages.iter() {
+            let ids = by_workspace
+                .entry(message.workspace_id.clone())
+                .or_default();
+            if !ids.contains(&message.sender_id) {
+                ids.push(message.sender_id.clone());
+            }
+        }
+
+        let mut senders: HashMap<(String, String), UserDto> = HashMap::new();
+        for (workspace_id, ids) in by_workspace {
+            for user in self.resolve_users(&workspace_id, &ids).await {
+                senders.insert((workspace_id.clone(), user.id.clone()), user);
+            }
+        }
+        for message in messages.iter_mut() {
+            let key = (message.workspace_id.clone(), message.sender_id.clone());
+            message.sender = senders.get(&key).cloned();
+        }
+    }
+
+    /// Member ids of `channel_id`, cached like the user directory. `None`
+    /// when the member list is not visible to the current user.
+    async fn channel_member_ids(&self, channel_id: &str) -> Option<Vec<String>> {
+        if let Some((members, fetched_at)) = self.channel_members.read().await.get(channel_id) {
+            if fetched_at.elapsed() < user_directory::USER_DIRECTORY_TTL {
+                return members.clone();
+            }
+        }
+
+        let members = self
+            .send_json(
+                Method::GET,
+                &format!("/channels/{channel_id}/members"),
+                None,
+                true,
+            )
+            .await
+            .and_then(|value| {
+                serde_json::from_value::<Vec<JsonValue>>(value)
+                    .map_err(|err| ApiError::InvalidResponse(err.to_string()))
+            })
+            .map(|members| {
+                members
+                    .iter()
+                    .filter_map(|member| member.get("user_id").and_then(JsonValue::as_str))
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+            });
+        let members = match members {
+            Ok(members) => Some(members),
+            Err(err) => {
+                log::info!("could not load members of channel {channel_id}: {err}");
+                None
+            }
+        };
+        self.channel_members
+            .write()
+            .await
+            .insert(channel_id.to_string(), (members.clone(), Instant::now()));
+        members
+    }
+
+    /// `channel_member_ids` for several channels, a few requests at a time.
+    async fn channel_member_ids_many(&self, channels: &[ChannelDto]) -> Vec<Option<Vec<String>>> {
+        futures_util::stream::iter(
+            channels
+                .iter()
+                .map(|channel| self.channel_member_ids(&channel.id)),
+        )
+        .buffered(dm::DM_MEMBER_FETCH_CONCURRENCY)
+        .collect()
+        .await
+    }
+
+    async fn refresh_channels(&self) -> Result<Vec<ChannelDto>, ApiError> {
+        let value = self.send_json(Method::GET, "/channels", None, true).await?;
+        let channels: Vec<ChannelDto> = serde_json::from_value(value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        *self.channels.write().await = channels
+            .iter()
+            .map(|channel| (channel.id.clone(), channel.clone()))
+            .collect();
+        Ok(channels)
+    }
+
+    async fn create_channel(&self, name: &str, is_private: bool) -> Result<ChannelDto, ApiError> {
+        let value = self
+            .send_json(
+                Method::POST,
+                "/channels",
+                Some(json!({ "name": name, "is_private": is_private })),
+                true,
+            )
+            .await?;
+        let channel: ChannelDto = serde_json::from_value(value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        self.channels
+            .write()
+            .await
+            .insert(channel.id.clone(), channel.clone());
+        Ok(channel)
+    }
+
+    /// Patches a channel and announces the result as `channels:updated`.
+    async fn update_channel(
+        &self,
+        channel_id: &str,
+        changes: JsonValue,
+    ) -> Result<ChannelDto, ApiError> {
+        let value = self
+            .send_json(
+                Method::PATCH,
+                &format!("/channels/{channel_id}"),
+                Some(changes.clone()),
+                true,
+            )
+            .await?;
+        let cached = self.channels.read().await.get(channel_id).cloned();
+        let mut patch = if value.is_null() { changes } else { value };
+        if patch.get("channel_id").is_none() && patch.get("id").is_none() {
+            patch["channel_id"] = json!(channel_id);
+        }
+        let channel = channels::apply_event("CHANNEL_UPDATED", &patch, cached.as_ref())
+            .ok_or_else(|| {
+                ApiError::InvalidResponse("channel update without channel".to_string())
+            })?;
+        Ok(self.store_channel_update(channel).await)
+    }
+
+    async fn store_channel_update(&self, channel: ChannelDto) -> ChannelDto {
+        self.channels
+            .write()
+            .await
+            .insert(channel.id.clone(), channel.clone());
+        let channel = self
+            .with_read_state(vec![channel.clone()])
+            .await
+            .pop()
+            .unwrap_or(channel);
+        ws_emit(&self.app, "channels:updated", &channel).await;
+        channel
+    }
+
+    /// Archived channels are read-only; channels not loaded yet are left to
+    /// the server.
+    async fn ensure_channel_writable(&self, channel_id: &str) -> Result<(), ApiError> {
+        channels::ensure_writable(self.channels.read().await.get(channel_id))
+    }
+
+    /// Same check for writes that only name a message, such as edits,
+    /// reactions and thread replies.
+    async fn ensure_message_writable(&self, message_id: &str) -> Result<(), ApiError> {
+        let channel_id = self
+            .message_cache
+            .read()
+            .await
+            .get(message_id)
+            .map(|message| message.channel_id.clone());
+        match channel_id {
+            Some(channel_id) => self.ensure_channel_writable(&channel_id).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn apply_channel_event(&self, event_type: &str, payload: &JsonValue) {
+        let body = payload.get("channel").unwrap_or(payload);
+        let cached = match body
+            .get("channel_id")
+            .or_else(|| body.get("id"))
+            .and_then(JsonValue::as_str)
+        {
+            Some(channel_id) => self.channels.read().await.get(channel_id).cloned(),
+            None => None,
+        };
+        if let Some(channel) = channels::apply_event(event_type, payload, cached.as_ref()) {
+            self.store_channel_update(channel).await;
+        }
+    }
+
+    async fn add_channel_member(&self, channel_id: &str, user_id: &str) -> Result<(), ApiError> {
+        self.send_json(
+            Method::POST,
+            &format!("/channels/{channel_id}/members"),
+            Some(json!({ "user_id": user_id })),
+            true,
+        )
+        .await?;
+        self.channel_members.write().await.remove(channel_id);
+        Ok(())
+    }
+
+    /// Returns the private channel whose members are exactly `user_ids` plus
+    /// the current user, creating it when none exists yet.
+    async fn open_dm(&self, user_ids: &[String]) -> Result<dm::DmConversationDto, ApiError> {
+        let me = self
+            .current_user
+            .read()
+            .await
+            .clone()
+            .ok_or(ApiError::Unauthenticated)?;
+        let members = dm::member_set(&me.id, user_ids);
+        let name = dm::channel_name(&members);
+
+        let mut candidates: Vec<ChannelDto> = self
+            .refresh_channels()
+            .await?
+            .into_iter()
+            .filter(dm::is_dm_channel)
+            .collect();
+        candidates.sort_by_key(|channel| (channel.name != name, channel.created_at));
+        let member_ids = self.channel_member_ids_many(&candidates).await;
+        if let Some((channel, existing)) = candidates
+            .into_iter()
+            .zip(member_ids)
+            .find(|(_, ids)| dm::same_members(ids.as_deref(), &members))
+        {
+            let users = self.resolve_users(&me.workspace_id, &members).await;
+            return Ok(dm::conversation(channel, existing, &me.id, &users));
+        }
+
+        let channel = self.create_channel(&name, true).await?;
+        for user_id in &members {
+            match self.add_channel_member(&channel.id, user_id).await {
+                Ok(()) | Err(ApiError::Http { status: 409, .. }) => {}
+                Err(err) => {
+                    // Deleting a channel takes admin rights; leaving the
+                    // half-built conversation works for everyone.
+                    if let Err(cleanup) = self
+                        .send_json(
+                            Method::DELETE,
+                            &format!("/channels/{}/members/{}", channel.id, me.id),
+                            None,
+                            true,
+                        )
+                        .await
+                    {
+                        log::warn!("could not leave incomplete DM {}: {cleanup}", channel.id);
+                    }
+                    self.channels.write().await.remove(&channel.id);
+                    self.channel_members.write().await.remove(&channel.id);
+                    return Err(err);
+                }
+            }
+        }
+        self.channel_members
+            .write()
+            .await
+            .insert(channel.id.clone(), (Some(members.clone()), Instant::now()));
+        let users = self.resolve_users(&me.workspace_id, &members).await;
+        Ok(dm::conversation(channel, Some(members), &me.id, &users))
+    }
+
+    async fn list_dms(&self) -> Result<Vec<dm::DmConversationDto>, ApiError> {
+        let me = self
+            .current_user
+            .read()
+            .await
+            .clone()
+            .ok_or(ApiError::Unauthenticated)?;
+        let channels = self.refresh_channels().await?;
+        let channels = self.with_read_state(channels).await;
+
+        let channels: Vec<ChannelDto> = channels.into_iter().filter(dm::is_dm_channel).collect();
+        let member_ids = self.channel_member_ids_many(&channels).await;
+        let mut user_ids: Vec<String> = member_ids.iter().flatten().flatten().cloned().collect();
+        user_ids.sort();
+        user_ids.dedup();
+        let users = self.resolve_users(&me.workspace_id, &user_ids).await;
+
+        let mut conversations: Vec<dm::DmConversationDto> = channels
+            .into_iter()
+            .zip(member_ids)
+            .map(|(channel, member_ids)| dm::conversation(channel, member_ids, &me.id, &users))
+            .collect();
+        conversations.sort_by(|a, b| b.channel.created_at.cmp(&a.channel.created_at));
+        Ok(conversations)
+    }
+
+    async fn workspace_member_roles(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Vec<roles::MemberRole>, ApiError> {
+        let value = self
+            .send_json(
+                Method::GET,
+                &format!("/workspaces/{workspace_id}/members"),
+                None,
+                true,
+            )
+            .await?;
+        let members: Vec<JsonValue> = serde_json::from_value(value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        Ok(roles::member_roles(&members))
+    }
+
+    async fn set_member_role(
+        &self,
+        workspace_id: &str,
+        user_id: &str,
+        role: roles::Role,
+    ) -> Result<(), ApiError> {
+        let members = self.workspace_member_roles(workspace_id).await?;
+        roles::ensure_owner_remains(&members, user_id, Some(role))?;
+        self.send_json(
+            Method::PATCH,
+            &format!("/workspaces/{workspace_id}/members/{user_id}"),
+            Some(json!({ "role": role })),
+            true,
+        )
+        .await?;
+
+        if let Some(user) = self
+            .user_directory
+            .write()
+            .await
+            .get_mut(workspace_id, user_id)
+        {
+            user.role = role.as_str().to_string();
+        }
+        if let Some(me) = self.current_user.write().await.as_mut() {
+            if me.id == user_id && me.workspace_id == workspace_id {
+                me.role = role.as_str().to_string();
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove_member(&self, workspace_id: &str, user_id: &str) -> Result<(), ApiError> {
+        let members = self.workspace_member_roles(workspace_id).await?;
+        roles::ensure_owner_remains(&members, user_id, None)?;
+        self.send_json(
+            Method::DELETE,
+            &format!("/workspaces/{workspace_id}/members/{user_id}"),
+            None,
+            true,
+        )
+        .await?;
+        self.user_directory
+            .write()
+            .await
+            .remove(workspace_id, user_id);
+        Ok(())
+    }
+
+    /// Disables or re-enables an account. Nobody can disable themselves or
+    /// the last active owner of the current workspace.
+    async fn set_user_status(
+        &self,
+        user_id: &str,
+        status: roles::UserStatus,
+    ) -> Result<(), ApiError> {
+        let me = self
+            .current_user
+            .read()
+            .await
+            .clone()
+            .ok_or(ApiError::Unauthenticated)?;
+        if status == roles::UserStatus::Disabled {
+            if me.id == user_id {
+                return Err(ApiError::validation(
+                    "bad_request",
+                    "you cannot disable your own account",
+                ));
+            }
+            let members = self.workspace_member_roles(&me.workspace_id).await?;
+            roles::ensure_owner_remains(&members, user_id, None)?;
+        }
+        self.send_json(
+            Method::PATCH,
+            &format!("/users/{user_id}"),
+            Some(json!({ "status": status })),
+            true,
+        )
+        .await?;
+
+        if let Some(user) = self
+            .user_directory
+            .write()
+            .await
+            .get_mut(&me.workspace_id, user_id)
+        {
+            user.status = Some(status);
+        }
+        Ok(())
+    }
+
+    /// Validates a CSV of users and, unless `dry_run` is set or a row is
+    /// invalid, adds every row to the current workspace. Existing accounts,
+    /// in this or any other workspace, keep their password and only get
+    /// their membership updated; new ones without a password in the file get
+    /// a generated temporary one.
+    async fn import_users_csv(
+        &self,
+        payload: UsersImportCsvPayload,
+    ) -> Result<user_import::ImportReportDto, ApiError> {
+        use user_import::{ImportAction, ImportReportDto, ImportRowResultDto, ImportRowStatus};
+
+        let me = self
+            .current_user
+            .read()
+            .await
+            .clone()
+            .ok_or(ApiError::Unauthenticated)?;
+        let dry_run = payload.dry_run.unwrap_or(false);
+        let concurrency = payload
+            .concurrency
+            .unwrap_or(user_import::USER_IMPORT_DEFAULT_CONCURRENCY)
+            .clamp(1, user_import::USER_IMPORT_MAX_CONCURRENCY);
+        let (rows, invalid) = user_import::parse(&payload.csv)?;
+
+        // `/users` only lists this workspace. Someone with an account in
+        // another workspace is planned as a create; the server answers that
+        // with a 409 and `import_user_row` retries it as an update.
+        let value = self.send_json(Method::GET, "/users", None, true).await?;
+        let existing: Vec<UserDto> = serde_json::from_value(value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        let existing: HashSet<String> = existing
+            .into_iter()
+            .map(|user| user.email.to_lowercase())
+            .collect();
+        let action_for = |row: &user_import::ImportRow| {
+            if existing.contains(&row.email) {
+                ImportAction::Update
+            } else {
+                ImportAction::Create
+            }
+        };
+
+        let apply = !dry_run && invalid.is_empty();
+        let mut results: Vec<ImportRowResultDto> = invalid
+            .into_iter()
+            .map(ImportRowResultDto::invalid)
+            .collect();
+        if !apply {
+            results.extend(
+                rows.iter()
+                    .map(|row| ImportRowResultDto::ready(row, action_for(row))),
+            );
+            return Ok(ImportReportDto::new(dry_run, false, results));
+        }
+
+        let workspace_id = me.workspace_id.as_str();
+        let total = rows.len();
+        let imports = rows.into_iter().map(|row| {
+            let action = action_for(&row);
+            async move {
+                let result = self.import_user_row(workspace_id, &row, action).await;
+                (row, result)
+            }
+        });
+        let mut completions = futures_util::stream::iter(imports).buffer_unordered(concurrency);
+        let mut progress = user_import::ImportProgressDto {
+            total,
+            completed: 0,
+            failed: 0,
+        };
+        let mut imported = Vec::new();
+        while let Some((row, result)) = completions.next().await {
+            let mut item = ImportRowResultDto::ready(&row, action_for(&row));
+            match result {
+                Ok((user, action, temporary_password)) => {
+                    progress.completed += 1;
+                    item.action = Some(action);
+                    item.status = ImportRowStatus::Imported;
+                    item.temporary_password = temporary_password;
+                    imported.push(user.clone());
+                    item.user = Some(user);
+                }
+                Err(err) => {
+                    log::warn!("import of {} (line {}) failed: {err}", row.email, row.line);
+                    progress.failed += 1;
+                    item.status = ImportRowStatus::Failed;
+                    item.error = Some(ApiErrorDto::from(err));
+                }
+            }
+            results.push(item);
+            ws_emit(&self.app, "users:import_progress", progress.clone()).await;
+        }
+
+        self.user_directory
+            .write()
+            .await
+            .merge(workspace_id, imported);
+        Ok(ImportReportDto::new(false, true, results))
+    }
+
+    /// Adds one imported row and returns the action that was applied. A
+    /// create the server refuses with 409 belongs to an account from another
+    /// workspace, so it is sent again as an update, without a password.
+    async fn import_user_row(
+        &self,
+        workspace_id: &str,
+        row: &user_import::ImportRow,
+        action: user_import::ImportAction,
+    ) -> Result<(UserDto, user_import::ImportAction, Option<String>), ApiError> {
+        use user_import::ImportAction;
+
+        let body = json!({
+            "email": row.email,
+            "name": row.name,
+            "role": row.role
+        });
+        let (value, action, temporary_password) = match action {
+            ImportAction::Update => (
+                self.post_import_member(workspace_id, &body).await?,
+                ImportAction::Update,
+                None,
+            ),
+            ImportAction::Create => {
+                let temporary_password = match &row.password {
+                    Some(_) => None,
+                    None => Some(user_import::temporary_password()?),
+                };
+                let mut create = body.clone();
+                create["password"] = json!(row.password.as_ref().or(temporary_password.as_ref()));
+                match self.post_import_member(workspace_id, &create).await {
+                    Err(ApiError::Http { status: 409, .. }) => (
+                        self.post_import_member(workspace_id, &body).await?,
+                        ImportAction::Update,
+                        None,
+                    ),
+                    result => (result?, ImportAction::Create, temporary_password),
+                }
+            }
+        };
+
+        let user = user_directory::user_from_member(workspace_id, &value).ok_or_else(|| {
+            ApiError::InvalidResponse(format!("member response for {} has no user id", row.email))
+        })?;
+        Ok((user, action, temporary_password))
+    }
+
+    /// Posts one workspace member, waiting out rate limits that outlast the
+    /// retries in `send_json`.
+    async fn post_import_member(
+        &self,
+        workspace_id: &str,
+        body: &JsonValue,
+    ) -> Result<JsonValue, ApiError> {
+        let mut attempt = 0;
+        loop {
+            match self
+                .send_json(
+                    Method::POST,
+                    &format!("/workspaces/{workspace_id}/members"),
+                    Some(body.clone()),
+                    true,
+                )
+                .await
+            {
+                Err(ApiError::Http { status: 429, .. })
+                    if attempt < USER_IMPORT_RATE_LIMIT_RETRIES =>
+                {
+                    attempt += 1;
+                    tokio::time::sleep(USER_IMPORT_RATE_LIMIT_BACKOFF * attempt).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// What the signed-in user may do in a channel or on a message. Unknown
+    /// roles get member permissions; a channel or message that is not loaded
+    /// is an error rather than a context-free answer.
+    async fn permissions_for(
+        &self,
+        payload: PermissionsContextPayload,
+    ) -> Result<permissions::PermissionsDto, ApiError> {
+        let me = self
+            .current_user
+            .read()
+            .await
+            .clone()
+            .ok_or(ApiError::Unauthenticated)?;
+        let role = roles::Role::parse(&me.role).unwrap_or(roles::Role::Member);
+
+        let message = match &payload.message_id {
+            Some(message_id) => Some(
+                self.message_cache
+                    .read()
+                    .await
+                    .get(message_id)
+                    .cloned()
+                    .ok_or_else(|| {
+                        ApiError::validation("message_not_loaded", "message is not loaded")
+                    })?,
+            ),
+            None => None,
+        };
+        let channel_id = payload
+            .channel_id
+            .or_else(|| message.as_ref().map(|message| message.channel_id.clone()));
+        let channel = match &channel_id {
+            Some(channel_id) => Some(
+                self.channels
+                    .read()
+                    .await
+                    .get(channel_id)
+                    .cloned()
+                    .ok_or_else(|| {
+                        ApiError::validation("channel_not_loaded", "channel is not loaded")
+                    })?,
+            ),
+            None => None,
+        };
+
+        let context = permissions::PermissionContext {
+            user_id: &me.id,
+            channel: channel.as_ref(),
+            message: message.as_ref(),
+        };
+        Ok(permissions::PermissionsDto {
+            role,
+            allowed: permissions::allowed(role, context),
+        })
+    }
+
+    /// Users and channels of the current workspace that mentions can refer to.
+    async fn mention_directory(&self) -> Result<(Vec<UserDto>, Vec<ChannelDto>), ApiError> {
+        let workspace_id = self
+            .current_user
+            .read()
+            .await
+            .as_ref()
+            .map(|user| user.workspace_id.clone())
+            .ok_or(ApiError::Unauthenticated)?;
+        self.resolve_users(&workspace_id, &[]).await;
+        Ok(self.cached_mention_directory(&workspace_id).await)
+    }
+
+    /// The users and channels of `workspace_id` already in memory, without
+    /// refreshing anything.
+    async fn cached_mention_directory(
+        &self,
+        workspace_id: &str,
+    ) -> (Vec<UserDto>, Vec<ChannelDto>) {
+        let users = self.user_directory.read().await.users(workspace_id);
+        let channels = self
+            .channels
+            .read()
+            .await
+            .values()
+            .filter(|channel| channel.workspace_id == workspace_id)
+            .cloned()
+            .collect();
+        (users, channels)
+    }
+
+    async fn suggest_mentions(
+        &self,
+        payload: MentionsSuggestPayload,
+    ) -> Result<Vec<mentions::MentionSuggestionDto>, ApiError> {
+        let (mut users, channels) = self.mention_directory().await?;
+        if let Some(channel_id) = payload.channel_id.as_deref() {
+            if let Some(member_ids) = self.channel_member_ids(channel_id).await {
+                users.retain(|user| member_ids.contains(&user.id));
+            }
+        }
+        let limit = payload
+            .limit
+            .unwrap_or(mentions::DEFAULT_SUGGESTION_LIMIT)
+            .clamp(1, 50);
+        Ok(mentions::suggest(&payload.prefix, &users, &channels, limit))
+    }
+
+    /// Renders `body_html` for display, labelling mentions with current names.
+    async fn render_bodies<'a>(&self, messages: impl IntoIterator<Item = &'a mut MessageDto>) {
+        let (users, channels) = self.mention_directory().await.unwrap_or_default();
+        render_message_bodies(&users, &channels, messages);
+    }
+
+    /// Same as `render_bodies` from the directory already in memory, so the
+    /// realtime loop never waits on a directory refresh.
+    async fn render_cached_bodies<'a>(
+        &self,
+        messages: impl IntoIterator<Item = &'a mut MessageDto>,
+    ) {
+        let workspace_id = self
+            .current_user
+            .read()
+            .await
+            .as_ref()
+            .map(|user| user.workspace_id.clone());
+        let (users, channels) = match workspace_id {
+            Some(workspace_id) => self.cached_mention_directory(&workspace_id).await,
+            None => Default::default(),
+        };
+        render_message_bodies(&users, &channels, messages);
+    }
+
+    /// Rewrites `@name` / `#channel` in an outgoing body to stable ids. Falls
+    /// back to the body as typed when the directory is unavailable.
+    async fn normalize_mentions(&self, body_md: &str) -> String {
+        match self.mention_directory().await {
+            Ok((users, channels)) => mentions::normalize(body_md, &users, &channels),
+            Err(_) => body_md.to_string(),
+        }
+    }
+
+    async fn count_unread(&self, message: &MessageDto) {
+        let Some(me) = self.current_user.read().await.clone() else {
+            return;
+        };
+        let mentions_me = read_state::mentions_user(&message.body_md, &me);
+        let updated = self
+            .read_state
+            .write()
+            .await
+            .record_message(&me.id, message, mentions_me);
+        if let Some(updated) = updated {
+            ws_emit(
+                &self.app,
+                "channels:read_state",
+                json!({ "channel_id": message.channel_id, "state": updated }),
+            )
+            .await;
+        }
+    }
+
+    fn main_window_focused(&self) -> bool {
+        self.app
+            .get_webview_window("main")
+            .and_then(|window| window.is_focused().ok())
+            .unwrap_or(false)
+    }
+
+    /// Raises an OS notification for `message` when the window is not focused
+    /// and a rule matches. Desktop notifications carry no click callback, so
+    /// the message is announced as `notifications:pending` for the UI to offer
+    /// rather than opened.
+    async fn notify_message(&self, message: &MessageDto) {
+        if self.main_window_focused() {
+            return;
+        }
+        let Some(me) = self.current_user.read().await.clone() else {
+            return;
+        };
+        let channel = self.channels.read().await.get(&message.channel_id).cloned();
+        let is_private = channel.as_ref().is_some_and(|channel| channel.is_private);
+        let reason = notifications::evaluate(
+            &*self.notification_settings.read().await,
+            message,
+            &me,
+            is_private,
+            now_millis(),
+        );
+        let Some(reason) = reason else {
+            return;
+        };
+
+        // Runs inside the realtime loop, so the sender comes from memory only;
+        // an unknown one is fetched for the next notification.
+        let known = match &message.sender {
+            Some(sender) => Some(sender.name.clone()),
+            None => self
+                .user_directory
+                .read()
+                .await
+                .get(&message.workspace_id, &message.sender_id)
+                .map(|user| user.name.clone()),
+        };
+        let sender = match known {
+            Some(name) => name,
+            None => {
+                self.backfill_user_directory(
+                    &message.workspace_id,
+                    std::slice::from_ref(&message.sender_id),
+                )
+                .await;
+                "Galynx".to_string()
+            }
+        };
+        let title = match &channel {
+            Some(channel) if !channel.is_private => format!("{sender} en #{}", channel.name),
+            _ => sender,
+        };
+        let shown = self
+            .app
+            .notification()
+            .builder()
+            .title(title)
+            .body(notifications::notification_body(&message.body_md))
+            .show();
+        if let Err(err) = shown {
+            log::warn!("could not show notification: {err}");
+            return;
+        }
+
+        let target = json!({
+            "channel_id": message.channel_id,
+            "message_id": message.id,
+            "thread_root_id": message.thread_root_id,
+            "reason": reason,
+        });
+        ws_emit(&self.app, "notifications:pending", target).await;
+    }
+
+    async fn mark_channel_read(
+        &self,
+        channel_id: &str,
+        message_id: Option<String>,
+    ) -> Result<read_state::ChannelReadState, ApiError> {
+        let me = self
+            .current_user
+            .read()
+            .await
+            .clone()
+            .ok_or(ApiError::Unauthenticated)?;
+
+        let (message_id, read_at, unread_count, mention_count) = {
+            let cache = self.message_cache.read().await;
+            let in_channel = || {
+                cache.messages().filter(|message| {
+                    message.channel_id == channel_id
+                        && message.thread_root_id.is_none()
+                        && message.deleted_at.is_none()
+                })
+            };
+            let target = match &message_id {
+                Some(message_id) => cache.get(message_id),
+                None => in_channel().max_by_key(|message| message.created_at),
+            };
+            let read_at = target.map_or_else(now_millis, |message| message.created_at);
+            let message_id = message_id.or_else(|| target.map(|message| message.id.clone()));
+
+            let mut unread_count = 0;
+            let mut mention_count = 0;
+            for message in in_channel() {
+                if message.created_at > read_at && message.sender_id != me.id {
+                    unread_count += 1;
+                    if read_state::mentions_user(&message.body_md, &me) {
+                        mention_count += 1;
+                    }
+                }
+            }
+            (message_id, read_at, unread_count, mention_count)
+        };
+
+        let updated = self.read_state.write().await.mark_read(
+            &me.id,
+            channel_id,
+            message_id.clone(),
+            read_at,
+            unread_count,
+            mention_count,
+        );
+        self.persist_read_state().await;
+        self.sync_read_position(channel_id, message_id.as_deref(), read_at)
+            .await;
+        Ok(updated)
+    }
+
+    /// The API has no read-position endpoint yet; once it answers anything
+    /// but 404/405 the position is mirrored there too.
+    async fn sync_read_position(&self, channel_id: &str, message_id: Option<&str>, read_at: i64) {
+        if !self.read_sync_supported.load(Ordering::Relaxed) {
+            return;
+        }
+        let result = self
+            .send_json(
+                Method::POST,
+                &format!("/channels/{channel_id}/read"),
+                Some(json!({ "message_id": message_id, "read_at": read_at })),
+                true,
+            )
+            .await;
+        match result {
+            Ok(_) => {}
+            Err(ApiError::Http {
+                status: 404 | 405, ..
+            }) => self.read_sync_supported.store(false, Ordering::Relaxed),
+            Err(err) => log::info!("could not sync read position: {err}"),
+        }
+    }
+
+    /// Writes a `Snapshot::take_snapshot` result under `key`; `None` means
+    /// nothing changed and the store is left alone.
+    fn persist_store_key(&self, key: &str, snapshot: Option<JsonValue>) {
+        let Some(snapshot) = snapshot else {
+            return;
+        };
+        let saved = self.get_secure_store().and_then(|store| {
+            store.set(key, snapshot);
+            store
+                .save()
+                .map_err(|err| ApiError::Storage(format!("could not save {key}: {err}")))
+        });
+        if let Err(err) = saved {
+            log::warn!("could not persist {key}: {err}");
+        }
+    }
+
+    async fn persist_read_state(&self) {
+        let snapshot = self.read_state.write().await.take_snapshot();
+        self.persist_store_key(READ_STATE_STORE_KEY, snapshot);
+    }
+
+    async fn save_draft(
+        &self,
+        payload: DraftSavePayload,
+    ) -> Result<Option<drafts::DraftDto>, ApiError> {
+        let user_id = self.my_user_id().await.ok_or(ApiError::Unauthenticated)?;
+        let draft = self.drafts.write().await.save(
+            &user_id,
+            &payload.channel_id,
+            payload.root_id.as_deref(),
+            payload.body_md,
+            now_millis(),
+        );
+        self.persist_drafts().await;
+        Ok(draft)
+    }
+
+    /// Drops the draft a successful send was composed from.
+    async fn clear_sent_draft(&self, channel_id: Option<&str>, root_id: Option<&str>) {
+        let Some(user_id) = self.my_user_id().await else {
+            return;
+        };
+        {
+            let mut drafts = self.drafts.write().await;
+            if let Some(channel_id) = channel_id {
+                drafts.clear_channel(&user_id, channel_id);
+            }
+            if let Some(root_id) = root_id {
+                drafts.clear_thread(&user_id, root_id);
+            }
+        }
+        self.persist_drafts().await;
+    }
+
+    async fn persist_drafts(&self) {
+        let snapshot = self.drafts.write().await.take_snapshot();
+        self.persist_store_key(DRAFTS_STORE_KEY, snapshot);
+    }
+
+    async fn send_channel_message(
+        &self,
+        channel_id: &str,
+        body_md: &str,
+        attachments: Vec<AttachmentDto>,
+    ) -> Result<MessageDto, ApiError> {
+        self.ensure_channel_writable(channel_id).await?;
+        let body_md = self.normalize_mentions(body_md).await;
+        let mut body = json!({ "body_md": body_md });
+        if !attachments.is_empty() {
+            body["attachment_ids"] = attachments
+                .iter()
+                .map(|attachment| attachment.id.clone())
+                .collect();
+        }
+        let value = self
+            .send_json(
+                Method::POST,
+                &format!("/channels/{channel_id}/messages"),
+                Some(body),
+                true,
+            )
+            .await?;
+        let mut message: MessageDto = serde_json::from_value(value)
+            .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+        if message.attachments.is_empty() {
+            message.attachments = attachments;
+        }
+        let mut message = self.cache_message(message).await;
+        self.render_bodies(std::slice::from_mut(&mut message)).await;
+        Ok(message)
+    }
+
+    async fn schedule_message(
+        &self,
+        payload: MessagesSchedulePayload,
+    ) -> Result<scheduled::ScheduledMessageDto, ApiError> {
+        let user_id = self.my_user_id().await.ok_or(ApiError::Unauthenticated)?;
+        if payload.body_md.trim().is_empty() {
+            return Err(ApiError::validation(
+                "bad_request",
+                "scheduled message body is empty",
+            ));
+        }
+        self.ensure_channel_writable(&payload.channel_id).await?;
+        let created_at = now_millis();
+        let message = scheduled::ScheduledMessageDto {
+            id: next_client_msg_id(),
+            channel_id: payload.channel_id,
+            body_md: payload.body_md,
+            send_at: payload.send_at.max(created_at),
+            created_at,
+            attempts: 0,
+            last_error: None,
+            next_attempt_at: None,
+        };
+        self.scheduled_messages
+            .write()
+            .await
+            .schedule(&user_id, message.clone());
+        self.persist_scheduled_messages().await;
+        Ok(message)
+    }
+
+    /// Sends whatever is due for the signed-in user through the regular
+    /// `messages_send` path. Messages that were due while the app was closed
+    /// go out on the first tick after launch, flagged as `late`.
+    async fn send_due_scheduled_messages(&self) {
+        let Some(user_id) = self.my_user_id().await else {
+            return;
+        };
+        let now = now_millis();
+        let due = self.scheduled_messages.read().await.due(&user_id, now);
+        for scheduled in due {
+            // The user may have cancelled it since `due` was taken.
+            if !self
+                .scheduled_messages
+                .write()
+                .await
+                .claim(&user_id, &scheduled.id)
+            {
+                continue;
+            }
+            match self
+                .send_channel_message(&scheduled.channel_id, &scheduled.body_md, Vec::new())
+                .await
+            {
+                Ok(message) => {
+                    self.scheduled_messages
+                        .write()
+                        .await
+                        .remove(&user_id, &scheduled.id);
+                    // Persisted per message so a crash mid-batch cannot send
+                    // it again on the next start.
+                    self.persist_scheduled_messages().await;
+                    let late = scheduled.is_late(now);
+                    ws_emit(
+                        &self.app,
+                        "messages:scheduled_sent",
+                        ScheduledMessageSentDto {
+                            scheduled,
+                            message,
+                            late,
+                        },
+                    )
+                    .await;
+                }
+                Err(ApiError::Network(err)) => {
+                    log::info!(
+                        "scheduled message {} waits for network: {err}",
+                        scheduled.id
+                    );
+                    self.scheduled_messages.write().await.release(&scheduled.id);
+                    break;
+                }
+                Err(err) => {
+                    log::warn!("could not send scheduled message {}: {err}", scheduled.id);
+                    let updated = self.scheduled_messages.write().await.record_failure(
+                        &user_id,
+                        &scheduled.id,
+                        err.to_string(),
+                        now_millis(),
+                    );
+                    self.persist_scheduled_messages().await;
+                    if let Some(updated) = updated.filter(|message| message.is_failed()) {
+                        ws_emit(&self.app, "messages:scheduled_failed", updated).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn persist_scheduled_messages(&self) {
+        let snapshot = self.scheduled_messages.write().await.take_snapshot();
+        self.persist_store_key(SCHEDULED_MESSAGES_STORE_KEY, snapshot);
+    }
+
+    /// Lists pins from the API, or from the local pin book when the server
+    /// is unreachable or does not expose pins yet.
+    async fn list_pins(&self, channel_id: &str) -> Result<pins::PinListDto, ApiError> {
+        let user_id = self.my_user_id().await.ok_or(ApiError::Unauthenticated)?;
+        let (mut items, source) = match self
+            .send_json(
+                Method::GET,
+                &format!("/channels/{channel_id}/pins"),
+                None,
+                true,
+            )
+            .await
+        {
+            Ok(value) => {
+                let items = pins::parse_list(value)
+                    .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+                (
+                    self.sync_local_pins(&user_id, channel_id, items).await,
+                    pins::PinSource::Server,
+                )
+            }
+            Err(ApiError::Network(err)) => {
+                log::info!("pins offline, using local pins: {err}");
+                (
+                    self.local_pins.read().await.list(&user_id, channel_id),
+                    pins::PinSource::Local,
+                )
+            }
+            Err(err) if pins::is_route_missing(&err) => (
+                self.local_pins.read().await.list(&user_id, channel_id),
+                pins::PinSource::Local,
+            ),
+            Err(err) => return Err(err),
+        };
+
+        {
+            let cache = self.message_cache.read().await;
+            for pin in &mut items {
+                if pin.message.is_none() {
+                    pin.message = cache.get(&pin.message_id).cloned();
+                }
+            }
+        }
+        self.render_bodies(items.iter_mut().filter_map(|pin| pin.message.as_mut()))
+            .await;
+        Ok(pins::PinListDto { items, source })
+    }
+
+    /// Replays pins made while offline or before the server had pin
+    /// endpoints. Pins that still cannot be sent stay in the local book and
+    /// in the list, so they do not vanish once the server answers.
+    async fn sync_local_pins(
+        &self,
+        user_id: &str,
+        channel_id: &str,
+        mut items: Vec<pins::PinDto>,
+    ) -> Vec<pins::PinDto> {
+        let local = self.local_pins.read().await.list(user_id, channel_id);
+        if local.is_empty() {
+            return items;
+        }
+        let mut offline = false;
+        for pin in local {
+            let on_server = items.iter().any(|item| item.message_id == pin.message_id);
+            let mut synced = on_server;
+            if !on_server && !offline {
+                let result = self
+                    .send_json(
+                        Method::POST,
+                        &format!("/channels/{channel_id}/pins"),
+                        Some(json!({ "message_id": pin.message_id })),
+                        true,
+                    )
+                    .await;
+                match result {
+                    Ok(_) | Err(ApiError::Http { status: 409, .. }) => synced = true,
+                    Err(ApiError::Network(err)) => {
+                        log::info!("pins offline again, keeping local pins: {err}");
+                        offline = true;
+                    }
+                    Err(err) => log::warn!("could not sync local pin {}: {err}", pin.message_id),
+                }
+            }
+            if synced {
+                self.local_pins
+                    .write()
+                    .await
+                    .remove(user_id, channel_id, &pin.message_id);
+            }
+            if !on_server {
+                items.push(pin);
+            }
+        }
+        self.persist_local_pins().await;
+        items.sort_by(|a, b| b.pinned_at.cmp(&a.pinned_at));
+        items
+    }
+
+    async fn pin_message(
+        &self,
+        channel_id: &str,
+        message_id: &str,
+    ) -> Result<pins::PinDto, ApiError> {
+        self.ensure_channel_writable(channel_id).await?;
+        let user_id = self.my_user_id().await.ok_or(ApiError::Unauthenticated)?;
+        let local_pin = pins::PinDto {
+            message_id: message_id.to_string(),
+            channel_id: channel_id.to_string(),
+            pinned_by: Some(user_id.clone()),
+            pinned_at: now_millis(),
+            message: None,
+        };
+        let result = self
+            .send_json(
+                Method::POST,
+                &format!("/channels/{channel_id}/pins"),
+                Some(json!({ "message_id": message_id })),
+                true,
+            )
+            .await;
+        let (mut pin, local) = match result {
+            Ok(value) => (
+                serde_json::from_value::<pins::PinDto>(value)
+                    .map_err(|err| ApiError::InvalidResponse(err.to_string()))?,
+                false,
+            ),
+            Err(ApiError::Network(err)) => {
+                log::info!("pinning offline, keeping pin locally: {err}");
+                (self.local_pins.write().await.add(&user_id, local_pin), true)
+            }
+            Err(err) if pins::is_route_missing(&err) => {
+                (self.local_pins.write().await.add(&user_id, local_pin), true)
+            }
+            Err(err) => return Err(err),
+        };
+
+        pin.message = self.message_cache.read().await.get(message_id).cloned();
+        self.render_bodies(pin.message.as_mut()).await;
+        // Pins the server accepted come back as a `PIN_ADDED` broadcast,
+        // which `apply_pin_event` forwards; only local ones are announced here.
+        if local {
+            self.persist_local_pins().await;
+            ws_emit(
+                &self.app,
+                "pins:updated",
+                json!({
+                    "channel_id": channel_id,
+                    "message_id": message_id,
+                    "pinned": true,
+                    "pin": pin,
+                }),
+            )
+            .await;
+        }
+        Ok(pin)
+    }
+
+    async fn unpin_message(&self, channel_id: &str, message_id: &str) -> Result<(), ApiError> {
+        self.ensure_channel_writable(channel_id).await?;
+        let user_id = self.my_user_id().await.ok_or(ApiError::Unauthenticated)?;
+        let result = self
+            .send_json(
+                Method::DELETE,
+                &format!("/channels/{channel_id}/pins/{message_id}"),
+                None,
+                true,
+            )
+            .await;
+        let is_local = self
+            .local_pins
+            .read()
+            .await
+            .list(&user_id, channel_id)
+            .iter()
+            .any(|pin| pin.message_id == message_id);
+        let broadcast = match result {
+            Ok(_) => true,
+            // Not pinned on the server, or no pin routes there.
+            Err(ApiError::Http { status: 404, .. }) => false,
+            Err(err) if pins::is_route_missing(&err) => false,
+            // A pin made while offline only lives in the local book, so it
+            // can go; one on the server stays until the server removes it.
+            Err(ApiError::Network(err)) if is_local => {
+                log::info!("unpinning offline, removing local pin: {err}");
+                false
+            }
+            Err(err) => return Err(err),
+        };
+        if is_local {
+            self.local_pins
+                .write()
+                .await
+                .remove(&user_id, channel_id, message_id);
+            self.persist_local_pins().await;
+        }
+
+        // The server announces its own unpins with `PIN_REMOVED`.
+        if !broadcast {
+            ws_emit(
+                &self.app,
+                "pins:updated",
+                json!({ "channel_id": channel_id, "message_id": message_id, "pinned": false }),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Forwards `PIN_ADDED`/`PIN_REMOVED` broadcasts as `pins:updated`, the
+    /// same event local pin changes produce.
+    async fn apply_pin_event(&self, pinned: bool, payload: &JsonValue) {
+        let pin = payload.get("pin").unwrap_or(payload);
+        let (Some(channel_id), Some(message_id)) = (
+            pin.get("channel_id").and_then(JsonValue::as_str),
+            pin.get("message_id").and_then(JsonValue::as_str),
+        ) else {
+            return;
+        };
+        let mut update = json!({
+            "channel_id": channel_id,
+            "message_id": message_id,
+            "pinned": pinned,
+        });
+        if pinned {
+            if let Ok(mut pin) = serde_json::from_value::<pins::PinDto>(pin.clone()) {
+                if pin.message.is_none() {
+                    pin.message = self.message_cache.read().await.get(message_id).cloned();
+                }
+                self.render_cached_bodies(pin.message.as_mut()).await;
+                update["pin"] = json!(pin);
+            }
+        }
+        ws_emit(&self.app, "pins:updated", update).await;
+    }
+
+    async fn persist_local_pins(&self) {
+        let snapshot = self.local_pins.write().await.take_snapshot();
+        self.persist_store_key(LOCAL_PINS_STORE_KEY, snapshot);
+    }
+
+    async fn save_item(&self, message_id: &str) -> Result<saved_items::SavedItemDto, ApiError> {
+        let user_id = self.my_user_id().await.ok_or(ApiError::Unauthenticated)?;
+        let message = self
+            .message_cache
+            .read()
+            .await
+            .get(message_id)
+            .cloned()
+            .ok_or_else(|| ApiError::validation("message_not_loaded", "message is not loaded"))?;
+        let mut item = self
+            .saved_items
+            .write()
+            .await
+            .save(&user_id, message, now_millis());
+        self.persist_saved_items().await;
+        self.render_bodies(std::slice::from_mut(&mut item.message))
+            .await;
+        Ok(item)
+    }
+
+    async fn persist_saved_items(&self) {
+        let snapshot = self.saved_items.write().await.take_snapshot();
+        self.persist_store_key(SAVED_ITEMS_STORE_KEY, snapshot);
+    }
+
+    async fn with_read_state(&self, mut channels: Vec<ChannelDto>) -> Vec<ChannelDto> {
+        let Some(user_id) = self.my_user_id().await else {
+            return channels;
+        };
+        let book = self.read_state.read().await;
+        for channel in &mut channels {
+            let state = book.channel(&user_id, &channel.id);
+            channel.unread_count = state.unread_count;
+            channel.mention_count = state.mention_count;
+            channel.last_read_at = state.last_read_at;
+        }
+        channels
+    }
+
+    async fn forget_message(&self, message_id: &str) {
+        self.message_cache.write().await.remove(message_id);
+        self.search_index.write().await.remove(message_id);
+        self.saved_items.write().await.mark_deleted(message_id);
+    }
+
+    async fn forget_cached_messages(&self) {
+        *self.message_cache.write().await =
+            message_cache::MessageCache::new(message_cache::MESSAGE_CACHE_CAPACITY);
+        self.search_index.write().await.clear();
+        self.flush_search_index().await;
+    }
+
+    async fn flush_search_index(&self) {
+        let Some(snapshot) = self.search_index.write().await.take_snapshot() else {
+            return;
+        };
+        let path = match search_index::index_path(&self.app) {
+            Ok(path) => path,
+            Err(err) => {
+                log::warn!("could not persist search index: {err}");
+                return;
+            }
+        };
+        let written = tokio::task::spawn_blocking(move || {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, xor_crypt(&snapshot))
+        })
+        .await;
+        if let Ok(Err(err)) = written {
+            log::warn!("could not persist search index: {err}");
+        }
+    }
+
+    /// Searches through the API and falls back to the messages cached locally
+    /// when the server is unreachable or does not expose search yet.
+    async fn search_messages(
+        &self,
+        payload: MessageSearchPayload,
+    ) -> Result<search::MessageSearchResultDto, ApiError> {
+        let query = payload.query.trim();
+        if query.is_empty() {
+            return Err(ApiError::validation(
+                "bad_request",
+                "search query must not be empty",
+            ));
+        }
+        let limit = payload
+            .limit
+            .unwrap_or(search::DEFAULT_SEARCH_LIMIT)
+            .clamp(1, search::MAX_SEARCH_LIMIT);
+        let filters = &payload.filters;
+        let local_cursor = payload
+            .cursor
+            .as_deref()
+            .is_some_and(|cursor| cursor.starts_with("local:"));
+
+        if !local_cursor {
+            let path = search::server_search_path(query, filters, limit, payload.cursor.as_deref());
+
+            match self.send_json(Method::GET, &path, None, true).await {
+                Ok(value) => {
+                    let list: MessageListDto = serde_json::from_value(value)
+                        .map_err(|err| ApiError::InvalidResponse(err.to_string()))?;
+                    self.remember_message_download_urls(&list.items).await;
+                    let terms = search::query_terms(query);
+                    let mut messages = self.cache_messages(list.items).await;
+                    self.render_bodies(&mut messages).await;
+                    let items = messages
+                        .into_iter()
+                        .map(|message| search::to_hit(message, &terms))
+                        .collect();
+                    return Ok(search::MessageSearchResultDto {
+                        items,
+                        next_cursor: list.next_cursor,
+                        source: search::SearchSource::Server,
+                    });
+                }
+                Err(ApiError::Network(err)) => {
+                    log::info!("message search offline, using local cache: {err}");
+                }
+                Err(ApiError::Http { status: 404, .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut result = search::search_local(
+            self.message_cache.read().await.messages(),
+            query,
+            filters,
+            limit,
+            payload.cursor.as_deref(),
+        );
+        self.render_bodies(result.items.iter_mut().map(|hit| &mut hit.message))
+            .await;
+        Ok(result)
+    }
+
+    /// Sends a client command over the open realtime socket and waits for the
+    /// matching `ACK`/`ERROR`, returning `payload.result`.
+    async fn send_ws_command(
+        &self,
+        command: &str,
+        payload: JsonValue,
+    ) -> Result<JsonValue, ApiError> {
+        let outbox = self
+            .ws_outbox
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| ApiError::Realtime("realtime connection is not open".to_string()))?;
+
+        let client_msg_id = next_client_msg_id();
+        let (tx, rx) = oneshot::channel();
+        self.ws_pending
+            .lock()
+            .await
+            .insert(client_msg_id.clone(), tx);
+
+        let frame = json!({
+            "command": command,
+            "client_msg_id": client_msg_id,
+            "payload": payload,
+        });
+        if outbox.send(frame.to_string()).is_err() {
+            self.ws_pending.lock().await.remove(&client_msg_id);
+            return Err(ApiError::Realtime(
+                "realtime connection is not open".to_string(),
+            ));
+        }
+
+        match tokio::time::timeout(WS_COMMAND_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(ApiError::Realtime(format!(
+                "connection closed before {command} was acknowledged"
+            ))),
+            Err(_) => {
+                self.ws_pending.lock().await.remove(&client_msg_id);
+                Err(ApiError::Realtime(format!("{command} timed out")))
+            }
+        }
+    }
+
+    async fn fail_pending_ws_commands(&self) {
+        for (_, tx) in self.ws_pending.lock().await.drain() {
+            let _ = tx.send(Err(ApiError::Realtime(
+                "realtime connection closed".to_string(),
+            )));
+        }
+    }
+
+    async fn resolve_ws_command(&self, event: &JsonValue, result: Result<JsonValue, ApiError>) {
+        let mut pending = self.ws_pending.lock().await;
+        let key = match event.get("correlation_id").and_then(JsonValue::as_str) {
+            Some(id) => Some(id.to_string()),
+            // ERROR frames may omit the correlation id; only a single
+            // in-flight command can be blamed safely.
+            None if pending.len() == 1 => pending.keys().next().cloned(),
+            None => None,
+        };
+        if let Some(tx) = key.and_then(|key| pending.remove(&key)) {
+            let _ = tx.send(result);
+        }
+    }
+
+    /// Applies a realtime envelope to local state before it is forwarded to
+    /// the webview.
+    async fn apply_realtime_event(&self, event: &mut JsonValue) {
+        let event_type = event
+            .get("event_type")
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default();
+        let payload = event.get("payload").cloned().unwrap_or(JsonValue::Null);
+
+        match event_type {
+            "ACK" => {
+                let result = payload.get("result").cloned().unwrap_or(JsonValue::Null);
+                self.resolve_ws_command(event, Ok(result)).await;
+            }
+            "ERROR" => {
+                let status = payload
+                    .get("status")
+                    .and_then(JsonValue::as_u64)
+                    .unwrap_or(500) as u16;
+                let message = payload
+                    .get("error")
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or("realtime command failed")
+                    .to_string();
+                self.resolve_ws_command(
+                    event,
+                    Err(ApiError::Http {
+                        status,
+                        error: "realtime_error".to_string(),
+                        message,
+                    }),
+                )
+                .await;
+            }
+            "MESSAGE_CREATED" => {
+                let message = payload.get("message").cloned().unwrap_or(payload);
+                if let Ok(message) = serde_json::from_value::<MessageDto>(message) {
+                    let mut message = self.cache_message(message).await;
+                    self.render_cached_bodies(std::slice::from_mut(&mut message))
+                        .await;
+                    replace_event_message(event, &message);
+                    self.count_unread(&message).await;
+                    self.notify_message(&message).await;
+                }
+            }
+            "MESSAGE_UPDATED" => {
+                let message = payload.get("message").cloned().unwrap_or(payload);
+                if let Ok(message) = serde_json::from_value::<MessageDto>(message) {
+                    let mut message = self.cache_message(message).await;
+                    self.render_cached_bodies(std::slice::from_mut(&mut message))
+                        .await;
+                    replace_event_message(event, &message);
+                }
+            }
+            "MESSAGE_DELETED" => {
+                if let Some(message_id) = payload
+                    .get("message_id")
+                    .or_else(|| payload.get("id"))
+                    .and_then(JsonValue::as_str)
+                {
+                    self.forget_message(message_id).await;
+                }
+            }
+            "REACTION_UPDATED" => self.apply_reaction_event(&payload).await,
+            "CHANNEL_UPDATED" | "CHANNEL_ARCHIVED" | "CHANNEL_UNARCHIVED" => {
+                self.apply_channel_event(event_type, &payload).await
+            }
+            "PIN_ADDED" => self.apply_pin_event(true, &payload).await,
+            "PIN_REMOVED" => self.apply_pin_event(false, &payload).await,
+            _ => {}
+        }
+    }
+
+    async fn apply_reaction_event(&self, payload: &JsonValue) {
+        let Some(message_id) = payload.get("message_id").and_then(JsonValue::as_str) else {
+            return;
+        };
+        let my_user_id = self.my_user_id().await;
+        let mut cache = self.message_cache.write().await;
+        let Some(message) = cache.get_mut(message_id) else {
+            return;
+        };
+
+        if let Some(summary) = payload.get("reactions") {
+            message.reactions = Some(reactions::parse_summary(summary, my_user_id.as_deref()));
+        } else if let Some((emoji, user_id, change)) = reactions::parse_change(payload) {
+            reactions::apply_change(
+                message.reactions.get_or_insert_with(Vec::new),
+                &emoji,
+                &user_id,
+                change,
+                my_user_id.as_deref(),
+            );
+        } else {
+            return;
+        }
+
+        let update = json!({ "message_id": message_id, "reactions": message.reactions });
+        drop(cache);
+        ws_emit(&self.app, "messages:reactions_updated", update).await;
+    }
+
+    async fn change_reaction(
+        &self,
+        message_id: &str,
+        emoji: &str,
+        change: reactions::ReactionChange,
+    ) -> Result<Vec<reactions::ReactionSummaryDto>, ApiError> {
+        let emoji = emoji.trim();
+        if emoji.is_empty() {
+            return Err(ApiError::validation(
+                "bad_request",
+                "emoji must not be empty",
+            ));
+        }
+        self.ensure_message_writable(message_id).await?;
+        let command = match change {
+            reactions::ReactionChange::Added => "ADD_REACTION",
+            reactions::ReactionChange::Removed => "REMOVE_REACTION",
+        };
+        self.send_ws_command(command, json!({ "message_id": message_id, "emoji": emoji }))
+            .await?;
+
+        let my_user_id = self.my_user_id().await;
+        let mut cache = self.message_cache.write().await;
+        let Some(message) = cache.get_mut(message_id) else {
+            return Ok(Vec::new());
+        };
+        if let Some(user_id) = my_user_id.as_deref() {
+            reactions::apply_change(
+                message.reactions.get_or_insert_with(Vec::new),
+                emoji,
+                user_id,
+                change,
+                Some(user_id),
+            );
+        }
+        Ok(message.reactions.clone().unwrap_or_default())
+    }
+}
+
+#[tauri::command]
+async fn auth_login(
+    state: State<'_, AppState>,
+    payload: LoginPayload,
+) -> CmdResult<AuthSessionDto> {
+    let device = state.device_for_login(payload.device_name.as_deref()).await;
+    let mut body = json!({
+      "email": payload.email,
+      "password": payload.password,
+      "device_id": device.device_id,
+      "device_name": device.device_name
+    });
+    if let Some(workspace_id) = payload.workspace_id {
+        body["workspace_id"] = JsonValue::String(workspace_id);
+    }
+
+    let tokens_value = state
+        .send_json(Method::POST, "/auth/login", Some(body), false)
+        .await
+        .map_err(ApiErrorDto::from)?;
+
+    state
+        .start_session(tokens_value)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn auth_accept_invite(
+    state: State<'_, AppState>,
+    payload: AcceptInvitePayload,
+) -> CmdResult<AuthSessionDto> {
+    state
+        .accept_invite(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+/// The invite token from a deep link that arrived before the frontend was
+/// listening for `auth:invite_link`.
+#[tauri::command]
+async fn auth_pending_invite(state: State<'_, AppState>) -> CmdResult<Option<String>> {
+    Ok(state.pending_invite.lock().await.take())
+}
+
+#[tauri::command]
+async fn auth_change_password(
+    state: State<'_, AppState>,
+    payload: ChangePasswordPayload,
+) -> CmdResult<()> {
+    state
+        .change_password(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn auth_request_reset(
+    state: State<'_, AppState>,
+    payload: RequestResetPayload,
+) -> CmdResult<()> {
+    state
+        .request_password_reset(&payload.email)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn auth_confirm_reset(
+    state: State<'_, AppState>,
+    payload: ConfirmResetPayload,
+) -> CmdResult<()> {
+    state
+        .confirm_password_reset(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn sessions_list(state: State<'_, AppState>) -> CmdResult<Vec<sessions::SessionDto>> {
+    state.list_sessions().await.map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn sessions_revoke(
+    state: State<'_, AppState>,
+    payload: SessionIdPayload,
+) -> CmdResult<Vec<sessions::SessionDto>> {
+    state
+        .revoke_session(&payload.session_id)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn sessions_revoke_others(
+    state: State<'_, AppState>,
+) -> CmdResult<Vec<sessions::SessionDto>> {
+    state
+        .revoke_other_sessions()
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn auth_me(state: State<'_, AppState>) -> CmdResult<UserDto> {
+    let me_value = state
+        .send_json(Method::GET, "/me", None, true)
+        .await
+        .map_err(ApiErrorDto::from)?;
+    let user: UserDto = serde_json::from_value(me_value)
+        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+    *state.current_user.write().await = Some(user.clone());
+    Ok(user)
+}
+
+#[tauri::command]
+async fn auth_logout(state: State<'_, AppState>) -> CmdResult<()> {
+    if let Ok(tokens) = state.require_tokens().await {
+        let _ = state
+            .send_json(
+                Method::POST,
+                "/auth/logout",
+                Some(json!({ "refresh_token": tokens.refresh_token })),
+                false,
+            )
+            .await;
+    }
+
+    state.clear_tokens().await.map_err(ApiErrorDto::from)?;
+    state.forget_cached_messages().await;
+    state.user_directory.write().await.clear();
+    state.channel_members.write().await.clear();
+    Ok(())
+}
+
+#[tauri::command]
+async fn channels_list(
+    state: State<'_, AppState>,
+    payload: Option<ChannelsListPayload>,
+) -> CmdResult<Vec<ChannelDto>> {
+    let mut channels = state.refresh_channels().await.map_err(ApiErrorDto::from)?;
+    channels.retain(|channel| !dm::is_dm_channel(channel));
+    let channels = channels::visible(channels, payload.unwrap_or_default().include_archived);
+    Ok(state.with_read_state(channels).await)
+}
+
+#[tauri::command]
+async fn channels_mark_read(
+    state: State<'_, AppState>,
+    payload: ChannelMarkReadPayload,
+) -> CmdResult<read_state::ChannelReadState> {
+    state
+        .mark_channel_read(&payload.channel_id, payload.message_id)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn channels_create(
+    state: State<'_, AppState>,
+    payload: CreateChannelPayload,
+) -> CmdResult<ChannelDto> {
+    let name = channels::normalize_name(&payload.name).map_err(ApiErrorDto::from)?;
+    state
+        .create_channel(&name, payload.is_private)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn channels_delete(
     state: State<'_, AppState>,
     payload: DeleteChannelPayload,
 ) -> CmdResult<()> {
@@ -849,9 +3985,56 @@ async fn channels_delete(
         )
         .await
         .map_err(ApiErrorDto::from)?;
+    state.channels.write().await.remove(&payload.channel_id);
     Ok(())
 }
 
+#[tauri::command]
+async fn channels_rename(
+    state: State<'_, AppState>,
+    payload: ChannelRenamePayload,
+) -> CmdResult<ChannelDto> {
+    let name = channels::normalize_name(&payload.name).map_err(ApiErrorDto::from)?;
+    state
+        .update_channel(&payload.channel_id, json!({ "name": name }))
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn channels_set_topic(
+    state: State<'_, AppState>,
+    payload: ChannelTopicPayload,
+) -> CmdResult<ChannelDto> {
+    let topic = channels::normalize_topic(payload.topic.as_deref()).map_err(ApiErrorDto::from)?;
+    state
+        .update_channel(&payload.channel_id, json!({ "topic": topic }))
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn channels_archive(
+    state: State<'_, AppState>,
+    payload: ChannelIdPayload,
+) -> CmdResult<ChannelDto> {
+    state
+        .update_channel(&payload.channel_id, json!({ "archived": true }))
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn channels_unarchive(
+    state: State<'_, AppState>,
+    payload: ChannelIdPayload,
+) -> CmdResult<ChannelDto> {
+    state
+        .update_channel(&payload.channel_id, json!({ "archived": false }))
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
 #[tauri::command]
 async fn channel_members_list(
     state: State<'_, AppState>,
@@ -876,15 +4059,25 @@ async fn channel_members_add(
     payload: ChannelMemberPayload,
 ) -> CmdResult<()> {
     state
-        .send_json(
-            Method::POST,
-            &format!("/channels/{}/members", payload.channel_id),
-            Some(json!({ "user_id": payload.user_id })),
-            true,
-        )
+        .add_channel_member(&payload.channel_id, &payload.user_id)
         .await
-        .map_err(ApiErrorDto::from)?;
-    Ok(())
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn dm_open(
+    state: State<'_, AppState>,
+    payload: DmOpenPayload,
+) -> CmdResult<dm::DmConversationDto> {
+    state
+        .open_dm(&payload.user_ids)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn dm_list(state: State<'_, AppState>) -> CmdResult<Vec<dm::DmConversationDto>> {
+    state.list_dms().await.map_err(ApiErrorDto::from)
 }
 
 #[tauri::command]
@@ -944,8 +4137,16 @@ async fn workspace_members_list(
         )
         .await
         .map_err(ApiErrorDto::from)?;
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
+    let members: Vec<JsonValue> = serde_json::from_value(value)
+        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+
+    state.user_directory.write().await.merge(
+        &payload.workspace_id,
+        members
+            .iter()
+            .filter_map(|member| user_directory::user_from_member(&payload.workspace_id, member)),
+    );
+    Ok(members)
 }
 
 #[tauri::command]
@@ -976,14 +4177,182 @@ async fn workspace_members_upsert(
     Ok(())
 }
 
+#[tauri::command]
+async fn workspace_members_set_role(
+    state: State<'_, AppState>,
+    payload: WorkspaceMemberRolePayload,
+) -> CmdResult<()> {
+    state
+        .set_member_role(&payload.workspace_id, &payload.user_id, payload.role)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn workspace_members_remove(
+    state: State<'_, AppState>,
+    payload: WorkspaceMemberPayload,
+) -> CmdResult<()> {
+    state
+        .remove_member(&payload.workspace_id, &payload.user_id)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn users_disable(state: State<'_, AppState>, payload: UserIdPayload) -> CmdResult<()> {
+    state
+        .set_user_status(&payload.user_id, roles::UserStatus::Disabled)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn users_enable(state: State<'_, AppState>, payload: UserIdPayload) -> CmdResult<()> {
+    state
+        .set_user_status(&payload.user_id, roles::UserStatus::Active)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn users_import_csv(
+    state: State<'_, AppState>,
+    payload: UsersImportCsvPayload,
+) -> CmdResult<user_import::ImportReportDto> {
+    state
+        .import_users_csv(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn permissions_for_context(
+    state: State<'_, AppState>,
+    payload: Option<PermissionsContextPayload>,
+) -> CmdResult<permissions::PermissionsDto> {
+    state
+        .permissions_for(payload.unwrap_or_default())
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn invites_create(
+    state: State<'_, AppState>,
+    payload: InviteCreatePayload,
+) -> CmdResult<invites::InviteDto> {
+    state
+        .create_invite(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn invites_list(state: State<'_, AppState>) -> CmdResult<Vec<invites::InviteDto>> {
+    state.list_invites().await.map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn invites_revoke(state: State<'_, AppState>, payload: InviteIdPayload) -> CmdResult<()> {
+    state
+        .revoke_invite(&payload.invite_id)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
 #[tauri::command]
 async fn users_list(state: State<'_, AppState>) -> CmdResult<Vec<UserDto>> {
     let value = state
         .send_json(Method::GET, "/users", None, true)
         .await
         .map_err(ApiErrorDto::from)?;
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
+    let users: Vec<UserDto> = serde_json::from_value(value)
+        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+
+    let mut directory = state.user_directory.write().await;
+    for user in &users {
+        directory.merge(&user.workspace_id, [user.clone()]);
+    }
+    Ok(users)
+}
+
+#[tauri::command]
+async fn users_resolve(
+    state: State<'_, AppState>,
+    payload: UsersResolvePayload,
+) -> CmdResult<Vec<UserDto>> {
+    let workspace_id = match payload.workspace_id {
+        Some(workspace_id) => workspace_id,
+        None => state
+            .current_user
+            .read()
+            .await
+            .as_ref()
+            .map(|user| user.workspace_id.clone())
+            .ok_or_else(|| ApiErrorDto::from(ApiError::Unauthenticated))?,
+    };
+    let mut user_ids = payload.user_ids;
+    user_ids.sort();
+    user_ids.dedup();
+    Ok(state.resolve_users(&workspace_id, &user_ids).await)
+}
+
+#[tauri::command]
+async fn drafts_save(
+    state: State<'_, AppState>,
+    payload: DraftSavePayload,
+) -> CmdResult<Option<drafts::DraftDto>> {
+    state.save_draft(payload).await.map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn drafts_get(
+    state: State<'_, AppState>,
+    payload: DraftGetPayload,
+) -> CmdResult<Option<drafts::DraftDto>> {
+    let Some(user_id) = state.my_user_id().await else {
+        return Ok(None);
+    };
+    Ok(state
+        .drafts
+        .read()
+        .await
+        .get(&user_id, &payload.channel_id, payload.root_id.as_deref()))
+}
+
+#[tauri::command]
+async fn drafts_list(state: State<'_, AppState>) -> CmdResult<Vec<drafts::DraftDto>> {
+    let Some(user_id) = state.my_user_id().await else {
+        return Ok(Vec::new());
+    };
+    Ok(state.drafts.read().await.list(&user_id))
+}
+
+#[tauri::command]
+async fn markdown_render(
+    state: State<'_, AppState>,
+    payload: MarkdownRenderPayload,
+) -> CmdResult<String> {
+    let (users, channels) = state.mention_directory().await.unwrap_or_default();
+    Ok(markdown::render(
+        &payload.body_md,
+        markdown::MentionContext {
+            users: &users,
+            channels: &channels,
+        },
+    ))
+}
+
+#[tauri::command]
+async fn mentions_suggest(
+    state: State<'_, AppState>,
+    payload: MentionsSuggestPayload,
+) -> CmdResult<Vec<mentions::MentionSuggestionDto>> {
+    state
+        .suggest_mentions(payload)
+        .await
+        .map_err(ApiErrorDto::from)
 }
 
 #[tauri::command]
@@ -1029,8 +4398,13 @@ async fn messages_list(
         .await
         .map_err(ApiErrorDto::from)?;
 
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
+    let mut list: MessageListDto = serde_json::from_value(value)
+        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+    state.remember_message_download_urls(&list.items).await;
+    list.items = state.cache_messages(list.items).await;
+    state.attach_senders(&mut list.items).await;
+    state.render_bodies(&mut list.items).await;
+    Ok(list)
 }
 
 #[tauri::command]
@@ -1038,78 +4412,229 @@ async fn messages_send(
     state: State<'_, AppState>,
     payload: SendMessagePayload,
 ) -> CmdResult<MessageDto> {
-    let value = state
-        .send_json(
-            Method::POST,
-            &format!("/channels/{}/messages", payload.channel_id),
-            Some(json!({ "body_md": payload.body_md })),
-            true,
-        )
+    let message = state
+        .send_channel_message(&payload.channel_id, &payload.body_md, Vec::new())
+        .await
+        .map_err(ApiErrorDto::from)?;
+    state
+        .clear_sent_draft(Some(&payload.channel_id), None)
+        .await;
+    Ok(message)
+}
+
+#[tauri::command]
+async fn messages_schedule(
+    state: State<'_, AppState>,
+    payload: MessagesSchedulePayload,
+) -> CmdResult<scheduled::ScheduledMessageDto> {
+    state
+        .schedule_message(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn messages_scheduled_list(
+    state: State<'_, AppState>,
+) -> CmdResult<Vec<scheduled::ScheduledMessageDto>> {
+    let Some(user_id) = state.my_user_id().await else {
+        return Ok(Vec::new());
+    };
+    Ok(state.scheduled_messages.read().await.list(&user_id))
+}
+
+#[tauri::command]
+async fn messages_schedule_cancel(
+    state: State<'_, AppState>,
+    payload: ScheduledMessageIdPayload,
+) -> CmdResult<bool> {
+    let user_id = state
+        .my_user_id()
+        .await
+        .ok_or_else(|| ApiErrorDto::from(ApiError::Unauthenticated))?;
+    let removed = {
+        let mut queue = state.scheduled_messages.write().await;
+        if queue.is_sending(&payload.id) {
+            return Err(ApiErrorDto::from(ApiError::validation(
+                "scheduled_sending",
+                "the message is being sent",
+            )));
+        }
+        queue.remove(&user_id, &payload.id).is_some()
+    };
+    state.persist_scheduled_messages().await;
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn messages_send_with_attachments(
+    state: State<'_, AppState>,
+    payload: SendMessageWithAttachmentsPayload,
+) -> CmdResult<MessageDto> {
+    let message = state
+        .send_message_with_attachments(payload)
+        .await
+        .map_err(ApiErrorDto::from)?;
+    state
+        .clear_sent_draft(Some(&message.channel_id), None)
+        .await;
+    ws_emit(&state.app, "messages:sent", &message).await;
+    Ok(message)
+}
+
+#[tauri::command]
+async fn attachments_upload_commit(
+    state: State<'_, AppState>,
+    payload: AttachmentUploadPayload,
+) -> CmdResult<AttachmentDto> {
+    state
+        .upload_attachment_for_message(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+fn batch_upload_concurrency(requested: Option<usize>) -> usize {
+    requested
+        .unwrap_or(BATCH_UPLOAD_DEFAULT_CONCURRENCY)
+        .clamp(1, BATCH_UPLOAD_MAX_CONCURRENCY)
+}
+
+/// Uploads `files` with at most `concurrency` in flight. A failed file is
+/// reported in its item and never stops the others; `on_progress` sees one
+/// update per finished file, in completion order.
+async fn run_upload_batch<U, UFut, P, PFut>(
+    batch_id: String,
+    message_id: String,
+    files: Vec<AttachmentFilePayload>,
+    concurrency: usize,
+    mut upload: U,
+    mut on_progress: P,
+) -> AttachmentBatchResultDto
+where
+    U: FnMut(AttachmentFilePayload) -> UFut,
+    UFut: std::future::Future<Output = Result<AttachmentDto, ApiError>>,
+    P: FnMut(AttachmentBatchProgressDto) -> PFut,
+    PFut: std::future::Future<Output = ()>,
+{
+    let total = files.len();
+    let bytes_total: i64 = files.iter().map(|file| file.size_bytes).sum();
+
+    let uploads = files.into_iter().enumerate().map(|(index, file)| {
+        let filename = file.filename.clone();
+        let size_bytes = file.size_bytes;
+        let upload = upload(file);
+        async move { (index, filename, size_bytes, upload.await) }
+    });
+    let mut completions = futures_util::stream::iter(uploads).buffer_unordered(concurrency);
+
+    let mut items = Vec::with_capacity(total);
+    let mut progress = AttachmentBatchProgressDto {
+        batch_id: batch_id.clone(),
+        message_id,
+        total,
+        completed: 0,
+        failed: 0,
+        bytes_total,
+        bytes_completed: 0,
+    };
+    while let Some((index, filename, size_bytes, result)) = completions.next().await {
+        progress.bytes_completed += size_bytes;
+        let (attachment, error) = match result {
+            Ok(attachment) => {
+                progress.completed += 1;
+                (Some(attachment), None)
+            }
+            Err(err) => {
+                log::warn!("batch upload of {filename} failed: {err}");
+                progress.failed += 1;
+                (None, Some(ApiErrorDto::from(err)))
+            }
+        };
+        items.push(AttachmentBatchItemDto {
+            index,
+            filename,
+            attachment,
+            error,
+        });
+        on_progress(progress.clone()).await;
+    }
+    items.sort_by_key(|item| item.index);
+
+    AttachmentBatchResultDto {
+        batch_id,
+        message_id: progress.message_id,
+        uploaded: progress.completed,
+        failed: progress.failed,
+        items,
+    }
+}
+
+#[tauri::command]
+async fn attachments_upload_batch(
+    state: State<'_, AppState>,
+    payload: AttachmentBatchUploadPayload,
+) -> CmdResult<AttachmentBatchResultDto> {
+    let batch_id = payload
+        .batch_id
+        .unwrap_or_else(|| payload.message_id.clone());
+    let channel_id = payload.channel_id;
+    let message_id = payload.message_id;
+    let state = state.inner();
+
+    Ok(run_upload_batch(
+        batch_id,
+        message_id.clone(),
+        payload.files,
+        batch_upload_concurrency(payload.concurrency),
+        |file| {
+            state.upload_attachment_for_message(AttachmentUploadPayload {
+                channel_id: channel_id.clone(),
+                message_id: message_id.clone(),
+                filename: file.filename,
+                content_type: file.content_type,
+                size_bytes: file.size_bytes,
+                bytes: file.bytes,
+            })
+        },
+        |progress| ws_emit(&state.app, "attachments:batch_progress", progress),
+    )
+    .await)
+}
+
+#[tauri::command]
+async fn attachment_get(
+    state: State<'_, AppState>,
+    payload: AttachmentGetPayload,
+) -> CmdResult<AttachmentDto> {
+    state
+        .fetch_attachment(&payload.attachment_id)
         .await
-        .map_err(ApiErrorDto::from)?;
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
+        .map_err(ApiErrorDto::from)
 }
 
 #[tauri::command]
-async fn attachments_upload_commit(
+async fn attachment_resolve_url(
     state: State<'_, AppState>,
-    payload: AttachmentUploadPayload,
-) -> CmdResult<AttachmentDto> {
+    payload: AttachmentResolveUrlPayload,
+) -> CmdResult<String> {
     state
-        .upload_attachment_for_message(payload)
+        .resolve_download_url(
+            &payload.attachment_id,
+            payload.force_refresh.unwrap_or(false),
+        )
         .await
         .map_err(ApiErrorDto::from)
 }
 
 #[tauri::command]
-async fn attachment_get(
+async fn attachment_preview(
     state: State<'_, AppState>,
     payload: AttachmentGetPayload,
-) -> CmdResult<AttachmentDto> {
-    let value = state
-        .send_json(
-            Method::GET,
-            &format!("/attachments/{}", payload.attachment_id),
-            None,
-            true,
-        )
+) -> CmdResult<Option<previews::AttachmentPreviewDto>> {
+    state
+        .attachment_preview(&payload.attachment_id)
         .await
-        .map_err(ApiErrorDto::from)?;
-
-    let name = value
-        .get("filename")
-        .or_else(|| value.get("name"))
-        .and_then(JsonValue::as_str)
-        .unwrap_or("file")
-        .to_string();
-    let size_bytes = value
-        .get("size_bytes")
-        .or_else(|| value.get("size"))
-        .and_then(JsonValue::as_i64)
-        .unwrap_or(0);
-    let content_type = value
-        .get("content_type")
-        .and_then(JsonValue::as_str)
-        .map(ToString::to_string);
-    let storage_key = value
-        .get("storage_key")
-        .or_else(|| value.get("key"))
-        .and_then(JsonValue::as_str)
-        .map(ToString::to_string);
-    let download_url = value
-        .get("download_url")
-        .and_then(JsonValue::as_str)
-        .map(ToString::to_string);
-
-    Ok(AttachmentDto {
-        id: payload.attachment_id,
-        name,
-        size_bytes,
-        content_type,
-        storage_key,
-        download_url,
-    })
+        .map_err(ApiErrorDto::from)
 }
 
 #[tauri::command]
@@ -1117,17 +4642,27 @@ async fn messages_edit(
     state: State<'_, AppState>,
     payload: EditMessagePayload,
 ) -> CmdResult<MessageDto> {
+    state
+        .ensure_message_writable(&payload.message_id)
+        .await
+        .map_err(ApiErrorDto::from)?;
+    let body_md = state.normalize_mentions(&payload.body_md).await;
     let value = state
         .send_json(
             Method::PATCH,
             &format!("/messages/{}", payload.message_id),
-            Some(json!({ "body_md": payload.body_md })),
+            Some(json!({ "body_md": body_md })),
             true,
         )
         .await
         .map_err(ApiErrorDto::from)?;
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
+    let message: MessageDto = serde_json::from_value(value)
+        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+    let mut message = state.cache_message(message).await;
+    state
+        .render_bodies(std::slice::from_mut(&mut message))
+        .await;
+    Ok(message)
 }
 
 #[tauri::command]
@@ -1135,6 +4670,10 @@ async fn messages_delete(
     state: State<'_, AppState>,
     payload: DeleteMessagePayload,
 ) -> CmdResult<()> {
+    state
+        .ensure_message_writable(&payload.message_id)
+        .await
+        .map_err(ApiErrorDto::from)?;
     state
         .send_json(
             Method::DELETE,
@@ -1144,9 +4683,149 @@ async fn messages_delete(
         )
         .await
         .map_err(ApiErrorDto::from)?;
+    state.forget_message(&payload.message_id).await;
     Ok(())
 }
 
+#[tauri::command]
+async fn messages_search(
+    state: State<'_, AppState>,
+    payload: MessageSearchPayload,
+) -> CmdResult<search::MessageSearchResultDto> {
+    state
+        .search_messages(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn local_search(
+    state: State<'_, AppState>,
+    payload: LocalSearchPayload,
+) -> CmdResult<Vec<search_index::LocalSearchHitDto>> {
+    let limit = payload
+        .limit
+        .unwrap_or(search_index::DEFAULT_LOCAL_SEARCH_LIMIT)
+        .clamp(1, search::MAX_SEARCH_LIMIT);
+    Ok(state
+        .search_index
+        .read()
+        .await
+        .search(&payload.query, payload.channel_id.as_deref(), limit))
+}
+
+#[tauri::command]
+async fn reactions_add(
+    state: State<'_, AppState>,
+    payload: ReactionPayload,
+) -> CmdResult<Vec<reactions::ReactionSummaryDto>> {
+    state
+        .change_reaction(
+            &payload.message_id,
+            &payload.emoji,
+            reactions::ReactionChange::Added,
+        )
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn reactions_remove(
+    state: State<'_, AppState>,
+    payload: ReactionPayload,
+) -> CmdResult<Vec<reactions::ReactionSummaryDto>> {
+    state
+        .change_reaction(
+            &payload.message_id,
+            &payload.emoji,
+            reactions::ReactionChange::Removed,
+        )
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn reactions_list(
+    state: State<'_, AppState>,
+    payload: MessageIdPayload,
+) -> CmdResult<Vec<reactions::ReactionSummaryDto>> {
+    Ok(state
+        .message_cache
+        .read()
+        .await
+        .get(&payload.message_id)
+        .and_then(|message| message.reactions.clone())
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+async fn pins_add(state: State<'_, AppState>, payload: PinPayload) -> CmdResult<pins::PinDto> {
+    state
+        .pin_message(&payload.channel_id, &payload.message_id)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn pins_remove(state: State<'_, AppState>, payload: PinPayload) -> CmdResult<()> {
+    state
+        .unpin_message(&payload.channel_id, &payload.message_id)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn pins_list(
+    state: State<'_, AppState>,
+    payload: ChannelIdPayload,
+) -> CmdResult<pins::PinListDto> {
+    state
+        .list_pins(&payload.channel_id)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn saved_items_add(
+    state: State<'_, AppState>,
+    payload: MessageIdPayload,
+) -> CmdResult<saved_items::SavedItemDto> {
+    state
+        .save_item(&payload.message_id)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn saved_items_remove(
+    state: State<'_, AppState>,
+    payload: MessageIdPayload,
+) -> CmdResult<bool> {
+    let user_id = state
+        .my_user_id()
+        .await
+        .ok_or_else(|| ApiErrorDto::from(ApiError::Unauthenticated))?;
+    let removed = state
+        .saved_items
+        .write()
+        .await
+        .remove(&user_id, &payload.message_id);
+    state.persist_saved_items().await;
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn saved_items_list(state: State<'_, AppState>) -> CmdResult<Vec<saved_items::SavedItemDto>> {
+    let Some(user_id) = state.my_user_id().await else {
+        return Ok(Vec::new());
+    };
+    let mut items = state.saved_items.read().await.list(&user_id);
+    state
+        .render_bodies(items.iter_mut().map(|item| &mut item.message))
+        .await;
+    Ok(items)
+}
+
 #[tauri::command]
 async fn thread_get(
     state: State<'_, AppState>,
@@ -1162,8 +4841,16 @@ async fn thread_get(
         .await
         .map_err(ApiErrorDto::from)?;
 
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
+    let mut summary: ThreadSummaryDto = serde_json::from_value(value)
+        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+    summary.root_message = state.cache_message(summary.root_message).await;
+    state
+        .attach_senders(std::slice::from_mut(&mut summary.root_message))
+        .await;
+    state
+        .render_bodies(std::slice::from_mut(&mut summary.root_message))
+        .await;
+    Ok(summary)
 }
 
 #[tauri::command]
@@ -1186,8 +4873,13 @@ async fn thread_replies_list(
         .await
         .map_err(ApiErrorDto::from)?;
 
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
+    let mut list: MessageListDto = serde_json::from_value(value)
+        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+    state.remember_message_download_urls(&list.items).await;
+    list.items = state.cache_messages(list.items).await;
+    state.attach_senders(&mut list.items).await;
+    state.render_bodies(&mut list.items).await;
+    Ok(list)
 }
 
 #[tauri::command]
@@ -1195,17 +4887,28 @@ async fn thread_reply_send(
     state: State<'_, AppState>,
     payload: SendThreadReplyPayload,
 ) -> CmdResult<MessageDto> {
+    state
+        .ensure_message_writable(&payload.root_id)
+        .await
+        .map_err(ApiErrorDto::from)?;
+    let body_md = state.normalize_mentions(&payload.body_md).await;
     let value = state
         .send_json(
             Method::POST,
             &format!("/threads/{}/replies", payload.root_id),
-            Some(json!({ "body_md": payload.body_md })),
+            Some(json!({ "body_md": body_md })),
             true,
         )
         .await
         .map_err(ApiErrorDto::from)?;
-    serde_json::from_value(value)
-        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))
+    let message: MessageDto = serde_json::from_value(value)
+        .map_err(|err| ApiErrorDto::from(ApiError::InvalidResponse(err.to_string())))?;
+    state.clear_sent_draft(None, Some(&payload.root_id)).await;
+    let mut message = state.cache_message(message).await;
+    state
+        .render_bodies(std::slice::from_mut(&mut message))
+        .await;
+    Ok(message)
 }
 
 #[tauri::command]
@@ -1235,6 +4938,122 @@ async fn settings_set_api_base(state: State<'_, AppState>, payload: ApiBasePaylo
     state.set_api_base(&payload.api_base).await.map_err(ApiErrorDto::from)
 }
 
+#[tauri::command]
+async fn settings_get_notifications(
+    state: State<'_, AppState>,
+) -> CmdResult<notifications::NotificationSettingsDto> {
+    Ok(state.notification_settings.read().await.clone())
+}
+
+#[tauri::command]
+async fn settings_set_notifications(
+    state: State<'_, AppState>,
+    payload: notifications::NotificationSettingsDto,
+) -> CmdResult<notifications::NotificationSettingsDto> {
+    state
+        .set_notification_settings(payload)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn settings_get_attachment_denylist(state: State<'_, AppState>) -> CmdResult<Vec<String>> {
+    Ok(state.attachment_denylist.read().await.clone())
+}
+
+#[tauri::command]
+async fn settings_set_attachment_denylist(
+    state: State<'_, AppState>,
+    payload: AttachmentDenylistPayload,
+) -> CmdResult<Vec<String>> {
+    state
+        .set_attachment_denylist(payload.entries)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn cache_stats(state: State<'_, AppState>) -> CmdResult<attachment_cache::CacheStatsDto> {
+    state
+        .with_attachment_cache(|cache| Ok(cache.stats()))
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn cache_clear(state: State<'_, AppState>) -> CmdResult<attachment_cache::CacheStatsDto> {
+    state
+        .with_attachment_cache(|cache| {
+            cache.clear()?;
+            Ok(cache.stats())
+        })
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+#[tauri::command]
+async fn settings_set_attachment_cache_budget(
+    state: State<'_, AppState>,
+    payload: AttachmentCacheBudgetPayload,
+) -> CmdResult<attachment_cache::CacheStatsDto> {
+    state
+        .set_attachment_cache_budget(payload.budget_bytes)
+        .await
+        .map_err(ApiErrorDto::from)
+}
+
+async fn serve_attachment_request(
+    app: &AppHandle,
+    request: tauri::http::Request<Vec<u8>>,
+) -> tauri::http::Response<Vec<u8>> {
+    let attachment_id = request.uri().path().trim_matches('/').to_string();
+    let response = tauri::http::Response::builder();
+    if !attachment_cache::is_valid_key(&attachment_id) {
+        return response
+            .status(StatusCode::BAD_REQUEST.as_u16())
+            .body(Vec::new())
+            .unwrap_or_default();
+    }
+
+    let Some(state) = app.try_state::<AppState>() else {
+        return response
+            .status(StatusCode::SERVICE_UNAVAILABLE.as_u16())
+            .body(Vec::new())
+            .unwrap_or_default();
+    };
+
+    match state.load_attachment_for_protocol(&attachment_id).await {
+        Ok(cached) => response
+            .status(StatusCode::OK.as_u16())
+            .header(
+                "Content-Type",
+                cached
+                    .content_type
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+            )
+            .body(cached.bytes)
+            .unwrap_or_default(),
+        Err(err) => {
+            log::warn!("could not serve attachment {attachment_id}: {err}");
+            let status = ApiErrorDto::from(err).status;
+            response.status(status).body(Vec::new()).unwrap_or_default()
+        }
+    }
+}
+
+fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn next_client_msg_id() -> String {
+    let millis = now_millis();
+    let seq = CLIENT_MSG_SEQ.fetch_add(1, Ordering::Relaxed);
+    format!("desktop-{millis}-{seq}")
+}
+
 fn websocket_url(api_base: &str) -> String {
     let url = api_base.trim_end_matches('/');
     if let Some(rest) = url.strip_prefix("https://") {
@@ -1289,19 +5108,30 @@ async fn run_ws_loop(state: AppState, app: AppHandle, mut shutdown_rx: oneshot::
             Ok((socket, _)) => {
                 retry_seconds = 1;
                 ws_emit(&app, "realtime:status", json!({ "status": "online" })).await;
-                let (_, mut reader) = socket.split();
+                let (mut writer, mut reader) = socket.split();
+                let (outbox_tx, mut outbox_rx) = mpsc::unbounded_channel::<String>();
+                *state.ws_outbox.lock().await = Some(outbox_tx);
 
                 loop {
                     tokio::select! {
                       _ = &mut shutdown_rx => {
+                        *state.ws_outbox.lock().await = None;
+                        state.fail_pending_ws_commands().await;
                         ws_emit(&app, "realtime:status", json!({ "status": "offline" })).await;
                         return;
                       }
+                      Some(frame) = outbox_rx.recv() => {
+                        if let Err(err) = writer.send(Message::Text(frame)).await {
+                          log::warn!("ws send error: {err}");
+                          break;
+                        }
+                      }
                       message = reader.next() => {
                         match message {
                           Some(Ok(msg)) if msg.is_text() => {
                             if let Ok(text) = msg.to_text() {
-                              if let Ok(payload) = serde_json::from_str::<JsonValue>(text) {
+                              if let Ok(mut payload) = serde_json::from_str::<JsonValue>(text) {
+                                state.apply_realtime_event(&mut payload).await;
                                 ws_emit(&app, "realtime:event", &payload).await;
                                 if let Some(event_type) = payload.get("event_type").and_then(JsonValue::as_str) {
                                   ws_emit(&app, &format!("realtime:{event_type}"), &payload).await;
@@ -1320,6 +5150,9 @@ async fn run_ws_loop(state: AppState, app: AppHandle, mut shutdown_rx: oneshot::
                       }
                     }
                 }
+
+                *state.ws_outbox.lock().await = None;
+                state.fail_pending_ws_commands().await;
             }
             Err(err) => {
                 log::warn!("ws connect failed: {err}");
@@ -1367,8 +5200,32 @@ async fn realtime_disconnect(state: State<'_, AppState>) -> CmdResult<()> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let mut builder = tauri::Builder::default();
+    // Must be the first plugin: a second launch from a `galynx://` link hands
+    // its URL to this instance (through the deep-link plugin) and exits.
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+        }));
+    }
+
+    builder
         .plugin(tauri_plugin_store::Builder::default().build())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .register_asynchronous_uri_scheme_protocol(
+            attachment_cache::ATTACHMENT_SCHEME,
+            |ctx, request, responder| {
+                let app = ctx.app_handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    responder.respond(serve_attachment_request(&app, request).await);
+                });
+            },
+        )
         .setup(|app| {
             initialize_encryption_key(app.handle());
 
@@ -1387,6 +5244,23 @@ pub fn run() {
             let api_base = env_api_base
                 .or(stored_api_base)
                 .unwrap_or_else(|| DEFAULT_API_BASE.to_string());
+            let attachment_denylist = load_attachment_denylist_from_store_for_app(app.handle())
+                .unwrap_or_else(upload_validation::default_attachment_denylist);
+            let attachment_cache_budget =
+                load_attachment_cache_budget_from_store_for_app(app.handle())
+                    .unwrap_or(attachment_cache::DEFAULT_CACHE_BUDGET_BYTES);
+            let read_state = load_read_state_from_store_for_app(app.handle()).unwrap_or_default();
+            let notification_settings =
+                load_notification_settings_from_store_for_app(app.handle()).unwrap_or_default();
+            let drafts = load_drafts_from_store_for_app(app.handle()).unwrap_or_default();
+            let scheduled_messages =
+                load_scheduled_messages_from_store_for_app(app.handle()).unwrap_or_default();
+            let local_pins = load_local_pins_from_store_for_app(app.handle()).unwrap_or_default();
+            let saved_items = load_saved_items_from_store_for_app(app.handle()).unwrap_or_default();
+            let device = load_or_create_device_for_app(app.handle()).or_else(|err| {
+                log::warn!("could not load the device id, using one for this run only: {err}");
+                sessions::DeviceDto::generate()
+            })?;
             let client = reqwest::Client::builder()
                 .build()
                 .map_err(|err| tauri::Error::Anyhow(err.into()))?;
@@ -1398,42 +5272,167 @@ pub fn run() {
                 tokens: Arc::new(RwLock::new(None)),
                 refresh_lock: Arc::new(Mutex::new(())),
                 ws_shutdown: Arc::new(Mutex::new(None)),
+                download_urls: Arc::new(RwLock::new(HashMap::new())),
+                attachment_denylist: Arc::new(RwLock::new(attachment_denylist)),
+                attachment_cache: attachment_cache::AttachmentCache::open(
+                    attachment_cache::cache_dir(app.handle())?,
+                    attachment_cache_budget,
+                )
+                .shared(),
+                current_user: Arc::new(RwLock::new(None)),
+                message_cache: Arc::new(RwLock::new(message_cache::MessageCache::new(
+                    message_cache::MESSAGE_CACHE_CAPACITY,
+                ))),
+                search_index: Arc::new(RwLock::new(load_search_index_for_app(app.handle())?)),
+                user_directory: Arc::new(RwLock::new(user_directory::UserDirectory::default())),
+                read_state: Arc::new(RwLock::new(read_state)),
+                user_directory_backfill: Arc::new(AtomicBool::new(false)),
+                read_sync_supported: Arc::new(AtomicBool::new(true)),
+                drafts: Arc::new(RwLock::new(drafts)),
+                scheduled_messages: Arc::new(RwLock::new(scheduled_messages)),
+                local_pins: Arc::new(RwLock::new(local_pins)),
+                saved_items: Arc::new(RwLock::new(saved_items)),
+                channels: Arc::new(RwLock::new(HashMap::new())),
+                channel_members: Arc::new(RwLock::new(HashMap::new())),
+                notification_settings: Arc::new(RwLock::new(notification_settings)),
+                device: Arc::new(RwLock::new(device)),
+                pending_invite: Arc::new(Mutex::new(None)),
+                ws_outbox: Arc::new(Mutex::new(None)),
+                ws_pending: Arc::new(Mutex::new(HashMap::new())),
             };
 
             app.manage(state.clone());
+
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(err) = app.deep_link().register_all() {
+                log::warn!(
+                    "could not register the {} scheme: {err}",
+                    invites::DEEP_LINK_SCHEME
+                );
+            }
+            let link_state = state.clone();
+            app.deep_link().on_open_url(move |event| {
+                let state = link_state.clone();
+                let urls = event.urls();
+                tauri::async_runtime::spawn(async move {
+                    for url in urls {
+                        state.open_deep_link(url.as_str()).await;
+                    }
+                });
+            });
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                let state = state.clone();
+                tauri::async_runtime::spawn(async move {
+                    for url in urls {
+                        state.open_deep_link(url.as_str()).await;
+                    }
+                });
+            }
+
+            let flush_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(LOCAL_STATE_FLUSH_INTERVAL);
+                loop {
+                    ticker.tick().await;
+                    flush_state.flush_search_index().await;
+                    flush_state.flush_attachment_cache_index().await;
+                    flush_state.persist_read_state().await;
+                    flush_state.persist_saved_items().await;
+                }
+            });
             tauri::async_runtime::spawn(async move {
                 state.validate_stored_session().await;
+                let mut ticker = tokio::time::interval(SCHEDULED_MESSAGES_POLL_INTERVAL);
+                loop {
+                    ticker.tick().await;
+                    state.send_due_scheduled_messages().await;
+                }
             });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             auth_login,
+            auth_accept_invite,
+            auth_pending_invite,
             auth_me,
             auth_logout,
+            auth_change_password,
+            auth_request_reset,
+            auth_confirm_reset,
+            sessions_list,
+            sessions_revoke,
+            sessions_revoke_others,
+            invites_create,
+            invites_list,
+            invites_revoke,
             workspaces_list,
             workspaces_create,
             workspace_members_list,
             workspace_members_upsert,
+            workspace_members_set_role,
+            workspace_members_remove,
             users_list,
+            users_resolve,
+            mentions_suggest,
+            markdown_render,
             users_create,
+            users_disable,
+            users_enable,
+            users_import_csv,
+            permissions_for_context,
             channels_list,
+            channels_mark_read,
             channels_create,
             channels_delete,
+            channels_rename,
+            channels_set_topic,
+            channels_archive,
+            channels_unarchive,
             channel_members_list,
             channel_members_add,
             channel_members_remove,
+            dm_open,
+            dm_list,
             messages_list,
             messages_send,
+            messages_send_with_attachments,
+            drafts_save,
+            drafts_get,
+            drafts_list,
+            messages_schedule,
+            messages_scheduled_list,
+            messages_schedule_cancel,
             attachments_upload_commit,
+            attachments_upload_batch,
             attachment_get,
+            attachment_resolve_url,
+            attachment_preview,
             messages_edit,
             messages_delete,
+            messages_search,
+            local_search,
+            reactions_add,
+            reactions_remove,
+            reactions_list,
+            pins_add,
+            pins_remove,
+            pins_list,
+            saved_items_add,
+            saved_items_remove,
+            saved_items_list,
             thread_get,
             thread_replies_list,
             thread_reply_send,
             audit_list,
             settings_get_api_base,
             settings_set_api_base,
+            settings_get_notifications,
+            settings_set_notifications,
+            settings_get_attachment_denylist,
+            settings_set_attachment_denylist,
+            settings_set_attachment_cache_budget,
+            cache_stats,
+            cache_clear,
             realtime_connect,
             realtime_disconnect
         ])
@@ -1535,6 +5534,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn issued_download_url_expires_before_server_ttl() {
+        let issued_at = Instant::now();
+        let issued = IssuedDownloadUrl {
+            url: "https://files.local/att-1?sig=abc".to_string(),
+            issued_at,
+        };
+        assert!(issued.is_fresh(issued_at));
+        assert!(issued.is_fresh(issued_at + Duration::from_secs(500)));
+        assert!(!issued.is_fresh(issued_at + Duration::from_secs(540)));
+        assert!(!issued.is_fresh(issued_at + DOWNLOAD_URL_TTL));
+    }
+
     #[test]
     fn api_error_dto_maps_unauthenticated_and_http() {
         let unauth = ApiErrorDto::from(ApiError::Unauthenticated);
@@ -1549,5 +5561,139 @@ mod tests {
         assert_eq!(http.status, 429);
         assert_eq!(http.error, "too_many_requests");
         assert_eq!(http.message, "slow down");
+
+        let too_large = ApiErrorDto::from(ApiError::AttachmentTooLarge {
+            size_bytes: 200,
+            max_bytes: 100,
+        });
+        assert_eq!(too_large.status, 413);
+        assert_eq!(too_large.error, "attachment_too_large");
+
+        let blocked = ApiErrorDto::from(ApiError::AttachmentBlocked("exe".to_string()));
+        assert_eq!(blocked.status, 415);
+        assert_eq!(blocked.error, "attachment_type_blocked");
+    }
+
+    fn batch_file(filename: &str, size_bytes: i64) -> AttachmentFilePayload {
+        AttachmentFilePayload {
+            filename: filename.to_string(),
+            content_type: "text/plain".to_string(),
+            size_bytes,
+            bytes: Vec::new(),
+        }
+    }
+
+    /// Runs a batch where each upload takes `size_bytes` scheduler turns and
+    /// files named `bad` fail. Returns the result, every progress update and
+    /// the most uploads seen in flight at once.
+    fn run_test_batch(
+        files: Vec<AttachmentFilePayload>,
+        concurrency: usize,
+    ) -> (
+        AttachmentBatchResultDto,
+        Vec<AttachmentBatchProgressDto>,
+        usize,
+    ) {
+        use std::cell::{Cell, RefCell};
+
+        let in_flight = Cell::new(0_usize);
+        let max_in_flight = Cell::new(0_usize);
+        let updates = RefCell::new(Vec::new());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(run_upload_batch(
+            "batch-1".to_string(),
+            "m1".to_string(),
+            files,
+            concurrency,
+            |file| {
+                let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+                async move {
+                    in_flight.set(in_flight.get() + 1);
+                    max_in_flight.set(max_in_flight.get().max(in_flight.get()));
+                    for _ in 0..file.size_bytes {
+                        tokio::task::yield_now().await;
+                    }
+                    in_flight.set(in_flight.get() - 1);
+                    if file.filename == "bad" {
+                        return Err(ApiError::Network("connection reset".to_string()));
+                    }
+                    Ok(AttachmentDto {
+                        id: format!("att-{}", file.filename),
+                        name: file.filename,
+                        size_bytes: file.size_bytes,
+                        content_type: Some(file.content_type),
+                        storage_key: None,
+                        download_url: None,
+                        width: None,
+                        height: None,
+                    })
+                }
+            },
+            |progress| {
+                updates.borrow_mut().push(progress);
+                async {}
+            },
+        ));
+        (result, updates.into_inner(), max_in_flight.get())
+    }
+
+    #[test]
+    fn batch_upload_concurrency_is_clamped() {
+        assert_eq!(
+            batch_upload_concurrency(None),
+            BATCH_UPLOAD_DEFAULT_CONCURRENCY
+        );
+        assert_eq!(batch_upload_concurrency(Some(0)), 1);
+        assert_eq!(batch_upload_concurrency(Some(4)), 4);
+        assert_eq!(
+            batch_upload_concurrency(Some(100)),
+            BATCH_UPLOAD_MAX_CONCURRENCY
+        );
+
+        let files = (0..6).map(|n| batch_file(&format!("f{n}"), 3)).collect();
+        let (result, _, max_in_flight) = run_test_batch(files, 2);
+        assert_eq!(max_in_flight, 2);
+        assert_eq!(result.uploaded, 6);
+    }
+
+    #[test]
+    fn failed_file_does_not_stop_the_batch() {
+        let files = vec![batch_file("a", 2), batch_file("bad", 1), batch_file("c", 2)];
+        let (result, _, _) = run_test_batch(files, 3);
+        assert_eq!((result.uploaded, result.failed), (2, 1));
+
+        let failed = &result.items[1];
+        assert_eq!(failed.filename, "bad");
+        assert!(failed.attachment.is_none());
+        assert!(failed.error.is_some());
+        for item in [&result.items[0], &result.items[2]] {
+            assert!(item.attachment.is_some());
+            assert!(item.error.is_none());
+        }
+    }
+
+    #[test]
+    fn batch_progress_follows_completion_order() {
+        let files = vec![
+            batch_file("slow", 5),
+            batch_file("fast", 1),
+            batch_file("bad", 3),
+        ];
+        let (result, updates, _) = run_test_batch(files, 3);
+
+        let finished: Vec<(usize, usize, i64)> = updates
+            .iter()
+            .map(|update| (update.completed, update.failed, update.bytes_completed))
+            .collect();
+        assert_eq!(finished, vec![(1, 0, 1), (1, 1, 4), (2, 1, 9)]);
+        assert!(updates
+            .iter()
+            .all(|update| update.total == 3 && update.bytes_total == 9));
+        assert_eq!(result.batch_id, "batch-1");
+
+        let order: Vec<usize> = result.items.iter().map(|item| item.index).collect();
+        assert_eq!(order, vec![0, 1, 2]);
     }
 }
@@ -0,0 +1,538 @@
+use crate::{mentions, ChannelDto, UserDto};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd, TextMergeStream};
+use std::{collections::HashSet, sync::OnceLock};
+
+const ALLOWED_TAGS: [&str; 26] = [
+    "p",
+    "br",
+    "hr",
+    "strong",
+    "em",
+    "del",
+    "code",
+    "pre",
+    "blockquote",
+    "ul",
+    "ol",
+    "li",
+    "a",
+    "span",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "table",
+    "thead",
+    "tbody",
+    "tr",
+    "th",
+    "td",
+];
+const HIGHLIGHT_CLASSES: [&str; 4] = ["tok-kw", "tok-str", "tok-num", "tok-com"];
+const CODE_KEYWORDS: [&str; 52] = [
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "class",
+    "const",
+    "continue",
+    "def",
+    "default",
+    "do",
+    "elif",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "fn",
+    "for",
+    "from",
+    "func",
+    "function",
+    "if",
+    "impl",
+    "import",
+    "in",
+    "interface",
+    "let",
+    "match",
+    "mod",
+    "mut",
+    "new",
+    "nil",
+    "None",
+    "null",
+    "package",
+    "pub",
+    "return",
+    "select",
+    "self",
+    "static",
+    "struct",
+    "switch",
+    "this",
+    "trait",
+    "true",
+    "try",
+    "type",
+    "use",
+    "var",
+    "where",
+    "while",
+];
+
+/// Names used to label `<@id>` / `<#id>` mentions in rendered output.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct MentionContext<'a> {
+    pub(crate) users: &'a [UserDto],
+    pub(crate) channels: &'a [ChannelDto],
+}
+
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn sanitizer() -> &'static ammonia::Builder<'static> {
+    static SANITIZER: OnceLock<ammonia::Builder<'static>> = OnceLock::new();
+    SANITIZER.get_or_init(|| {
+        let mut builder = ammonia::Builder::empty();
+        builder
+            .add_tags(ALLOWED_TAGS)
+            .add_tag_attributes("a", ["href", "title"])
+            .add_tag_attributes("ol", ["start"])
+            .add_tag_attributes("pre", ["data-language"])
+            .add_tag_attributes("span", ["data-mention-kind", "data-mention-id"])
+            .add_allowed_classes("span", ["mention"])
+            .add_allowed_classes("span", HIGHLIGHT_CLASSES)
+            .clean_content_tags(HashSet::from(["script", "style"]))
+            .url_schemes(HashSet::from(["http", "https", "mailto"]))
+            .url_relative(ammonia::UrlRelative::Deny)
+            .link_rel(Some("noopener noreferrer nofollow"))
+            .strip_comments(true);
+        builder
+    })
+}
+
+/// Renders CommonMark to HTML that is safe to inject into the webview. Raw
+/// HTML in the source is shown as text, images become plain links, and the
+/// result is passed through an allow-list sanitizer.
+pub(crate) fn render(body_md: &str, context: MentionContext<'_>) -> String {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES;
+    let mut events = Vec::new();
+    let mut code_block: Option<(String, String)> = None;
+    let mut link_depth = 0usize;
+
+    for event in TextMergeStream::new(Parser::new_ext(body_md, options)) {
+        if let Some((language, code)) = &mut code_block {
+            match event {
+                Event::Text(text) => code.push_str(&text),
+                Event::End(TagEnd::CodeBlock) => {
+                    events.push(Event::Html(highlight_code(language, code).into()));
+                    code_block = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => code_language(&info),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code_block = Some((language, String::new()));
+            }
+            Event::Html(html) | Event::InlineHtml(html) => events.push(Event::Text(html)),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                link_depth += 1;
+                events.push(Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }));
+            }
+            Event::End(TagEnd::Image) => {
+                link_depth = link_depth.saturating_sub(1);
+                events.push(Event::End(TagEnd::Link));
+            }
+            Event::Start(Tag::Link { .. }) => {
+                link_depth += 1;
+                events.push(event);
+            }
+            Event::End(TagEnd::Link) => {
+                link_depth = link_depth.saturating_sub(1);
+                events.push(event);
+            }
+            Event::Text(text) => events.push(Event::Html(CowStr::from(render_inline_text(
+                &text,
+                context,
+                link_depth == 0,
+            )))),
+            other => events.push(other),
+        }
+    }
+
+    let mut html = String::with_capacity(body_md.len() * 2);
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    sanitizer().clean(&html).to_string()
+}
+
+/// Escapes `text` and turns mentions and, outside links, bare URLs into
+/// elements.
+fn render_inline_text(text: &str, context: MentionContext<'_>, linkify: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    if !linkify {
+        push_mentions(text, context, &mut out);
+        return out;
+    }
+
+    let mut rest = text;
+    while let Some(start) = ["https://", "http://"]
+        .iter()
+        .filter_map(|scheme| rest.find(scheme))
+        .min()
+    {
+        let candidate = &rest[start..];
+        let len = candidate
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"'))
+            .unwrap_or(candidate.len());
+        let url = candidate[..len].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'']);
+        push_mentions(&rest[..start], context, &mut out);
+        if url.len() > "https://".len() {
+            out.push_str("<a href=\"");
+            escape_html(url, &mut out);
+            out.push_str("\">");
+            escape_html(url, &mut out);
+            out.push_str("</a>");
+        } else {
+            escape_html(url, &mut out);
+        }
+        rest = &rest[start + url.len()..];
+    }
+    push_mentions(rest, context, &mut out);
+    out
+}
+
+fn push_mentions(text: &str, context: MentionContext<'_>, out: &mut String) {
+    let mut tokens = mentions::parse(text);
+    mentions::resolve(&mut tokens, context.users, context.channels);
+
+    let mut cursor = 0;
+    for token in tokens {
+        let Some(id) = token.id.as_deref() else {
+            continue;
+        };
+        let (kind, label) = match token.kind {
+            mentions::MentionKind::User => (
+                "user",
+                context
+                    .users
+                    .iter()
+                    .find(|user| user.id == id)
+                    .map(|user| format!("@{}", user.name))
+                    .unwrap_or_else(|| format!("@{}", token.text)),
+            ),
+            mentions::MentionKind::Channel => (
+                "channel",
+                context
+                    .channels
+                    .iter()
+                    .find(|channel| channel.id == id)
+                    .map(|channel| format!("#{}", channel.name))
+                    .unwrap_or_else(|| format!("#{}", token.text)),
+            ),
+            mentions::MentionKind::Broadcast => ("broadcast", format!("@{id}")),
+        };
+        escape_html(&text[cursor..token.start], out);
+        out.push_str("<span class=\"mention\" data-mention-kind=\"");
+        out.push_str(kind);
+        out.push_str("\" data-mention-id=\"");
+        escape_html(id, out);
+        out.push_str("\">");
+        escape_html(&label, out);
+        out.push_str("</span>");
+        cursor = token.end;
+    }
+    escape_html(&text[cursor..], out);
+}
+
+/// First word of a fence info string when it looks like a language name.
+fn code_language(info: &str) -> String {
+    let language = info.split_whitespace().next().unwrap_or_default();
+    if language.len() <= 20
+        && language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '#' | '-' | '.'))
+    {
+        language.to_ascii_lowercase()
+    } else {
+        String::new()
+    }
+}
+
+fn comment_prefix(language: &str) -> &'static str {
+    match language {
+        "py" | "python" | "sh" | "bash" | "zsh" | "shell" | "yaml" | "yml" | "toml" | "rb"
+        | "ruby" | "r" => "#",
+        "sql" | "lua" | "haskell" | "hs" => "--",
+        _ => "//",
+    }
+}
+
+/// Wraps keywords, strings, numbers and comments of a fenced block in
+/// `tok-*` spans. Blocks without a language are only escaped.
+pub(crate) fn highlight_code(language: &str, code: &str) -> String {
+    let mut out = String::with_capacity(code.len() * 2);
+    out.push_str("<pre");
+    if !language.is_empty() {
+        out.push_str(" data-language=\"");
+        escape_html(language, &mut out);
+        out.push('"');
+    }
+    out.push_str("><code>");
+    if language.is_empty() {
+        escape_html(code, &mut out);
+        out.push_str("</code></pre>\n");
+        return out;
+    }
+
+    let line_comment = comment_prefix(language);
+    let block_comments = line_comment == "//";
+    let chars: Vec<(usize, char)> = code.char_indices().collect();
+    let byte_at = |idx: usize| chars.get(idx).map_or(code.len(), |(offset, _)| *offset);
+    let span = |class: &str, text: &str, out: &mut String| {
+        out.push_str("<span class=\"");
+        out.push_str(class);
+        out.push_str("\">");
+        escape_html(text, out);
+        out.push_str("</span>");
+    };
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (offset, c) = chars[idx];
+        let rest = &code[offset..];
+        if rest.starts_with(line_comment) || (block_comments && rest.starts_with("/*")) {
+            let len = if rest.starts_with("/*") {
+                rest.find("*/").map_or(rest.len(), |end| end + 2)
+            } else {
+                rest.find('\n').unwrap_or(rest.len())
+            };
+            span("tok-com", &rest[..len], &mut out);
+            idx = chars.partition_point(|(pos, _)| *pos < offset + len);
+        } else if matches!(c, '"' | '\'' | '`') {
+            let mut end = idx + 1;
+            while end < chars.len() && chars[end].1 != c && chars[end].1 != '\n' {
+                end += if chars[end].1 == '\\' { 2 } else { 1 };
+            }
+            let end = if chars.get(end).is_some_and(|(_, next)| *next == c) {
+                end + 1
+            } else {
+                end.min(chars.len())
+            };
+            span("tok-str", &code[offset..byte_at(end)], &mut out);
+            idx = end;
+        } else if c.is_ascii_digit() {
+            let mut end = idx + 1;
+            while end < chars.len()
+                && (chars[end].1.is_alphanumeric() || matches!(chars[end].1, '.' | '_'))
+            {
+                end += 1;
+            }
+            span("tok-num", &code[offset..byte_at(end)], &mut out);
+            idx = end;
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = idx + 1;
+            while end < chars.len() && (chars[end].1.is_alphanumeric() || chars[end].1 == '_') {
+                end += 1;
+            }
+            let word = &code[offset..byte_at(end)];
+            if CODE_KEYWORDS.contains(&word) {
+                span("tok-kw", word, &mut out);
+            } else {
+                escape_html(word, &mut out);
+            }
+            idx = end;
+        } else {
+            escape_html(&c.to_string(), &mut out);
+            idx += 1;
+        }
+    }
+    out.push_str("</code></pre>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const HOSTILE_FRAGMENTS: [&str; 22] = [
+        "<script>alert(1)</script>",
+        "<img src=x onerror=alert(1)>",
+        "<a href=\"javascript:alert(1)\">x</a>",
+        "[x](javascript:alert(1))",
+        "[x](JaVaScRiPt:alert(1))",
+        "![x](data:text/html;base64,PHNjcmlwdD4=)",
+        "<iframe src=//evil>",
+        "<svg onload=alert(1)>",
+        "<style>*{}</style>",
+        "\"><b onmouseover=x>",
+        "```js\n\"</code><script>x</script>\n```",
+        "`<script>`",
+        "<@u-1>",
+        "#general",
+        "@here",
+        "https://example.com/a?b=\"c\"",
+        "**bold**",
+        "> quote",
+        "\n\n",
+        "| a | b |\n|---|---|\n| 1 | 2 |",
+        "&lt;script&gt;",
+        "<!-- comment -->",
+    ];
+
+    fn context_fixture() -> (Vec<UserDto>, Vec<ChannelDto>) {
+        (
+            vec![UserDto {
+                id: "u-1".to_string(),
+                email: "ana@galynx.local".to_string(),
+                name: "Ana <b>".to_string(),
+                workspace_id: "ws".to_string(),
+                role: "member".to_string(),
+                avatar_url: None,
+                status: None,
+            }],
+            vec![ChannelDto {
+                id: "c-1".to_string(),
+                workspace_id: "ws".to_string(),
+                name: "general".to_string(),
+                is_private: false,
+                created_by: "u-1".to_string(),
+                created_at: 1,
+                unread_count: 0,
+                mention_count: 0,
+                last_read_at: None,
+                topic: None,
+                archived: false,
+            }],
+        )
+    }
+
+    /// Every literal `<` in the output must open an allowed tag without
+    /// event handlers or script URLs; text is always escaped.
+    fn assert_safe(html: &str) {
+        for tag in html.split('<').skip(1) {
+            let tag = tag.split('>').next().unwrap_or_default().to_lowercase();
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default();
+            assert!(ALLOWED_TAGS.contains(&name), "tag {name} in {html}");
+            assert!(
+                !tag.contains("javascript:") && !tag.contains("data:"),
+                "{tag}"
+            );
+            assert!(
+                !tag.split_whitespace()
+                    .skip(1)
+                    .any(|attr| attr.starts_with("on")),
+                "event handler in {tag}"
+            );
+        }
+    }
+
+    #[test]
+    fn renders_markdown_and_neutralizes_raw_html() {
+        let html = render(
+            "**hola** <script>alert(1)</script> [doc](https://galynx.dev) ![pic](https://x/p.png)",
+            MentionContext::default(),
+        );
+        assert!(html.contains("<strong>hola</strong>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains(
+            "<a href=\"https://galynx.dev\" rel=\"noopener noreferrer nofollow\">doc</a>"
+        ));
+        assert!(html.contains(">pic</a>"));
+        assert_safe(&html);
+
+        let html = render(
+            "[x](javascript:alert(1)) [y](/relative)",
+            MentionContext::default(),
+        );
+        assert!(!html.contains("href"));
+    }
+
+    #[test]
+    fn renders_mentions_and_bare_links() {
+        let (users, channels) = context_fixture();
+        let context = MentionContext {
+            users: &users,
+            channels: &channels,
+        };
+        let html = render("<@u-1> mira <#c-1>, @here y https://galynx.dev/x.", context);
+        assert!(html.contains(
+            "<span class=\"mention\" data-mention-kind=\"user\" data-mention-id=\"u-1\">@Ana &lt;b&gt;</span>"
+        ));
+        assert!(html.contains("data-mention-id=\"c-1\">#general</span>"));
+        assert!(html.contains("data-mention-kind=\"broadcast\""));
+        assert!(html.contains(">https://galynx.dev/x</a>."));
+
+        let html = render("`<@u-1>` [https://a.dev](https://b.dev)", context);
+        assert!(!html.contains("mention"));
+        assert_eq!(html.matches("<a ").count(), 1);
+    }
+
+    #[test]
+    fn highlights_fenced_code_blocks() {
+        let html = render(
+            "```rust\nlet n = 42; // \"<b>\"\n```\n\n```\nlet x\n```",
+            MentionContext::default(),
+        );
+        assert!(html.contains("<pre data-language=\"rust\"><code><span class=\"tok-kw\">let</span> n = <span class=\"tok-num\">42</span>;"));
+        assert!(html.contains("<span class=\"tok-com\">// \"&lt;b&gt;\"</span>"));
+        assert!(html.contains("<pre><code>let x\n</code></pre>"));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(512))]
+
+        #[test]
+        fn fuzz_hostile_fragments_never_escape_sanitizer(
+            parts in prop::collection::vec(prop::sample::select(HOSTILE_FRAGMENTS.to_vec()), 0..12),
+            noise in "[ -~\n]{0,40}",
+        ) {
+            let (users, channels) = context_fixture();
+            let body = format!("{}{noise}", parts.concat());
+            assert_safe(&render(&body, MentionContext { users: &users, channels: &channels }));
+        }
+
+        #[test]
+        fn fuzz_arbitrary_input_renders_without_panicking(body in any::<String>()) {
+            let html = render(&body, MentionContext::default());
+            prop_assert!(!html.to_lowercase().contains("<script"));
+        }
+    }
+}
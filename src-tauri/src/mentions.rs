@@ -0,0 +1,371 @@
+use crate::{search, ChannelDto, UserDto};
+use serde::Serialize;
+
+pub(crate) const BROADCAST_MENTIONS: [&str; 3] = ["channel", "here", "todos"];
+pub(crate) const DEFAULT_SUGGESTION_LIMIT: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MentionKind {
+    User,
+    Channel,
+    Broadcast,
+}
+
+/// A mention found in `body_md`. `start..end` are byte offsets of the whole
+/// token including its sigil; `id` is set once it is resolved, or when the
+/// body already uses the normalized `<@id>` / `<#id>` form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MentionToken {
+    pub(crate) kind: MentionKind,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) text: String,
+    pub(crate) id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct MentionSuggestionDto {
+    kind: MentionKind,
+    id: String,
+    label: String,
+    detail: Option<String>,
+    insert_text: String,
+}
+
+fn is_handle_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | '_' | '-')
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-'
+}
+
+/// `@handle` used when inserting a suggestion: the folded name with spaces
+/// turned into dots, so "María José" becomes `@maria.jose`.
+pub(crate) fn user_handle(user: &UserDto) -> String {
+    search::fold(&user.name)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn user_keys(user: &UserDto) -> Vec<String> {
+    let folded = search::fold(&user.name);
+    let mut keys = vec![
+        user_handle(user),
+        folded.split_whitespace().collect::<String>(),
+        user.id.to_lowercase(),
+    ];
+    if let Some((local, _)) = user.email.split_once('@') {
+        keys.push(search::fold(local));
+    }
+    keys.retain(|key| !key.is_empty());
+    keys
+}
+
+/// Finds mention tokens outside inline code and fenced code blocks.
+pub(crate) fn parse(body_md: &str) -> Vec<MentionToken> {
+    let chars: Vec<(usize, char)> = body_md.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut open_fence: Option<usize> = None;
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let (offset, c) = chars[idx];
+        if c == '`' {
+            let run = chars[idx..].iter().take_while(|(_, c)| *c == '`').count();
+            open_fence = match open_fence {
+                Some(open) if open == run => None,
+                None => Some(run),
+                other => other,
+            };
+            idx += run;
+            continue;
+        }
+        if open_fence.is_some() {
+            idx += 1;
+            continue;
+        }
+
+        if c == '<' {
+            if let Some(token) = parse_normalized(body_md, &chars, idx) {
+                idx = chars.partition_point(|(pos, _)| *pos < token.end);
+                tokens.push(token);
+                continue;
+            }
+        }
+
+        let preceded_by_word = idx > 0 && is_handle_char(chars[idx - 1].1);
+        if (c == '@' || c == '#') && !preceded_by_word {
+            let text: String = chars[idx + 1..]
+                .iter()
+                .map(|(_, c)| *c)
+                .take_while(|c| is_handle_char(*c))
+                .collect();
+            let text = text.trim_end_matches(['.', '-', '_']).to_string();
+            if !text.is_empty() {
+                let end = offset + c.len_utf8() + text.len();
+                let folded = search::fold(&text);
+                let kind = match c {
+                    '#' => MentionKind::Channel,
+                    _ if BROADCAST_MENTIONS.contains(&folded.as_str()) => MentionKind::Broadcast,
+                    _ => MentionKind::User,
+                };
+                let id = (kind == MentionKind::Broadcast).then(|| folded.clone());
+                tokens.push(MentionToken {
+                    kind,
+                    start: offset,
+                    end,
+                    text,
+                    id,
+                });
+                idx = chars.partition_point(|(pos, _)| *pos < end);
+                continue;
+            }
+        }
+        idx += 1;
+    }
+    tokens
+}
+
+fn parse_normalized(body_md: &str, chars: &[(usize, char)], idx: usize) -> Option<MentionToken> {
+    let (start, _) = chars[idx];
+    let (_, sigil) = *chars.get(idx + 1)?;
+    let kind = match sigil {
+        '@' => MentionKind::User,
+        '#' => MentionKind::Channel,
+        _ => return None,
+    };
+    let id: String = chars[idx + 2..]
+        .iter()
+        .map(|(_, c)| *c)
+        .take_while(|c| is_id_char(*c))
+        .collect();
+    let close = start + 2 + id.len();
+    if id.is_empty() || !body_md[close..].starts_with('>') {
+        return None;
+    }
+    Some(MentionToken {
+        kind,
+        start,
+        end: close + 1,
+        text: id.clone(),
+        id: Some(id),
+    })
+}
+
+/// Fills `id` for tokens that match exactly one known user or channel.
+pub(crate) fn resolve(tokens: &mut [MentionToken], users: &[UserDto], channels: &[ChannelDto]) {
+    for token in tokens.iter_mut().filter(|token| token.id.is_none()) {
+        let folded = search::fold(&token.text);
+        let matches: Vec<&str> = match token.kind {
+            MentionKind::User => users
+                .iter()
+                .filter(|user| user_keys(user).contains(&folded))
+                .map(|user| user.id.as_str())
+                .collect(),
+            MentionKind::Channel => channels
+                .iter()
+                .filter(|channel| search::fold(&channel.name) == folded)
+                .map(|channel| channel.id.as_str())
+                .collect(),
+            MentionKind::Broadcast => continue,
+        };
+        if let [id] = matches.as_slice() {
+            token.id = Some(id.to_string());
+        }
+    }
+}
+
+/// Whether `token` reaches `user`: a broadcast, their `<@id>`, or one of the
+/// handles `resolve` accepts for them. Handles must match whole, so `@ana`
+/// does not reach Anabel.
+pub(crate) fn addresses(token: &MentionToken, user: &UserDto) -> bool {
+    match token.kind {
+        MentionKind::Broadcast => true,
+        MentionKind::Channel => false,
+        MentionKind::User => match &token.id {
+            Some(id) => *id == user.id,
+            None => user_keys(user).contains(&search::fold(&token.text)),
+        },
+    }
+}
+
+/// Rewrites resolvable mentions to `<@user_id>` / `<#channel_id>` so they
+/// survive renames. Unknown or ambiguous handles are left as typed.
+pub(crate) fn normalize(body_md: &str, users: &[UserDto], channels: &[ChannelDto]) -> String {
+    let mut tokens = parse(body_md);
+    resolve(&mut tokens, users, channels);
+
+    let mut normalized = String::with_capacity(body_md.len());
+    let mut cursor = 0;
+    for token in tokens {
+        let replacement = match (&token.kind, &token.id) {
+            (MentionKind::User, Some(id)) => format!("<@{id}>"),
+            (MentionKind::Channel, Some(id)) => format!("<#{id}>"),
+            (MentionKind::Broadcast, Some(keyword)) => format!("@{keyword}"),
+            _ => continue,
+        };
+        normalized.push_str(&body_md[cursor..token.start]);
+        normalized.push_str(&replacement);
+        cursor = token.end;
+    }
+    normalized.push_str(&body_md[cursor..]);
+    normalized
+}
+
+/// Prefix suggestions for the composer. A leading `#` completes channels,
+/// anything else completes broadcasts and `users`.
+pub(crate) fn suggest(
+    prefix: &str,
+    users: &[UserDto],
+    channels: &[ChannelDto],
+    limit: usize,
+) -> Vec<MentionSuggestionDto> {
+    if let Some(rest) = prefix.strip_prefix('#') {
+        let needle = search::fold(rest);
+        let mut matches: Vec<&ChannelDto> = channels
+            .iter()
+            .filter(|channel| search::fold(&channel.name).starts_with(&needle))
+            .collect();
+        matches.sort_by_key(|channel| search::fold(&channel.name));
+        return matches
+            .into_iter()
+            .take(limit)
+            .map(|channel| MentionSuggestionDto {
+                kind: MentionKind::Channel,
+                id: channel.id.clone(),
+                label: channel.name.clone(),
+                detail: None,
+                insert_text: format!("#{} ", channel.name),
+            })
+            .collect();
+    }
+
+    let needle = search::fold(prefix.trim_start_matches('@'));
+    let broadcasts = BROADCAST_MENTIONS
+        .iter()
+        .filter(|keyword| keyword.starts_with(&needle))
+        .map(|keyword| MentionSuggestionDto {
+            kind: MentionKind::Broadcast,
+            id: keyword.to_string(),
+            label: keyword.to_string(),
+            detail: None,
+            insert_text: format!("@{keyword} "),
+        });
+
+    let mut matches: Vec<&UserDto> = users
+        .iter()
+        .filter(|user| {
+            search::fold(&user.name)
+                .split_whitespace()
+                .any(|word| word.starts_with(&needle))
+                || user_keys(user).iter().any(|key| key.starts_with(&needle))
+        })
+        .collect();
+    matches.sort_by_key(|user| search::fold(&user.name));
+    let users = matches.into_iter().map(|user| MentionSuggestionDto {
+        kind: MentionKind::User,
+        id: user.id.clone(),
+        label: user.name.clone(),
+        detail: Some(user.email.clone()).filter(|email| !email.is_empty()),
+        insert_text: format!("@{} ", user_handle(user)),
+    });
+
+    users.chain(broadcasts).take(limit).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str, name: &str, email: &str) -> UserDto {
+        UserDto {
+            id: id.to_string(),
+            email: email.to_string(),
+            name: name.to_string(),
+            workspace_id: "ws".to_string(),
+            role: "member".to_string(),
+            avatar_url: None,
+            status: None,
+        }
+    }
+
+    fn channel(id: &str, name: &str) -> ChannelDto {
+        ChannelDto {
+            id: id.to_string(),
+            workspace_id: "ws".to_string(),
+            name: name.to_string(),
+            is_private: false,
+            created_by: "u1".to_string(),
+            created_at: 1,
+            unread_count: 0,
+            mention_count: 0,
+            last_read_at: None,
+            topic: None,
+            archived: false,
+        }
+    }
+
+    fn directory() -> (Vec<UserDto>, Vec<ChannelDto>) {
+        (
+            vec![
+                user("u-1", "María José", "mjose@galynx.local"),
+                user("u-2", "Mario Ruiz", "mario@galynx.local"),
+            ],
+            vec![channel("c-1", "general"), channel("c-2", "diseño")],
+        )
+    }
+
+    #[test]
+    fn parses_tokens_and_skips_code_and_emails() {
+        let tokens =
+            parse("hola @ana, mira #general y @here. `@code` mail: a@b.com\n```\n@fenced\n```");
+        let found: Vec<(MentionKind, &str)> = tokens
+            .iter()
+            .map(|token| (token.kind, token.text.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (MentionKind::User, "ana"),
+                (MentionKind::Channel, "general"),
+                (MentionKind::Broadcast, "here"),
+            ]
+        );
+        assert_eq!(&"hola @ana,"[tokens[0].start..tokens[0].end], "@ana");
+    }
+
+    #[test]
+    fn normalizes_unique_matches_to_ids() {
+        let (users, channels) = directory();
+        assert_eq!(
+            normalize(
+                "@maria.jose @MJOSE @mario revisa #Diseno y #nada, @Todos <@u-2>",
+                &users,
+                &channels
+            ),
+            "<@u-1> <@u-1> <@u-2> revisa <#c-2> y #nada, @todos <@u-2>"
+        );
+
+        let normalized = parse("<@u-1> y <#c-1>");
+        assert_eq!(normalized[0].id.as_deref(), Some("u-1"));
+        assert_eq!(normalized[1].kind, MentionKind::Channel);
+    }
+
+    #[test]
+    fn suggests_users_broadcasts_and_channels_by_prefix() {
+        let (users, channels) = directory();
+        let labels = |prefix: &str| -> Vec<String> {
+            suggest(prefix, &users, &channels, 5)
+                .into_iter()
+                .map(|suggestion| suggestion.insert_text)
+                .collect()
+        };
+        assert_eq!(labels("@mar"), vec!["@maria.jose ", "@mario.ruiz "]);
+        assert_eq!(labels("jos"), vec!["@maria.jose "]);
+        assert_eq!(labels("@ch"), vec!["@channel "]);
+        assert_eq!(labels("#dis"), vec!["#diseño "]);
+    }
+}
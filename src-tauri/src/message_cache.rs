@@ -0,0 +1,127 @@
+use crate::MessageDto;
+use std::collections::{HashMap, VecDeque};
+
+pub(crate) const MESSAGE_CACHE_CAPACITY: usize = 5_000;
+
+/// Messages the client has seen, bounded by insertion order. Realtime events
+/// that only carry deltas (reactions) are applied here.
+#[derive(Debug)]
+pub(crate) struct MessageCache {
+    capacity: usize,
+    messages: HashMap<String, MessageDto>,
+    order: VecDeque<String>,
+}
+
+impl MessageCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Stores `message`, keeping locally known reactions when the server copy
+    /// leaves them out. An empty list from the server clears them.
+    pub(crate) fn upsert(&mut self, mut message: MessageDto) -> MessageDto {
+        match self.messages.get(&message.id) {
+            Some(existing) => {
+                if message.reactions.is_none() {
+                    message.reactions = existing.reactions.clone();
+                }
+            }
+            None => self.order.push_back(message.id.clone()),
+        }
+        self.messages.insert(message.id.clone(), message.clone());
+        self.evict();
+        message
+    }
+
+    pub(crate) fn upsert_many(&mut self, messages: Vec<MessageDto>) -> Vec<MessageDto> {
+        messages
+            .into_iter()
+            .map(|message| self.upsert(message))
+            .collect()
+    }
+
+    pub(crate) fn get(&self, message_id: &str) -> Option<&MessageDto> {
+        self.messages.get(message_id)
+    }
+
+    pub(crate) fn messages(&self) -> impl Iterator<Item = &MessageDto> {
+        self.messages.values()
+    }
+
+    pub(crate) fn get_mut(&mut self, message_id: &str) -> Option<&mut MessageDto> {
+        self.messages.get_mut(message_id)
+    }
+
+    pub(crate) fn remove(&mut self, message_id: &str) -> Option<MessageDto> {
+        let removed = self.messages.remove(message_id)?;
+        self.order.retain(|id| id != message_id);
+        Some(removed)
+    }
+
+    fn evict(&mut self) {
+        while self.messages.len() > self.capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.messages.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reactions::ReactionSummaryDto;
+
+    fn message(id: &str) -> MessageDto {
+        MessageDto {
+            id: id.to_string(),
+            workspace_id: "ws".to_string(),
+            channel_id: "ch".to_string(),
+            sender_id: "u1".to_string(),
+            body_md: "hola".to_string(),
+            thread_root_id: None,
+            created_at: 1,
+            edited_at: None,
+            deleted_at: None,
+            attachments: Vec::new(),
+            reactions: None,
+            sender: None,
+            body_html: None,
+        }
+    }
+
+    #[test]
+    fn upsert_keeps_known_reactions_and_evicts_oldest() {
+        let mut cache = MessageCache::new(2);
+        let mut first = message("m1");
+        first.reactions = Some(vec![ReactionSummaryDto {
+            emoji: "👍".to_string(),
+            count: 1,
+            me: true,
+            user_ids: vec!["u1".to_string()],
+        }]);
+        cache.upsert(first);
+
+        let refreshed = cache.upsert(message("m1"));
+        assert_eq!(refreshed.reactions.map(|items| items.len()), Some(1));
+
+        let mut cleared = message("m1");
+        cleared.reactions = Some(Vec::new());
+        assert_eq!(cache.upsert(cleared).reactions, Some(Vec::new()));
+
+        let omitted = serde_json::to_value(message("m1")).unwrap();
+        assert!(omitted.get("reactions").is_none());
+        let omitted: MessageDto = serde_json::from_value(omitted).unwrap();
+        assert!(omitted.reactions.is_none());
+
+        cache.upsert(message("m2"));
+        cache.upsert(message("m3"));
+        assert!(cache.get("m1").is_none());
+        assert!(cache.get("m3").is_some());
+    }
+}
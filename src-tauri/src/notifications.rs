@@ -0,0 +1,237 @@
+use crate::{read_state, search, MessageDto, UserDto};
+use serde::{Deserialize, Serialize};
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+const NOTIFICATION_BODY_MAX_CHARS: usize = 140;
+
+fn default_true() -> bool {
+    true
+}
+
+/// Quiet hours in local time, as minutes since midnight. `start > end` spans
+/// midnight (e.g. 22:00–07:00).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct DndSchedule {
+    pub(crate) start_minute: u16,
+    pub(crate) end_minute: u16,
+    /// Local offset from UTC in minutes, as reported by the webview.
+    #[serde(default)]
+    pub(crate) utc_offset_minutes: i32,
+}
+
+impl DndSchedule {
+    pub(crate) fn is_active(&self, now_millis: i64) -> bool {
+        let local_minutes = now_millis / 60_000 + i64::from(self.utc_offset_minutes);
+        let minute = local_minutes.rem_euclid(MINUTES_PER_DAY);
+        let start = i64::from(self.start_minute);
+        let end = i64::from(self.end_minute);
+        if start <= end {
+            minute >= start && minute < end
+        } else {
+            minute >= start || minute < end
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct NotificationSettingsDto {
+    #[serde(default = "default_true")]
+    pub(crate) enabled: bool,
+    #[serde(default = "default_true")]
+    pub(crate) mentions: bool,
+    #[serde(default = "default_true")]
+    pub(crate) private_channels: bool,
+    #[serde(default)]
+    pub(crate) keywords: Vec<String>,
+    #[serde(default)]
+    pub(crate) muted_channels: Vec<String>,
+    #[serde(default)]
+    pub(crate) dnd: Option<DndSchedule>,
+}
+
+impl Default for NotificationSettingsDto {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mentions: true,
+            private_channels: true,
+            keywords: Vec::new(),
+            muted_channels: Vec::new(),
+            dnd: None,
+        }
+    }
+}
+
+impl NotificationSettingsDto {
+    pub(crate) fn normalized(mut self) -> Self {
+        self.keywords = self
+            .keywords
+            .iter()
+            .map(|keyword| keyword.trim().to_string())
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+        self.keywords.sort_by_key(|keyword| search::fold(keyword));
+        self.keywords
+            .dedup_by(|a, b| search::fold(a) == search::fold(b));
+        self.muted_channels.sort();
+        self.muted_channels.dedup();
+        if let Some(dnd) = &mut self.dnd {
+            dnd.start_minute %= MINUTES_PER_DAY as u16;
+            dnd.end_minute %= MINUTES_PER_DAY as u16;
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum NotificationReason {
+    Mention,
+    PrivateChannel,
+    Keyword { keyword: String },
+}
+
+/// Decides whether a newly created message should raise an OS notification.
+pub(crate) fn evaluate(
+    settings: &NotificationSettingsDto,
+    message: &MessageDto,
+    me: &UserDto,
+    channel_is_private: bool,
+    now_millis: i64,
+) -> Option<NotificationReason> {
+    if !settings.enabled || message.sender_id == me.id || message.deleted_at.is_some() {
+        return None;
+    }
+    if settings.muted_channels.contains(&message.channel_id) {
+        return None;
+    }
+    if settings
+        .dnd
+        .as_ref()
+        .is_some_and(|dnd| dnd.is_active(now_millis))
+    {
+        return None;
+    }
+
+    if settings.mentions && read_state::mentions_user(&message.body_md, me) {
+        return Some(NotificationReason::Mention);
+    }
+    if settings.private_channels && channel_is_private {
+        return Some(NotificationReason::PrivateChannel);
+    }
+    let body = search::fold(&message.body_md);
+    settings
+        .keywords
+        .iter()
+        .find(|keyword| body.contains(&search::fold(keyword)))
+        .map(|keyword| NotificationReason::Keyword {
+            keyword: keyword.clone(),
+        })
+}
+
+pub(crate) fn notification_body(body_md: &str) -> String {
+    let flattened = body_md.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flattened.chars().count() <= NOTIFICATION_BODY_MAX_CHARS {
+        return flattened;
+    }
+    let mut truncated: String = flattened
+        .chars()
+        .take(NOTIFICATION_BODY_MAX_CHARS - 1)
+        .collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn me() -> UserDto {
+        UserDto {
+            id: "me".to_string(),
+            email: "me@galynx.local".to_string(),
+            name: "Ana".to_string(),
+            workspace_id: "ws".to_string(),
+            role: "member".to_string(),
+            avatar_url: None,
+            status: None,
+        }
+    }
+
+    fn message(body: &str) -> MessageDto {
+        MessageDto {
+            id: "m1".to_string(),
+            workspace_id: "ws".to_string(),
+            channel_id: "general".to_string(),
+            sender_id: "u2".to_string(),
+            body_md: body.to_string(),
+            thread_root_id: None,
+            created_at: 1,
+            edited_at: None,
+            deleted_at: None,
+            attachments: Vec::new(),
+            reactions: None,
+            sender: None,
+            body_html: None,
+        }
+    }
+
+    #[test]
+    fn dnd_schedule_wraps_midnight_in_local_time() {
+        let dnd = DndSchedule {
+            start_minute: 22 * 60,
+            end_minute: 7 * 60,
+            utc_offset_minutes: -300,
+        };
+        let at_utc = |hour: i64| hour * 3_600_000;
+        assert!(dnd.is_active(at_utc(4)));
+        assert!(dnd.is_active(at_utc(11)));
+        assert!(!dnd.is_active(at_utc(12)));
+        assert!(!dnd.is_active(at_utc(20)));
+    }
+
+    #[test]
+    fn rules_apply_in_order_and_respect_mute() {
+        let mut settings = NotificationSettingsDto {
+            keywords: vec!["Despliegue".to_string()],
+            ..Default::default()
+        };
+        let me = me();
+        assert_eq!(
+            evaluate(&settings, &message("hola @ana"), &me, false, 0),
+            Some(NotificationReason::Mention)
+        );
+        assert_eq!(
+            evaluate(&settings, &message("hola"), &me, true, 0),
+            Some(NotificationReason::PrivateChannel)
+        );
+        assert_eq!(
+            evaluate(&settings, &message("el DESPLIEGUE falló"), &me, false, 0),
+            Some(NotificationReason::Keyword {
+                keyword: "Despliegue".to_string()
+            })
+        );
+        assert_eq!(evaluate(&settings, &message("hola"), &me, false, 0), None);
+
+        settings.muted_channels.push("general".to_string());
+        assert_eq!(evaluate(&settings, &message("@ana"), &me, true, 0), None);
+    }
+
+    #[test]
+    fn normalizes_keywords_and_truncates_body() {
+        let settings = NotificationSettingsDto {
+            keywords: vec![
+                " Canción ".to_string(),
+                "cancion".to_string(),
+                String::new(),
+            ],
+            ..Default::default()
+        }
+        .normalized();
+        assert_eq!(settings.keywords, vec!["Canción"]);
+
+        let body = notification_body(&"palabra ".repeat(40));
+        assert_eq!(body.chars().count(), NOTIFICATION_BODY_MAX_CHARS);
+        assert!(body.ends_with('…'));
+    }
+}
@@ -0,0 +1,240 @@
+use crate::ApiError;
+
+pub(crate) const PASSWORD_MIN_CHARS: usize = 8;
+pub(crate) const PASSWORD_MAX_CHARS: usize = 128;
+/// Passphrases this long are accepted without mixing character classes.
+pub(crate) const PASSPHRASE_MIN_CHARS: usize = 16;
+
+/// Passwords that pass the length and class rules but are guessed first.
+const COMMON_PASSWORDS: &[&str] = &[
+    "password1",
+    "password123",
+    "passw0rd",
+    "qwerty123",
+    "qwertyuiop",
+    "123456789",
+    "1234567890",
+    "iloveyou1",
+    "admin1234",
+    "welcome1",
+    "changeme123",
+    "contraseña1",
+    "galynx123",
+];
+
+/// Error codes a reset request answers with when the address has no
+/// account. Any other 404 means the route itself is missing.
+const UNKNOWN_ACCOUNT_ERRORS: &[&str] = &["user_not_found", "unknown_user", "unknown_email"];
+
+/// Which request a server error came from, so the same status can be
+/// explained in terms of what the user was doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PasswordFlow {
+    Change,
+    RequestReset,
+    ConfirmReset,
+}
+
+fn weak(message: impl Into<String>) -> ApiError {
+    ApiError::validation("weak_password", message)
+}
+
+/// Client-side strength check run before any password leaves the app.
+/// `user_inputs` are things like the email and name of the account, which
+/// must not make up the password.
+pub(crate) fn check_strength(password: &str, user_inputs: &[&str]) -> Result<(), ApiError> {
+    let length = password.chars().count();
+    if length < PASSWORD_MIN_CHARS {
+        return Err(weak(format!(
+            "password must have at least {PASSWORD_MIN_CHARS} characters"
+        )));
+    }
+    if length > PASSWORD_MAX_CHARS {
+        return Err(weak(format!(
+            "password must have at most {PASSWORD_MAX_CHARS} characters"
+        )));
+    }
+    if password.trim() != password {
+        return Err(weak("password must not start or end with a space"));
+    }
+
+    let classes = [
+        password.chars().any(char::is_lowercase),
+        password.chars().any(char::is_uppercase),
+        password.chars().any(|ch| ch.is_ascii_digit()),
+        password
+            .chars()
+            .any(|ch| !ch.is_alphanumeric() && !ch.is_whitespace()),
+    ]
+    .into_iter()
+    .filter(|present| *present)
+    .count();
+    if classes < 3 && length < PASSPHRASE_MIN_CHARS {
+        return Err(weak(format!(
+            "use at least three of lowercase, uppercase, digits and symbols, \
+             or a passphrase of {PASSPHRASE_MIN_CHARS} characters"
+        )));
+    }
+
+    let lowered = password.to_lowercase();
+    if COMMON_PASSWORDS.contains(&lowered.as_str()) {
+        return Err(weak("this password is too common"));
+    }
+    let first_char = lowered.chars().next();
+    if lowered.chars().all(|ch| Some(ch) == first_char) {
+        return Err(weak("password must not repeat one character"));
+    }
+    for input in user_inputs {
+        let input = input.trim().to_lowercase();
+        let input = input.split('@').next().unwrap_or_default();
+        if input.chars().count() >= 4 && lowered.contains(input) {
+            return Err(weak("password must not contain your name or email"));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a failed reset request only says the address has no account,
+/// which the form reports as success.
+pub(crate) fn is_unknown_account(err: &ApiError) -> bool {
+    matches!(
+        err,
+        ApiError::Http { status: 404, error, .. } if UNKNOWN_ACCOUNT_ERRORS.contains(&error.as_str())
+    )
+}
+
+/// Turns a failed password request into an error the UI can show as is.
+pub(crate) fn map_error(err: ApiError, flow: PasswordFlow) -> ApiError {
+    let (status, error, message) = match err {
+        ApiError::Http {
+            status,
+            error,
+            message,
+        } => (status, error, message),
+        other => return other,
+    };
+    let mapped = |error: &str, message: &str| ApiError::Http {
+        status,
+        error: error.to_string(),
+        message: message.to_string(),
+    };
+    match (flow, status, error.as_str()) {
+        (_, 429, _) => mapped(
+            "too_many_attempts",
+            "too many attempts, wait a few minutes and try again",
+        ),
+        (_, 400 | 422, "weak_password" | "password_policy" | "invalid_password") => {
+            ApiError::Http {
+                status,
+                error: "weak_password".to_string(),
+                message,
+            }
+        }
+        (PasswordFlow::Change, 400 | 401 | 403, "invalid_credentials" | "wrong_password")
+        | (PasswordFlow::Change, 403, _) => mapped(
+            "invalid_current_password",
+            "the current password is not correct",
+        ),
+        (PasswordFlow::ConfirmReset, 400 | 401 | 404 | 410, _) => mapped(
+            "invalid_reset_token",
+            "this reset link is invalid or has expired, request a new one",
+        ),
+        _ => ApiError::Http {
+            status,
+            error,
+            message,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_code(result: Result<(), ApiError>) -> Option<String> {
+        match result {
+            Err(ApiError::Validation { code, .. }) => Some(code.to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn enforces_length_classes_and_common_passwords() {
+        assert!(check_strength("Corto1!", &[]).is_err());
+        assert!(check_strength("solominusculas", &[]).is_err());
+        assert!(check_strength("Mayus-y-minus", &[]).is_ok());
+        assert!(check_strength("una frase bastante larga", &[]).is_ok());
+        assert!(check_strength(" Espacio-1 ", &[]).is_err());
+        assert!(check_strength("Password123", &[]).is_err());
+        assert!(check_strength(&"a".repeat(20), &[]).is_err());
+        assert!(check_strength(&"Aa1".repeat(50), &[]).is_err());
+        assert_eq!(
+            error_code(check_strength("abc", &[])).as_deref(),
+            Some("weak_password")
+        );
+    }
+
+    #[test]
+    fn rejects_personal_information() {
+        let inputs = ["lucia.perez@galynx.local", "Lucía"];
+        assert!(check_strength("Lucia.Perez-2024", &inputs).is_err());
+        assert!(check_strength("Galaxia-2024!", &inputs).is_ok());
+    }
+
+    #[test]
+    fn maps_server_errors_per_flow() {
+        let http = |status: u16, error: &str| ApiError::Http {
+            status,
+            error: error.to_string(),
+            message: "server message".to_string(),
+        };
+        let code = |err: ApiError| match err {
+            ApiError::Http { error, .. } => error,
+            other => other.to_string(),
+        };
+
+        assert_eq!(
+            code(map_error(http(403, "forbidden"), PasswordFlow::Change)),
+            "invalid_current_password"
+        );
+        assert_eq!(
+            code(map_error(http(410, "gone"), PasswordFlow::ConfirmReset)),
+            "invalid_reset_token"
+        );
+        assert_eq!(
+            code(map_error(http(404, "not_found"), PasswordFlow::Change)),
+            "not_found"
+        );
+        assert_eq!(
+            code(map_error(
+                http(429, "rate_limited"),
+                PasswordFlow::RequestReset
+            )),
+            "too_many_attempts"
+        );
+        assert_eq!(
+            code(map_error(
+                http(422, "password_policy"),
+                PasswordFlow::ConfirmReset
+            )),
+            "weak_password"
+        );
+        assert!(matches!(
+            map_error(ApiError::Network("down".to_string()), PasswordFlow::Change),
+            ApiError::Network(_)
+        ));
+    }
+
+    #[test]
+    fn only_explicit_unknown_account_errors_hide_a_404() {
+        let http = |status: u16, error: &str| ApiError::Http {
+            status,
+            error: error.to_string(),
+            message: "server message".to_string(),
+        };
+        assert!(is_unknown_account(&http(404, "user_not_found")));
+        assert!(!is_unknown_account(&http(404, "not_found")));
+        assert!(!is_unknown_account(&http(400, "user_not_found")));
+        assert!(!is_unknown_account(&ApiError::Network("down".to_string())));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{roles::Role, ChannelDto, MessageDto};
+use serde::{Deserialize, Serialize};
+
+/// Actions from the permission table in `plan_de_desarrollo_galynx.md`, plus
+/// the channel and member management that sits at the same level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Permission {
+    CreateChannels,
+    InviteUsers,
+    SendMessages,
+    EditOwnMessages,
+    DeleteMessages,
+    DeleteChannels,
+    ManageChannels,
+    ManageMembers,
+    DisableUsers,
+}
+
+impl Permission {
+    pub(crate) const ALL: [Permission; 9] = [
+        Permission::CreateChannels,
+        Permission::InviteUsers,
+        Permission::SendMessages,
+        Permission::EditOwnMessages,
+        Permission::DeleteMessages,
+        Permission::DeleteChannels,
+        Permission::ManageChannels,
+        Permission::ManageMembers,
+        Permission::DisableUsers,
+    ];
+}
+
+/// What an action applies to. Without a channel or message only the role
+/// table is consulted, so callers must not pass `None` for a channel or
+/// message they were asked about but could not find.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PermissionContext<'a> {
+    pub(crate) user_id: &'a str,
+    pub(crate) channel: Option<&'a ChannelDto>,
+    pub(crate) message: Option<&'a MessageDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PermissionsDto {
+    pub(crate) role: Role,
+    pub(crate) allowed: Vec<Permission>,
+}
+
+pub(crate) fn can(role: Role, permission: Permission, context: PermissionContext<'_>) -> bool {
+    let staff = matches!(role, Role::Owner | Role::Admin);
+    let archived = context.channel.is_some_and(|channel| channel.archived);
+    let message_deleted = context
+        .message
+        .is_some_and(|message| message.deleted_at.is_some());
+
+    match permission {
+        Permission::SendMessages => !archived,
+        Permission::EditOwnMessages => {
+            !archived
+                && !message_deleted
+                && context
+                    .message
+                    .map_or(true, |message| message.sender_id == context.user_id)
+        }
+        Permission::DeleteMessages => staff && !message_deleted,
+        Permission::CreateChannels
+        | Permission::InviteUsers
+        | Permission::DeleteChannels
+        | Permission::ManageChannels
+        | Permission::ManageMembers
+        | Permission::DisableUsers => staff,
+    }
+}
+
+pub(crate) fn allowed(role: Role, context: PermissionContext<'_>) -> Vec<Permission> {
+    Permission::ALL
+        .into_iter()
+        .filter(|permission| can(role, *permission, context))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sender_id: &str) -> MessageDto {
+        MessageDto {
+            id: "m1".to_string(),
+            workspace_id: "ws".to_string(),
+            channel_id: "general".to_string(),
+            sender_id: sender_id.to_string(),
+            body_md: "hola".to_string(),
+            thread_root_id: None,
+            created_at: 1,
+            edited_at: None,
+            deleted_at: None,
+            attachments: Vec::new(),
+            reactions: None,
+            sender: None,
+            body_html: None,
+        }
+    }
+
+    fn channel(archived: bool) -> ChannelDto {
+        ChannelDto {
+            id: "general".to_string(),
+            workspace_id: "ws".to_string(),
+            name: "general".to_string(),
+            is_private: false,
+            created_by: "o1".to_string(),
+            created_at: 1,
+            unread_count: 0,
+            mention_count: 0,
+            last_read_at: None,
+            topic: None,
+            archived,
+        }
+    }
+
+    #[test]
+    fn follows_the_role_table() {
+        let context = PermissionContext {
+            user_id: "me",
+            ..PermissionContext::default()
+        };
+        assert_eq!(allowed(Role::Owner, context), Permission::ALL.to_vec());
+        assert_eq!(allowed(Role::Admin, context), Permission::ALL.to_vec());
+        assert_eq!(
+            allowed(Role::Member, context),
+            vec![Permission::SendMessages, Permission::EditOwnMessages]
+        );
+    }
+
+    #[test]
+    fn edits_only_own_messages() {
+        let mine = message("me");
+        let theirs = message("u2");
+        let on = |message| PermissionContext {
+            user_id: "me",
+            channel: None,
+            message: Some(message),
+        };
+        assert!(can(Role::Member, Permission::EditOwnMessages, on(&mine)));
+        assert!(!can(Role::Member, Permission::EditOwnMessages, on(&theirs)));
+        assert!(!can(Role::Owner, Permission::EditOwnMessages, on(&theirs)));
+        assert!(can(Role::Admin, Permission::DeleteMessages, on(&theirs)));
+        assert!(!can(Role::Member, Permission::DeleteMessages, on(&mine)));
+    }
+
+    #[test]
+    fn archived_channels_are_read_only() {
+        let archived = channel(true);
+        let mine = message("me");
+        let context = PermissionContext {
+            user_id: "me",
+            channel: Some(&archived),
+            message: Some(&mine),
+        };
+        assert!(!can(Role::Owner, Permission::SendMessages, context));
+        assert!(!can(Role::Member, Permission::EditOwnMessages, context));
+        assert!(can(Role::Owner, Permission::ManageChannels, context));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{snapshot::Snapshot, ApiError, MessageDto};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PinDto {
+    pub(crate) message_id: String,
+    pub(crate) channel_id: String,
+    #[serde(default)]
+    pub(crate) pinned_by: Option<String>,
+    #[serde(default)]
+    pub(crate) pinned_at: i64,
+    #[serde(default)]
+    pub(crate) message: Option<MessageDto>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PinSource {
+    Server,
+    Local,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PinListDto {
+    pub(crate) items: Vec<PinDto>,
+    pub(crate) source: PinSource,
+}
+
+/// Pins kept on this machine while the API has no pin endpoints, per
+/// signed-in user and channel. Only the ids are stored; message bodies come
+/// from the message cache when listed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct LocalPinBook {
+    users: HashMap<String, HashMap<String, Vec<PinDto>>>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl LocalPinBook {
+    /// Newest pin first.
+    pub(crate) fn list(&self, user_id: &str, channel_id: &str) -> Vec<PinDto> {
+        let mut pins = self
+            .users
+            .get(user_id)
+            .and_then(|channels| channels.get(channel_id))
+            .cloned()
+            .unwrap_or_default();
+        pins.sort_by(|a, b| b.pinned_at.cmp(&a.pinned_at));
+        pins
+    }
+
+    /// Pinning an already pinned message returns the existing pin.
+    pub(crate) fn add(&mut self, user_id: &str, mut pin: PinDto) -> PinDto {
+        pin.message = None;
+        let pins = self
+            .users
+            .entry(user_id.to_string())
+            .or_default()
+            .entry(pin.channel_id.clone())
+            .or_default();
+        if let Some(existing) = pins.iter().find(|item| item.message_id == pin.message_id) {
+            return existing.clone();
+        }
+        pins.push(pin.clone());
+        self.dirty = true;
+        pin
+    }
+
+    pub(crate) fn remove(&mut self, user_id: &str, channel_id: &str, message_id: &str) -> bool {
+        let Some(pins) = self
+            .users
+            .get_mut(user_id)
+            .and_then(|channels| channels.get_mut(channel_id))
+        else {
+            return false;
+        };
+        let before = pins.len();
+        pins.retain(|pin| pin.message_id != message_id);
+        let removed = pins.len() != before;
+        if removed {
+            self.dirty = true;
+        }
+        removed
+    }
+}
+
+impl Snapshot for LocalPinBook {
+    fn dirty_flag(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+/// Whether a pin request failed because the server has no pin routes, as
+/// opposed to the channel or message not existing. Missing routes answer 405,
+/// or 404 without the API's own error body.
+pub(crate) fn is_route_missing(err: &ApiError) -> bool {
+    match err {
+        ApiError::Http { status: 405, .. } => true,
+        ApiError::Http {
+            status: 404, error, ..
+        } => matches!(
+            error.as_str(),
+            "http_error" | "unknown_error" | "route_not_found"
+        ),
+        _ => false,
+    }
+}
+
+/// Reads a pin list response, either a bare array or `{ items: [...] }`.
+pub(crate) fn parse_list(value: serde_json::Value) -> Result<Vec<PinDto>, serde_json::Error> {
+    match value {
+        serde_json::Value::Object(mut object) if object.contains_key("items") => {
+            serde_json::from_value(object.remove("items").unwrap_or_default())
+        }
+        other => serde_json::from_value(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+    use serde_json::json;
+
+    fn pin(message_id: &str, pinned_at: i64) -> PinDto {
+        PinDto {
+            message_id: message_id.to_string(),
+            channel_id: "general".to_string(),
+            pinned_by: Some("me".to_string()),
+            pinned_at,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn tells_missing_routes_from_missing_messages() {
+        let http = |status: u16, error: &str| ApiError::Http {
+            status,
+            error: error.to_string(),
+            message: "server message".to_string(),
+        };
+        assert!(is_route_missing(&http(405, "method_not_allowed")));
+        assert!(is_route_missing(&http(404, "http_error")));
+        assert!(!is_route_missing(&http(404, "message_not_found")));
+        assert!(!is_route_missing(&http(404, "not_found")));
+        assert!(!is_route_missing(&ApiError::Network("down".to_string())));
+    }
+
+    #[test]
+    fn local_pins_are_deduplicated_and_newest_first() {
+        let mut book = LocalPinBook::default();
+        book.add("me", pin("m1", 10));
+        book.add("me", pin("m2", 20));
+        assert_eq!(book.add("me", pin("m1", 30)).pinned_at, 10);
+
+        let listed: Vec<_> = book
+            .list("me", "general")
+            .into_iter()
+            .map(|pin| pin.message_id)
+            .collect();
+        assert_eq!(listed, vec!["m2", "m1"]);
+        assert!(book.list("other", "general").is_empty());
+
+        assert!(book.remove("me", "general", "m1"));
+        assert!(!book.remove("me", "general", "m1"));
+        assert_eq!(book.list("me", "general").len(), 1);
+    }
+
+    #[test]
+    fn snapshot_only_when_dirty_and_round_trips() {
+        let restored = snapshot::assert_round_trips(&mut LocalPinBook::default(), |book| {
+            book.add("me", pin("m1", 10));
+        });
+        let restored = restored.list("me", "general");
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].message_id, "m1");
+    }
+
+    #[test]
+    fn parses_bare_and_wrapped_lists() {
+        let item = json!({ "message_id": "m1", "channel_id": "general" });
+        assert_eq!(parse_list(json!([item.clone()])).unwrap().len(), 1);
+        let wrapped = parse_list(json!({ "items": [item], "next_cursor": null })).unwrap();
+        assert_eq!(wrapped[0].pinned_by, None);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::ApiError;
+use image::{GenericImageView, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+use tauri::{AppHandle, Manager};
+
+const THUMBNAIL_MAX_EDGE: u32 = 320;
+const TEXT_PREVIEW_MAX_LINES: usize = 20;
+const TEXT_PREVIEW_MAX_BYTES: usize = 4 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PreviewKind {
+    Image,
+    Text,
+    /// Text of the first page; the page count is kept alongside.
+    Pdf,
+}
+
+/// Preview stored in the app cache dir. `width`/`height` describe the original
+/// image, not the thumbnail.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AttachmentPreviewDto {
+    pub(crate) attachment_id: String,
+    pub(crate) kind: PreviewKind,
+    pub(crate) path: String,
+    pub(crate) size_bytes: u64,
+    pub(crate) width: Option<u32>,
+    pub(crate) height: Option<u32>,
+    #[serde(default)]
+    pub(crate) page_count: Option<u32>,
+}
+
+#[derive(Debug)]
+pub(crate) struct GeneratedPreview {
+    kind: PreviewKind,
+    extension: &'static str,
+    bytes: Vec<u8>,
+    pub(crate) width: Option<u32>,
+    pub(crate) height: Option<u32>,
+    page_count: Option<u32>,
+}
+
+pub(crate) fn previews_dir(app: &AppHandle) -> Result<PathBuf, ApiError> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("previews"))
+        .map_err(|err| ApiError::Storage(format!("could not resolve cache dir: {err}")))
+}
+
+fn is_text_type(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || matches!(
+            content_type,
+            "application/json"
+                | "application/xml"
+                | "application/x-yaml"
+                | "application/javascript"
+        )
+}
+
+pub(crate) fn is_previewable(content_type: &str) -> bool {
+    content_type.starts_with("image/")
+        || content_type == "application/pdf"
+        || is_text_type(content_type)
+}
+
+pub(crate) fn generate_preview(content_type: &str, bytes: &[u8]) -> Option<GeneratedPreview> {
+    if content_type.starts_with("image/") {
+        return image_preview(bytes);
+    }
+    if content_type == "application/pdf" {
+        return pdf_preview(bytes);
+    }
+    if is_text_type(content_type) {
+        return Some(text_preview(bytes));
+    }
+    None
+}
+
+fn image_preview(bytes: &[u8]) -> Option<GeneratedPreview> {
+    let image = image::load_from_memory(bytes).ok()?;
+    let (width, height) = image.dimensions();
+    let thumbnail = if width > THUMBNAIL_MAX_EDGE || height > THUMBNAIL_MAX_EDGE {
+        image.thumbnail(THUMBNAIL_MAX_EDGE, THUMBNAIL_MAX_EDGE)
+    } else {
+        image
+    };
+
+    let mut encoded = Cursor::new(Vec::new());
+    thumbnail.write_to(&mut encoded, ImageFormat::Png).ok()?;
+
+    Some(GeneratedPreview {
+        kind: PreviewKind::Image,
+        extension: "png",
+        bytes: encoded.into_inner(),
+        width: Some(width),
+        height: Some(height),
+        page_count: None,
+    })
+}
+
+fn first_lines(text: &str) -> String {
+    text.lines()
+        .take(TEXT_PREVIEW_MAX_LINES)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn text_preview(bytes: &[u8]) -> GeneratedPreview {
+    let head = &bytes[..bytes.len().min(TEXT_PREVIEW_MAX_BYTES)];
+    let text = String::from_utf8_lossy(head);
+    let excerpt = first_lines(text.trim_end_matches('\u{FFFD}'));
+
+    GeneratedPreview {
+        kind: PreviewKind::Text,
+        extension: "txt",
+        bytes: excerpt.into_bytes(),
+        width: None,
+        height: None,
+        page_count: None,
+    }
+}
+
+/// The first lines of text on page one. PDFs are not rasterized; a scanned
+/// page yields an empty excerpt but still reports the page count.
+fn pdf_preview(bytes: &[u8]) -> Option<GeneratedPreview> {
+    let document = lopdf::Document::load_mem(bytes).ok()?;
+    let pages = document.get_pages();
+    let first_page = *pages.keys().next()?;
+    let text = document.extract_text(&[first_page]).unwrap_or_default();
+    let mut excerpt = first_lines(text.trim());
+    if excerpt.len() > TEXT_PREVIEW_MAX_BYTES {
+        let mut end = TEXT_PREVIEW_MAX_BYTES;
+        while !excerpt.is_char_boundary(end) {
+            end -= 1;
+        }
+        excerpt.truncate(end);
+    }
+
+    Some(GeneratedPreview {
+        kind: PreviewKind::Pdf,
+        extension: "txt",
+        bytes: excerpt.into_bytes(),
+        width: None,
+        height: None,
+        page_count: u32::try_from(pages.len()).ok(),
+    })
+}
+
+fn file_stem(attachment_id: &str) -> String {
+    attachment_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn sidecar_path(dir: &Path, attachment_id: &str) -> PathBuf {
+    dir.join(format!("{}.json", file_stem(attachment_id)))
+}
+
+pub(crate) fn store_preview(
+    dir: &Path,
+    attachment_id: &str,
+    preview: GeneratedPreview,
+) -> Result<AttachmentPreviewDto, ApiError> {
+    fs::create_dir_all(dir)
+        .map_err(|err| ApiError::Storage(format!("could not create preview dir: {err}")))?;
+
+    let path = dir.join(format!(
+        "{}.{}",
+        file_stem(attachment_id),
+        preview.extension
+    ));
+    fs::write(&path, &preview.bytes)
+        .map_err(|err| ApiError::Storage(format!("could not write preview: {err}")))?;
+
+    let dto = AttachmentPreviewDto {
+        attachment_id: attachment_id.to_string(),
+        kind: preview.kind,
+        path: path.to_string_lossy().to_string(),
+        size_bytes: preview.bytes.len() as u64,
+        width: preview.width,
+        height: preview.height,
+        page_count: preview.page_count,
+    };
+    let sidecar = serde_json::to_vec(&dto)
+        .map_err(|err| ApiError::Storage(format!("could not serialize preview: {err}")))?;
+    fs::write(sidecar_path(dir, attachment_id), sidecar)
+        .map_err(|err| ApiError::Storage(format!("could not write preview metadata: {err}")))?;
+    Ok(dto)
+}
+
+pub(crate) fn cached_preview(dir: &Path, attachment_id: &str) -> Option<AttachmentPreviewDto> {
+    let raw = fs::read(sidecar_path(dir, attachment_id)).ok()?;
+    let dto: AttachmentPreviewDto = serde_json::from_slice(&raw).ok()?;
+    Path::new(&dto.path).exists().then_some(dto)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(width, height, Rgb([10_u8, 20, 30]));
+        let mut encoded = Cursor::new(Vec::new());
+        image.write_to(&mut encoded, ImageFormat::Png).unwrap();
+        encoded.into_inner()
+    }
+
+    #[test]
+    fn image_preview_downscales_and_keeps_original_dimensions() {
+        let preview = generate_preview("image/png", &png_bytes(1280, 640)).unwrap();
+        assert_eq!(preview.kind, PreviewKind::Image);
+        assert_eq!((preview.width, preview.height), (Some(1280), Some(640)));
+
+        let thumbnail = image::load_from_memory(&preview.bytes).unwrap();
+        assert_eq!(thumbnail.dimensions(), (320, 160));
+    }
+
+    #[test]
+    fn text_preview_keeps_first_lines() {
+        let body = (1..=50)
+            .map(|n| format!("línea {n}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let preview = generate_preview("text/plain", body.as_bytes()).unwrap();
+        let excerpt = String::from_utf8(preview.bytes).unwrap();
+        assert_eq!(excerpt.lines().count(), TEXT_PREVIEW_MAX_LINES);
+        assert!(excerpt.starts_with("línea 1\n"));
+    }
+
+    fn pdf_bytes(pages: &[&str]) -> Vec<u8> {
+        use lopdf::{
+            content::{Content, Operation},
+            dictionary, Document, Object, Stream,
+        };
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let kids: Vec<Object> = pages
+            .iter()
+            .map(|text| {
+                let content = Content {
+                    operations: vec![
+                        Operation::new("BT", vec![]),
+                        Operation::new("Tf", vec!["F1".into(), 24.into()]),
+                        Operation::new("Td", vec![100.into(), 600.into()]),
+                        Operation::new("Tj", vec![Object::string_literal(*text)]),
+                        Operation::new("ET", vec![]),
+                    ],
+                };
+                let content_id =
+                    doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                })
+                .into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as u32,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn pdf_preview_keeps_first_page_text_and_page_count() {
+        let pdf = pdf_bytes(&["Informe trimestral", "Anexo"]);
+        let preview = generate_preview("application/pdf", &pdf).unwrap();
+        assert_eq!(preview.kind, PreviewKind::Pdf);
+        assert_eq!(preview.page_count, Some(2));
+
+        let excerpt = String::from_utf8(preview.bytes).unwrap();
+        assert!(excerpt.contains("Informe trimestral"));
+        assert!(!excerpt.contains("Anexo"));
+
+        assert!(generate_preview("application/pdf", b"%PDF-1.4 truncated").is_none());
+        assert!(generate_preview("application/zip", b"PK").is_none());
+    }
+
+    #[test]
+    fn stored_preview_is_read_back_from_sidecar() {
+        let dir = std::env::temp_dir().join(format!("galynx-previews-{}", std::process::id()));
+        let preview = generate_preview("text/plain", b"hola\nmundo").unwrap();
+        let stored = store_preview(&dir, "att/1", preview).unwrap();
+        assert!(stored.path.ends_with("att_1.txt"));
+
+        let cached = cached_preview(&dir, "att/1").unwrap();
+        assert_eq!(cached.kind, PreviewKind::Text);
+        assert_eq!(cached.size_bytes, 10);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
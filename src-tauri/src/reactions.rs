@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ReactionSummaryDto {
+    pub(crate) emoji: String,
+    pub(crate) count: u32,
+    #[serde(default)]
+    pub(crate) me: bool,
+    #[serde(default)]
+    pub(crate) user_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReactionChange {
+    Added,
+    Removed,
+}
+
+/// Applies a single user's reaction change. Repeating the same change is a
+/// no-op, so the optimistic update after an ACK and the `REACTION_UPDATED`
+/// broadcast that follows it do not double count.
+pub(crate) fn apply_change(
+    reactions: &mut Vec<ReactionSummaryDto>,
+    emoji: &str,
+    user_id: &str,
+    change: ReactionChange,
+    my_user_id: Option<&str>,
+) {
+    let is_me = my_user_id == Some(user_id);
+    let position = reactions.iter().position(|item| item.emoji == emoji);
+
+    match (change, position) {
+        (ReactionChange::Added, Some(idx)) => {
+            let summary = &mut reactions[idx];
+            if summary.user_ids.iter().any(|id| id == user_id) {
+                return;
+            }
+            summary.user_ids.push(user_id.to_string());
+            summary.count += 1;
+            summary.me |= is_me;
+        }
+        (ReactionChange::Added, None) => reactions.push(ReactionSummaryDto {
+            emoji: emoji.to_string(),
+            count: 1,
+            me: is_me,
+            user_ids: vec![user_id.to_string()],
+        }),
+        (ReactionChange::Removed, Some(idx)) => {
+            let summary = &mut reactions[idx];
+            let before = summary.user_ids.len();
+            summary.user_ids.retain(|id| id != user_id);
+            if summary.user_ids.len() == before && !(is_me && summary.me) {
+                return;
+            }
+            summary.count = summary.count.saturating_sub(1);
+            if is_me {
+                summary.me = false;
+            }
+            if summary.count == 0 {
+                reactions.remove(idx);
+            }
+        }
+        (ReactionChange::Removed, None) => {}
+    }
+}
+
+/// Parses the `reactions` array of a `REACTION_UPDATED` payload, marking the
+/// entries the current user took part in.
+pub(crate) fn parse_summary(
+    value: &JsonValue,
+    my_user_id: Option<&str>,
+) -> Vec<ReactionSummaryDto> {
+    let Some(items) = value.as_array() else {
+        return Vec::new();
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let emoji = item.get("emoji").and_then(JsonValue::as_str)?.to_string();
+            let user_ids: Vec<String> = item
+                .get("user_ids")
+                .or_else(|| item.get("users"))
+                .and_then(JsonValue::as_array)
+                .map(|ids| {
+                    ids.iter()
+                        .filter_map(JsonValue::as_str)
+                        .map(ToString::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+            let count = item
+                .get("count")
+                .and_then(JsonValue::as_u64)
+                .map(|count| count as u32)
+                .unwrap_or(user_ids.len() as u32);
+            let me = item
+                .get("me")
+                .and_then(JsonValue::as_bool)
+                .unwrap_or_else(|| my_user_id.is_some_and(|me| user_ids.iter().any(|id| id == me)));
+            (count > 0).then_some(ReactionSummaryDto {
+                emoji,
+                count,
+                me,
+                user_ids,
+            })
+        })
+        .collect()
+}
+
+/// Reads the single-user change form of `REACTION_UPDATED`
+/// (`{ emoji, user_id, action: "added" | "removed" }`).
+pub(crate) fn parse_change(payload: &JsonValue) -> Option<(String, String, ReactionChange)> {
+    let emoji = payload.get("emoji").and_then(JsonValue::as_str)?;
+    let user_id = payload.get("user_id").and_then(JsonValue::as_str)?;
+    let action = payload
+        .get("action")
+        .or_else(|| payload.get("op"))
+        .and_then(JsonValue::as_str)?;
+    let change = match action.to_ascii_lowercase().as_str() {
+        "add" | "added" | "add_reaction" => ReactionChange::Added,
+        "remove" | "removed" | "remove_reaction" => ReactionChange::Removed,
+        _ => return None,
+    };
+    Some((emoji.to_string(), user_id.to_string(), change))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn repeated_changes_are_idempotent() {
+        let mut reactions = Vec::new();
+        apply_change(
+            &mut reactions,
+            "👍",
+            "u1",
+            ReactionChange::Added,
+            Some("u1"),
+        );
+        apply_change(
+            &mut reactions,
+            "👍",
+            "u1",
+            ReactionChange::Added,
+            Some("u1"),
+        );
+        apply_change(
+            &mut reactions,
+            "👍",
+            "u2",
+            ReactionChange::Added,
+            Some("u1"),
+        );
+        assert_eq!(reactions.len(), 1);
+        assert_eq!(reactions[0].count, 2);
+        assert!(reactions[0].me);
+
+        apply_change(
+            &mut reactions,
+            "👍",
+            "u1",
+            ReactionChange::Removed,
+            Some("u1"),
+        );
+        apply_change(
+            &mut reactions,
+            "👍",
+            "u1",
+            ReactionChange::Removed,
+            Some("u1"),
+        );
+        assert_eq!(reactions[0].count, 1);
+        assert!(!reactions[0].me);
+
+        apply_change(
+            &mut reactions,
+            "👍",
+            "u2",
+            ReactionChange::Removed,
+            Some("u1"),
+        );
+        assert!(reactions.is_empty());
+    }
+
+    #[test]
+    fn summary_marks_my_reactions() {
+        let parsed = parse_summary(
+            &json!([
+                { "emoji": "🎉", "user_ids": ["u1", "u2"] },
+                { "emoji": "👀", "count": 3 },
+                { "emoji": "🙈", "count": 0 }
+            ]),
+            Some("u2"),
+        );
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].count, 2);
+        assert!(parsed[0].me);
+        assert_eq!(parsed[1].count, 3);
+        assert!(!parsed[1].me);
+    }
+
+    #[test]
+    fn change_payload_accepts_action_aliases() {
+        let (emoji, user_id, change) =
+            parse_change(&json!({ "emoji": "🔥", "user_id": "u9", "op": "REMOVE" })).unwrap();
+        assert_eq!((emoji.as_str(), user_id.as_str()), ("🔥", "u9"));
+        assert_eq!(change, ReactionChange::Removed);
+        assert!(parse_change(&json!({ "emoji": "🔥", "user_id": "u9" })).is_none());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{mentions, snapshot::Snapshot, MessageDto, UserDto};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ChannelReadState {
+    pub(crate) last_read_message_id: Option<String>,
+    pub(crate) last_read_at: Option<i64>,
+    pub(crate) unread_count: u32,
+    pub(crate) mention_count: u32,
+}
+
+/// Read position per channel, kept per signed-in user so switching accounts
+/// on the same machine does not mix their unread counts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ReadStateBook {
+    users: HashMap<String, HashMap<String, ChannelReadState>>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+/// Whether `body_md` addresses `user` directly or through a broadcast mention.
+/// Mentions inside code and e-mail addresses do not count.
+pub(crate) fn mentions_user(body_md: &str, user: &UserDto) -> bool {
+    mentions::parse(body_md)
+        .iter()
+        .any(|token| mentions::addresses(token, user))
+}
+
+impl ReadStateBook {
+    pub(crate) fn channel(&self, user_id: &str, channel_id: &str) -> ChannelReadState {
+        self.users
+            .get(user_id)
+            .and_then(|channels| channels.get(channel_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn channel_mut(&mut self, user_id: &str, channel_id: &str) -> &mut ChannelReadState {
+        self.users
+            .entry(user_id.to_string())
+            .or_default()
+            .entry(channel_id.to_string())
+            .or_default()
+    }
+
+    /// Counts a newly created top-level message. Returns the updated state
+    /// when it changed. The user's own messages move the read position
+    /// forward instead.
+    pub(crate) fn record_message(
+        &mut self,
+        user_id: &str,
+        message: &MessageDto,
+        mentions_me: bool,
+    ) -> Option<ChannelReadState> {
+        if message.thread_root_id.is_some() || message.deleted_at.is_some() {
+            return None;
+        }
+        if message.sender_id == user_id {
+            return Some(self.mark_read(
+                user_id,
+                &message.channel_id,
+                Some(message.id.clone()),
+                message.created_at,
+                0,
+                0,
+            ));
+        }
+
+        let state = self.channel_mut(user_id, &message.channel_id);
+        if state
+            .last_read_at
+            .is_some_and(|read_at| message.created_at <= read_at)
+        {
+            return None;
+        }
+        state.unread_count += 1;
+        if mentions_me {
+            state.mention_count += 1;
+        }
+        let updated = state.clone();
+        self.dirty = true;
+        Some(updated)
+    }
+
+    /// Moves the read position to `read_at`; `unread_count`/`mention_count`
+    /// are what is still known to be unread past that point.
+    pub(crate) fn mark_read(
+        &mut self,
+        user_id: &str,
+        channel_id: &str,
+        message_id: Option<String>,
+        read_at: i64,
+        unread_count: u32,
+        mention_count: u32,
+    ) -> ChannelReadState {
+        let state = self.channel_mut(user_id, channel_id);
+        if state
+            .last_read_at
+            .map_or(true, |current| read_at >= current)
+        {
+            state.last_read_message_id = message_id;
+            state.last_read_at = Some(read_at);
+        }
+        state.unread_count = unread_count;
+        state.mention_count = mention_count;
+        let updated = state.clone();
+        self.dirty = true;
+        updated
+    }
+}
+
+impl Snapshot for ReadStateBook {
+    fn dirty_flag(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+
+    fn user() -> UserDto {
+        UserDto {
+            id: "me".to_string(),
+            email: "me@galynx.local".to_string(),
+            name: "Lucía".to_string(),
+            workspace_id: "ws".to_string(),
+            role: "member".to_string(),
+            avatar_url: None,
+            status: None,
+        }
+    }
+
+    fn message(id: &str, sender_id: &str, created_at: i64) -> MessageDto {
+        MessageDto {
+            id: id.to_string(),
+            workspace_id: "ws".to_string(),
+            channel_id: "general".to_string(),
+            sender_id: sender_id.to_string(),
+            body_md: "hola".to_string(),
+            thread_root_id: None,
+            created_at,
+            edited_at: None,
+            deleted_at: None,
+            attachments: Vec::new(),
+            reactions: None,
+            sender: None,
+            body_html: None,
+        }
+    }
+
+    #[test]
+    fn detects_direct_and_broadcast_mentions() {
+        let me = user();
+        assert!(mentions_user("hola @LUCÍA, ¿revisas?", &me));
+        assert!(mentions_user("@here deploy en 5", &me));
+        assert!(mentions_user("cc @me", &me));
+        assert!(!mentions_user("lucía sin arroba", &me));
+        assert!(mentions_user("ping <@me>", &me));
+        assert!(!mentions_user("hola @lucíana", &me));
+        assert!(!mentions_user("escribe a soporte@here.com", &me));
+        assert!(!mentions_user("usa `@channel` en el canal", &me));
+        assert!(!mentions_user("ping <@other>", &me));
+    }
+
+    #[test]
+    fn counts_messages_after_read_position() {
+        let mut book = ReadStateBook::default();
+        book.mark_read("me", "general", Some("m1".to_string()), 10, 0, 0);
+
+        assert!(book
+            .record_message("me", &message("m0", "u2", 5), false)
+            .is_none());
+        book.record_message("me", &message("m2", "u2", 11), false);
+        let state = book
+            .record_message("me", &message("m3", "u2", 12), true)
+            .unwrap();
+        assert_eq!((state.unread_count, state.mention_count), (2, 1));
+
+        let mut reply = message("m4", "u2", 13);
+        reply.thread_root_id = Some("m2".to_string());
+        assert!(book.record_message("me", &reply, true).is_none());
+
+        let state = book
+            .record_message("me", &message("m5", "me", 14), false)
+            .unwrap();
+        assert_eq!(state.unread_count, 0);
+        assert_eq!(state.last_read_message_id.as_deref(), Some("m5"));
+        assert_eq!(
+            book.channel("other", "general"),
+            ChannelReadState::default()
+        );
+    }
+
+    #[test]
+    fn snapshot_only_when_dirty_and_round_trips() {
+        let restored = snapshot::assert_round_trips(&mut ReadStateBook::default(), |book| {
+            book.mark_read("me", "general", None, 42, 0, 0);
+        });
+        assert_eq!(restored.channel("me", "general").last_read_at, Some(42));
+    }
+}
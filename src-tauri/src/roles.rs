@@ -0,0 +1,146 @@
+use crate::ApiError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Role {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl Role {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "owner" => Some(Self::Owner),
+            "admin" => Some(Self::Admin),
+            "member" => Some(Self::Member),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Owner => "owner",
+            Self::Admin => "admin",
+            Self::Member => "member",
+        }
+    }
+}
+
+/// `users.status` in the data model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum UserStatus {
+    Active,
+    Disabled,
+}
+
+impl UserStatus {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Disabled => "disabled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MemberRole {
+    pub(crate) user_id: String,
+    pub(crate) role: Option<Role>,
+    pub(crate) disabled: bool,
+}
+
+/// Reads the roles out of a `/workspaces/:id/members` response.
+pub(crate) fn member_roles(members: &[JsonValue]) -> Vec<MemberRole> {
+    members
+        .iter()
+        .filter_map(|member| {
+            let field = |key: &str| member.get(key).and_then(JsonValue::as_str);
+            Some(MemberRole {
+                user_id: field("user_id").or_else(|| field("id"))?.to_string(),
+                role: field("role").and_then(Role::parse),
+                disabled: field("status") == Some(UserStatus::Disabled.as_str()),
+            })
+        })
+        .collect()
+}
+
+/// Refuses a change that would leave the workspace without an active owner.
+/// `new_role` is `None` when `user_id` is being removed or disabled.
+pub(crate) fn ensure_owner_remains(
+    members: &[MemberRole],
+    user_id: &str,
+    new_role: Option<Role>,
+) -> Result<(), ApiError> {
+    if new_role == Some(Role::Owner) {
+        return Ok(());
+    }
+    let is_active_owner =
+        |member: &&MemberRole| member.role == Some(Role::Owner) && !member.disabled;
+    let target_is_owner = members
+        .iter()
+        .filter(is_active_owner)
+        .any(|member| member.user_id == user_id);
+    let other_owners = members
+        .iter()
+        .filter(is_active_owner)
+        .filter(|member| member.user_id != user_id)
+        .count();
+    if target_is_owner && other_owners == 0 {
+        return Err(ApiError::Http {
+            status: 409,
+            error: "last_owner".to_string(),
+            message: "the workspace must keep at least one active owner".to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn members() -> Vec<MemberRole> {
+        member_roles(&[
+            json!({ "user_id": "o1", "role": "owner" }),
+            json!({ "user_id": "o2", "role": "owner", "status": "disabled" }),
+            json!({ "id": "a1", "role": "ADMIN" }),
+            json!({ "user_id": "m1" }),
+        ])
+    }
+
+    #[test]
+    fn parses_roles_and_statuses() {
+        let members = members();
+        assert_eq!(members[0].role, Some(Role::Owner));
+        assert!(members[1].disabled);
+        assert_eq!(members[2].role, Some(Role::Admin));
+        assert_eq!(members[3].role, None);
+        assert_eq!(Role::parse(Role::Member.as_str()), Some(Role::Member));
+        assert_eq!(
+            serde_json::from_value::<Role>(json!("admin")).unwrap(),
+            Role::Admin
+        );
+    }
+
+    #[test]
+    fn keeps_last_active_owner() {
+        let members = members();
+        assert!(matches!(
+            ensure_owner_remains(&members, "o1", Some(Role::Admin)),
+            Err(ApiError::Http { status: 409, .. })
+        ));
+        assert!(ensure_owner_remains(&members, "o1", None).is_err());
+        assert!(ensure_owner_remains(&members, "o1", Some(Role::Owner)).is_ok());
+        assert!(ensure_owner_remains(&members, "a1", None).is_ok());
+        assert!(ensure_owner_remains(&members, "o2", Some(Role::Member)).is_ok());
+
+        let mut two_owners = members;
+        two_owners[1].disabled = false;
+        assert!(ensure_owner_remains(&two_owners, "o1", Some(Role::Member)).is_ok());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{snapshot::Snapshot, MessageDto};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SavedItemDto {
+    pub(crate) message_id: String,
+    pub(crate) channel_id: String,
+    pub(crate) saved_at: i64,
+    /// Last known copy of the message; kept after it is deleted.
+    pub(crate) message: MessageDto,
+    #[serde(default)]
+    pub(crate) deleted: bool,
+}
+
+/// The signed-in user's personal bookmarks, stored locally with a snapshot
+/// of each message so they survive edits and deletion on the server.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SavedItemBook {
+    users: HashMap<String, Vec<SavedItemDto>>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl SavedItemBook {
+    /// Most recently saved first.
+    pub(crate) fn list(&self, user_id: &str) -> Vec<SavedItemDto> {
+        let mut items = self.users.get(user_id).cloned().unwrap_or_default();
+        items.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+        items
+    }
+
+    /// Saving an already saved message refreshes its snapshot.
+    pub(crate) fn save(
+        &mut self,
+        user_id: &str,
+        message: MessageDto,
+        saved_at: i64,
+    ) -> SavedItemDto {
+        let items = self.users.entry(user_id.to_string()).or_default();
+        let item = match items.iter_mut().find(|item| item.message_id == message.id) {
+            Some(existing) => {
+                existing.message = message;
+                existing.deleted = false;
+                existing.clone()
+            }
+            None => {
+                let item = SavedItemDto {
+                    message_id: message.id.clone(),
+                    channel_id: message.channel_id.clone(),
+                    saved_at,
+                    message,
+                    deleted: false,
+                };
+                items.push(item.clone());
+                item
+            }
+        };
+        self.dirty = true;
+        item
+    }
+
+    pub(crate) fn remove(&mut self, user_id: &str, message_id: &str) -> bool {
+        let Some(items) = self.users.get_mut(user_id) else {
+            return false;
+        };
+        let before = items.len();
+        items.retain(|item| item.message_id != message_id);
+        let removed = items.len() != before;
+        if removed {
+            self.users.retain(|_, items| !items.is_empty());
+            self.dirty = true;
+        }
+        removed
+    }
+
+    /// Refreshes the snapshot of every saved copy of `message`, for all users.
+    /// A soft-deleted copy only flags the item so the last body is kept.
+    pub(crate) fn update_snapshot(&mut self, message: &MessageDto) {
+        if message.deleted_at.is_some() {
+            self.mark_deleted(&message.id);
+            return;
+        }
+        for item in self.users.values_mut().flatten() {
+            if item.message_id == message.id {
+                item.message = message.clone();
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Flags saved copies of a deleted message, keeping their snapshot.
+    pub(crate) fn mark_deleted(&mut self, message_id: &str) {
+        for item in self.users.values_mut().flatten() {
+            if item.message_id == message_id && !item.deleted {
+                item.deleted = true;
+                self.dirty = true;
+            }
+        }
+    }
+}
+
+impl Snapshot for SavedItemBook {
+    fn dirty_flag(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+
+    fn message(id: &str, body: &str) -> MessageDto {
+        MessageDto {
+            id: id.to_string(),
+            workspace_id: "ws".to_string(),
+            channel_id: "general".to_string(),
+            sender_id: "u2".to_string(),
+            body_md: body.to_string(),
+            thread_root_id: None,
+            created_at: 1,
+            edited_at: None,
+            deleted_at: None,
+            attachments: Vec::new(),
+            reactions: None,
+            sender: None,
+            body_html: None,
+        }
+    }
+
+    #[test]
+    fn keeps_snapshot_after_edit_and_deletion() {
+        let mut book = SavedItemBook::default();
+        book.save("me", message("m1", "v1"), 10);
+        book.update_snapshot(&message("m1", "v2"));
+        let mut deleted = message("m1", "");
+        deleted.deleted_at = Some(2);
+        book.update_snapshot(&deleted);
+
+        let items = book.list("me");
+        assert_eq!(items.len(), 1);
+        assert!(items[0].deleted);
+        assert_eq!(items[0].message.body_md, "v2");
+        assert!(book.list("other").is_empty());
+    }
+
+    #[test]
+    fn saving_twice_keeps_one_entry_and_original_time() {
+        let mut book = SavedItemBook::default();
+        book.save("me", message("m1", "v1"), 10);
+        book.save("me", message("m2", "hola"), 20);
+        let again = book.save("me", message("m1", "v1"), 30);
+        assert_eq!(again.saved_at, 10);
+
+        let order: Vec<_> = book
+            .list("me")
+            .into_iter()
+            .map(|item| item.message_id)
+            .collect();
+        assert_eq!(order, vec!["m2", "m1"]);
+        assert!(book.remove("me", "m2"));
+        assert!(!book.remove("me", "m2"));
+    }
+
+    #[test]
+    fn snapshot_only_when_dirty_and_round_trips() {
+        let mut book = SavedItemBook::default();
+        let restored = snapshot::assert_round_trips(&mut book, |book| {
+            book.save("me", message("m1", "hola"), 10);
+        });
+        book.mark_deleted("missing");
+        assert!(book.take_snapshot().is_none());
+        assert_eq!(restored.list("me")[0].message.body_md, "hola");
+    }
+}
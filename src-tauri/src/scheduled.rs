@@ -0,0 +1,261 @@
+use crate::snapshot::Snapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Failed sends are retried this many times before the message is left in
+/// the queue as failed for the user to cancel.
+pub(crate) const SCHEDULED_MESSAGE_MAX_ATTEMPTS: u32 = 5;
+/// A message sent this long after `send_at` was due while the app was closed.
+pub(crate) const SCHEDULED_MESSAGE_LATE_AFTER_MS: i64 = 120_000;
+/// The first retry waits this long and every further one twice as long, up
+/// to `SCHEDULED_MESSAGE_RETRY_MAX_MS`.
+pub(crate) const SCHEDULED_MESSAGE_RETRY_BASE_MS: i64 = 60_000;
+pub(crate) const SCHEDULED_MESSAGE_RETRY_MAX_MS: i64 = 30 * 60_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ScheduledMessageDto {
+    pub(crate) id: String,
+    pub(crate) channel_id: String,
+    pub(crate) body_md: String,
+    pub(crate) send_at: i64,
+    pub(crate) created_at: i64,
+    #[serde(default)]
+    pub(crate) attempts: u32,
+    #[serde(default)]
+    pub(crate) last_error: Option<String>,
+    /// Set after a failed send; the message is not due again before it.
+    #[serde(default)]
+    pub(crate) next_attempt_at: Option<i64>,
+}
+
+impl ScheduledMessageDto {
+    pub(crate) fn is_failed(&self) -> bool {
+        self.attempts >= SCHEDULED_MESSAGE_MAX_ATTEMPTS
+    }
+
+    pub(crate) fn is_late(&self, now_millis: i64) -> bool {
+        now_millis - self.send_at > SCHEDULED_MESSAGE_LATE_AFTER_MS
+    }
+
+    fn is_due(&self, now_millis: i64) -> bool {
+        self.send_at <= now_millis
+            && !self.is_failed()
+            && self.next_attempt_at.map_or(true, |at| at <= now_millis)
+    }
+}
+
+/// Wait before the retry that follows failed attempt number `attempts`.
+pub(crate) fn retry_delay_ms(attempts: u32) -> i64 {
+    let doublings = attempts.saturating_sub(1).min(16);
+    (SCHEDULED_MESSAGE_RETRY_BASE_MS << doublings).min(SCHEDULED_MESSAGE_RETRY_MAX_MS)
+}
+
+/// Messages waiting for their `send_at`, kept per signed-in user so they only
+/// go out under the account that wrote them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ScheduledQueue {
+    users: HashMap<String, Vec<ScheduledMessageDto>>,
+    /// Ids claimed by the send loop and not finished yet.
+    #[serde(skip)]
+    sending: HashSet<String>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl ScheduledQueue {
+    pub(crate) fn schedule(&mut self, user_id: &str, message: ScheduledMessageDto) {
+        self.users
+            .entry(user_id.to_string())
+            .or_default()
+            .push(message);
+        self.dirty = true;
+    }
+
+    /// Soonest first.
+    pub(crate) fn list(&self, user_id: &str) -> Vec<ScheduledMessageDto> {
+        let mut messages = self.users.get(user_id).cloned().unwrap_or_default();
+        messages.sort_by_key(|message| (message.send_at, message.created_at));
+        messages
+    }
+
+    /// Messages whose `send_at` has passed, that have not given up yet and
+    /// are not waiting out a retry delay.
+    pub(crate) fn due(&self, user_id: &str, now_millis: i64) -> Vec<ScheduledMessageDto> {
+        self.list(user_id)
+            .into_iter()
+            .filter(|message| message.is_due(now_millis))
+            .collect()
+    }
+
+    /// Marks a due message as being sent. False when it was cancelled after
+    /// `due` was taken or is already being sent, in which case it must not go
+    /// out.
+    pub(crate) fn claim(&mut self, user_id: &str, id: &str) -> bool {
+        let queued = self
+            .users
+            .get(user_id)
+            .is_some_and(|messages| messages.iter().any(|message| message.id == id));
+        queued && self.sending.insert(id.to_string())
+    }
+
+    /// Gives a claimed message back without changing it.
+    pub(crate) fn release(&mut self, id: &str) {
+        self.sending.remove(id);
+    }
+
+    pub(crate) fn is_sending(&self, id: &str) -> bool {
+        self.sending.contains(id)
+    }
+
+    /// Removes the message, either because it was cancelled or sent.
+    pub(crate) fn remove(&mut self, user_id: &str, id: &str) -> Option<ScheduledMessageDto> {
+        self.sending.remove(id);
+        let messages = self.users.get_mut(user_id)?;
+        let index = messages.iter().position(|message| message.id == id)?;
+        let removed = messages.remove(index);
+        self.users.retain(|_, messages| !messages.is_empty());
+        self.dirty = true;
+        Some(removed)
+    }
+
+    /// Counts a failed send, releases the claim and pushes the next attempt
+    /// back exponentially.
+    pub(crate) fn record_failure(
+        &mut self,
+        user_id: &str,
+        id: &str,
+        error: String,
+        now_millis: i64,
+    ) -> Option<ScheduledMessageDto> {
+        self.sending.remove(id);
+        let message = self
+            .users
+            .get_mut(user_id)?
+            .iter_mut()
+            .find(|message| message.id == id)?;
+        message.attempts += 1;
+        message.last_error = Some(error);
+        message.next_attempt_at = Some(now_millis + retry_delay_ms(message.attempts));
+        let updated = message.clone();
+        self.dirty = true;
+        Some(updated)
+    }
+}
+
+impl Snapshot for ScheduledQueue {
+    fn dirty_flag(&mut self) -> &mut bool {
+        &mut self.dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot;
+
+    fn scheduled(id: &str, send_at: i64) -> ScheduledMessageDto {
+        ScheduledMessageDto {
+            id: id.to_string(),
+            channel_id: "general".to_string(),
+            body_md: "buenos días".to_string(),
+            send_at,
+            created_at: 1,
+            attempts: 0,
+            last_error: None,
+            next_attempt_at: None,
+        }
+    }
+
+    #[test]
+    fn returns_due_messages_for_their_author_only() {
+        let mut queue = ScheduledQueue::default();
+        queue.schedule("me", scheduled("s2", 200));
+        queue.schedule("me", scheduled("s1", 100));
+        queue.schedule("other", scheduled("s3", 50));
+
+        let due: Vec<_> = queue.due("me", 150).into_iter().map(|m| m.id).collect();
+        assert_eq!(due, vec!["s1"]);
+        let listed: Vec<_> = queue.list("me").into_iter().map(|m| m.id).collect();
+        assert_eq!(listed, vec!["s1", "s2"]);
+
+        assert!(queue.remove("me", "s3").is_none());
+        assert_eq!(queue.remove("me", "s1").unwrap().id, "s1");
+        assert!(queue.due("me", 150).is_empty());
+    }
+
+    #[test]
+    fn stops_retrying_after_max_attempts() {
+        let mut queue = ScheduledQueue::default();
+        queue.schedule("me", scheduled("s1", 100));
+        let mut now = 100;
+        for _ in 0..SCHEDULED_MESSAGE_MAX_ATTEMPTS {
+            assert_eq!(queue.due("me", now).len(), 1);
+            let failed = queue
+                .record_failure("me", "s1", "channel_not_found".to_string(), now)
+                .unwrap();
+            now = failed.next_attempt_at.unwrap();
+        }
+        assert!(queue.due("me", now).is_empty());
+        let failed = &queue.list("me")[0];
+        assert!(failed.is_failed());
+        assert_eq!(failed.last_error.as_deref(), Some("channel_not_found"));
+    }
+
+    #[test]
+    fn backs_off_exponentially_between_attempts() {
+        assert_eq!(retry_delay_ms(1), SCHEDULED_MESSAGE_RETRY_BASE_MS);
+        assert_eq!(retry_delay_ms(2), 2 * SCHEDULED_MESSAGE_RETRY_BASE_MS);
+        assert_eq!(retry_delay_ms(3), 4 * SCHEDULED_MESSAGE_RETRY_BASE_MS);
+        assert_eq!(retry_delay_ms(40), SCHEDULED_MESSAGE_RETRY_MAX_MS);
+
+        let mut queue = ScheduledQueue::default();
+        queue.schedule("me", scheduled("s1", 100));
+        queue.record_failure("me", "s1", "boom".to_string(), 1_000);
+        assert!(queue.due("me", 1_000).is_empty());
+        assert!(queue
+            .due("me", 1_000 + SCHEDULED_MESSAGE_RETRY_BASE_MS - 1)
+            .is_empty());
+        assert_eq!(
+            queue
+                .due("me", 1_000 + SCHEDULED_MESSAGE_RETRY_BASE_MS)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn claims_only_messages_still_queued() {
+        let mut queue = ScheduledQueue::default();
+        queue.schedule("me", scheduled("s1", 100));
+        queue.schedule("me", scheduled("s2", 100));
+        assert_eq!(queue.due("me", 100).len(), 2);
+
+        // Cancelled between `due` and the send.
+        queue.remove("me", "s2");
+        assert!(!queue.claim("me", "s2"));
+
+        assert!(queue.claim("me", "s1"));
+        assert!(queue.is_sending("s1"));
+        assert!(!queue.claim("me", "s1"));
+        queue.release("s1");
+        assert!(!queue.is_sending("s1"));
+        assert!(queue.claim("me", "s1"));
+        queue.remove("me", "s1");
+        assert!(!queue.is_sending("s1"));
+    }
+
+    #[test]
+    fn flags_messages_sent_well_after_their_time() {
+        let message = scheduled("s1", 1_000);
+        assert!(!message.is_late(1_000 + SCHEDULED_MESSAGE_LATE_AFTER_MS));
+        assert!(message.is_late(1_001 + SCHEDULED_MESSAGE_LATE_AFTER_MS));
+    }
+
+    #[test]
+    fn snapshot_only_when_dirty_and_round_trips() {
+        let restored = snapshot::assert_round_trips(&mut ScheduledQueue::default(), |queue| {
+            queue.schedule("me", scheduled("s1", 100));
+        });
+        assert_eq!(restored.list("me"), vec![scheduled("s1", 100)]);
+    }
+}
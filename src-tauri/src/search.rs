@@ -0,0 +1,348 @@
+use crate::MessageDto;
+use serde::{Deserialize, Serialize};
+
+pub(crate) const DEFAULT_SEARCH_LIMIT: usize = 20;
+pub(crate) const MAX_SEARCH_LIMIT: usize = 100;
+const LOCAL_CURSOR_PREFIX: &str = "local:";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct MessageSearchFilters {
+    #[serde(default)]
+    pub(crate) channel_id: Option<String>,
+    #[serde(default)]
+    pub(crate) sender_id: Option<String>,
+    /// Inclusive lower bound on `created_at`, in epoch milliseconds.
+    #[serde(default)]
+    pub(crate) from: Option<i64>,
+    /// Inclusive upper bound on `created_at`, in epoch milliseconds.
+    #[serde(default)]
+    pub(crate) to: Option<i64>,
+    #[serde(default)]
+    pub(crate) has_attachment: Option<bool>,
+    #[serde(default)]
+    pub(crate) in_thread: Option<bool>,
+}
+
+/// Match position inside `body_md`, in UTF-16 code units so the webview can
+/// pass it straight to `String.prototype.slice`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct HighlightRange {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct MessageSearchHitDto {
+    pub(crate) message: MessageDto,
+    pub(crate) highlights: Vec<HighlightRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SearchSource {
+    Server,
+    Local,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct MessageSearchResultDto {
+    pub(crate) items: Vec<MessageSearchHitDto>,
+    pub(crate) next_cursor: Option<String>,
+    pub(crate) source: SearchSource,
+}
+
+/// Lowercases and strips the diacritics used in Spanish so "canción" and
+/// "CANCION" compare equal. Every input char maps to exactly one output char,
+/// which keeps match offsets valid for the original text.
+pub(crate) fn fold_char(c: char) -> char {
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    match lower {
+        'á' | 'à' | 'ä' | 'â' => 'a',
+        'é' | 'è' | 'ë' | 'ê' => 'e',
+        'í' | 'ì' | 'ï' | 'î' => 'i',
+        'ó' | 'ò' | 'ö' | 'ô' => 'o',
+        'ú' | 'ù' | 'ü' | 'û' => 'u',
+        'ñ' => 'n',
+        'ç' => 'c',
+        other => other,
+    }
+}
+
+pub(crate) fn fold(text: &str) -> String {
+    text.chars().map(fold_char).collect()
+}
+
+pub(crate) fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = query
+        .split_whitespace()
+        .map(fold)
+        .filter(|term| !term.is_empty())
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+pub(crate) fn matches_filters(message: &MessageDto, filters: &MessageSearchFilters) -> bool {
+    if message.deleted_at.is_some() {
+        return false;
+    }
+    if filters
+        .channel_id
+        .as_deref()
+        .is_some_and(|channel_id| message.channel_id != channel_id)
+    {
+        return false;
+    }
+    if filters
+        .sender_id
+        .as_deref()
+        .is_some_and(|sender_id| message.sender_id != sender_id)
+    {
+        return false;
+    }
+    if filters.from.is_some_and(|from| message.created_at < from)
+        || filters.to.is_some_and(|to| message.created_at > to)
+    {
+        return false;
+    }
+    if filters
+        .has_attachment
+        .is_some_and(|wanted| wanted == message.attachments.is_empty())
+    {
+        return false;
+    }
+    if filters
+        .in_thread
+        .is_some_and(|wanted| wanted != message.thread_root_id.is_some())
+    {
+        return false;
+    }
+    true
+}
+
+/// Finds every occurrence of each term in `text`, merged into sorted,
+/// non-overlapping ranges. Returns `None` when some term does not occur.
+pub(crate) fn highlight_ranges(text: &str, terms: &[String]) -> Option<Vec<HighlightRange>> {
+    let folded: Vec<char> = text.chars().map(fold_char).collect();
+    let mut utf16_offsets = Vec::with_capacity(folded.len() + 1);
+    let mut offset = 0;
+    for c in text.chars() {
+        utf16_offsets.push(offset);
+        offset += c.len_utf16();
+    }
+    utf16_offsets.push(offset);
+
+    let mut ranges = Vec::new();
+    for term in terms {
+        let needle: Vec<char> = term.chars().collect();
+        if needle.is_empty() || needle.len() > folded.len() {
+            return None;
+        }
+        let before = ranges.len();
+        for start in 0..=folded.len() - needle.len() {
+            if folded[start..start + needle.len()] == needle[..] {
+                ranges.push(HighlightRange {
+                    start: utf16_offsets[start],
+                    end: utf16_offsets[start + needle.len()],
+                });
+            }
+        }
+        if ranges.len() == before {
+            return None;
+        }
+    }
+
+    ranges.sort_by_key(|range| (range.start, range.end));
+    let mut merged: Vec<HighlightRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    Some(merged)
+}
+
+pub(crate) fn to_hit(message: MessageDto, terms: &[String]) -> MessageSearchHitDto {
+    let highlights = highlight_ranges(&message.body_md, terms).unwrap_or_default();
+    MessageSearchHitDto {
+        message,
+        highlights,
+    }
+}
+
+pub(crate) fn server_search_path(
+    query: &str,
+    filters: &MessageSearchFilters,
+    limit: usize,
+    cursor: Option<&str>,
+) -> String {
+    let mut params = url::form_urlencoded::Serializer::new(String::new());
+    params.append_pair("q", query);
+    params.append_pair("limit", &limit.to_string());
+    if let Some(cursor) = cursor {
+        params.append_pair("cursor", cursor);
+    }
+    if let Some(channel_id) = &filters.channel_id {
+        params.append_pair("channel_id", channel_id);
+    }
+    if let Some(sender_id) = &filters.sender_id {
+        params.append_pair("sender_id", sender_id);
+    }
+    if let Some(from) = filters.from {
+        params.append_pair("from", &from.to_string());
+    }
+    if let Some(to) = filters.to {
+        params.append_pair("to", &to.to_string());
+    }
+    if let Some(has_attachment) = filters.has_attachment {
+        params.append_pair("has_attachment", &has_attachment.to_string());
+    }
+    if let Some(in_thread) = filters.in_thread {
+        params.append_pair("in_thread", &in_thread.to_string());
+    }
+    format!("/messages/search?{}", params.finish())
+}
+
+fn local_offset(cursor: Option<&str>) -> usize {
+    cursor
+        .and_then(|cursor| cursor.strip_prefix(LOCAL_CURSOR_PREFIX))
+        .and_then(|offset| offset.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Searches messages already known to the client, newest first.
+pub(crate) fn search_local<'a>(
+    messages: impl Iterator<Item = &'a MessageDto>,
+    query: &str,
+    filters: &MessageSearchFilters,
+    limit: usize,
+    cursor: Option<&str>,
+) -> MessageSearchResultDto {
+    let terms = query_terms(query);
+    let mut hits: Vec<MessageSearchHitDto> = messages
+        .filter(|message| matches_filters(message, filters))
+        .filter_map(|message| {
+            let highlights = highlight_ranges(&message.body_md, &terms)?;
+            Some(MessageSearchHitDto {
+                message: message.clone(),
+                highlights,
+            })
+        })
+        .collect();
+    hits.sort_by(|a, b| {
+        b.message
+            .created_at
+            .cmp(&a.message.created_at)
+            .then_with(|| b.message.id.cmp(&a.message.id))
+    });
+
+    let offset = local_offset(cursor);
+    let total = hits.len();
+    let items: Vec<MessageSearchHitDto> = hits.into_iter().skip(offset).take(limit).collect();
+    let next = offset + items.len();
+    MessageSearchResultDto {
+        items,
+        next_cursor: (next < total).then(|| format!("{LOCAL_CURSOR_PREFIX}{next}")),
+        source: SearchSource::Local,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, body: &str, created_at: i64) -> MessageDto {
+        MessageDto {
+            id: id.to_string(),
+            workspace_id: "ws".to_string(),
+            channel_id: "general".to_string(),
+            sender_id: "u1".to_string(),
+            body_md: body.to_string(),
+            thread_root_id: None,
+            created_at,
+            edited_at: None,
+            deleted_at: None,
+            attachments: Vec::new(),
+            reactions: None,
+            sender: None,
+            body_html: None,
+        }
+    }
+
+    #[test]
+    fn highlights_ignore_case_and_accents_in_utf16_units() {
+        let terms = query_terms("CANCION año");
+        let ranges = highlight_ranges("🎵 La canción del año", &terms).unwrap();
+        assert_eq!(
+            ranges,
+            vec![
+                HighlightRange { start: 6, end: 13 },
+                HighlightRange { start: 18, end: 21 }
+            ]
+        );
+        assert!(highlight_ranges("otra cosa", &terms).is_none());
+    }
+
+    #[test]
+    fn filters_combine() {
+        let mut threaded = message("m1", "deploy listo", 10);
+        threaded.thread_root_id = Some("root".to_string());
+        let filters = MessageSearchFilters {
+            channel_id: Some("general".to_string()),
+            from: Some(5),
+            in_thread: Some(true),
+            has_attachment: Some(false),
+            ..Default::default()
+        };
+        assert!(matches_filters(&threaded, &filters));
+        assert!(!matches_filters(&message("m2", "deploy", 10), &filters));
+
+        threaded.deleted_at = Some(11);
+        assert!(!matches_filters(&threaded, &filters));
+    }
+
+    #[test]
+    fn server_path_encodes_query_and_filters() {
+        let filters = MessageSearchFilters {
+            channel_id: Some("c1".to_string()),
+            has_attachment: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            server_search_path("año & más", &filters, 20, None),
+            "/messages/search?q=a%C3%B1o+%26+m%C3%A1s&limit=20&channel_id=c1&has_attachment=true"
+        );
+    }
+
+    #[test]
+    fn local_search_pages_newest_first() {
+        let messages = [
+            message("m1", "deploy a staging", 1),
+            message("m2", "nada que ver", 2),
+            message("m3", "Deploy a prod", 3),
+            message("m4", "otro deploy", 4),
+        ];
+        let filters = MessageSearchFilters::default();
+        let first = search_local(messages.iter(), "deploy", &filters, 2, None);
+        let ids: Vec<&str> = first
+            .items
+            .iter()
+            .map(|hit| hit.message.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["m4", "m3"]);
+        assert_eq!(first.next_cursor.as_deref(), Some("local:2"));
+
+        let second = search_local(
+            messages.iter(),
+            "deploy",
+            &filters,
+            2,
+            first.next_cursor.as_deref(),
+        );
+        assert_eq!(second.items.len(), 1);
+        assert_eq!(second.items[0].message.id, "m1");
+        assert!(second.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{search, ApiError, MessageDto};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+};
+use tauri::{AppHandle, Manager};
+
+pub(crate) const SEARCH_INDEX_CAPACITY: usize = 50_000;
+pub(crate) const DEFAULT_LOCAL_SEARCH_LIMIT: usize = 50;
+/// Written through the same cipher as the secure store.
+const INDEX_FILE: &str = "search-index.bin";
+/// Plaintext index written by earlier versions; removed on launch.
+const LEGACY_INDEX_FILE: &str = "search-index.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedMessage {
+    id: String,
+    channel_id: String,
+    sender_id: String,
+    thread_root_id: Option<String>,
+    created_at: i64,
+    body_md: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LocalSearchHitDto {
+    message_id: String,
+    channel_id: String,
+    sender_id: String,
+    thread_root_id: Option<String>,
+    created_at: i64,
+    body_md: String,
+    highlights: Vec<search::HighlightRange>,
+}
+
+/// In-memory inverted index over every message the client has seen. Tokens
+/// are accent-folded and kept sorted so a query term matches any token it is
+/// a prefix of.
+#[derive(Debug)]
+pub(crate) struct SearchIndex {
+    capacity: usize,
+    docs: HashMap<String, IndexedMessage>,
+    postings: BTreeMap<String, HashSet<String>>,
+    /// Documents by `(created_at, id)`, so the oldest is evicted first.
+    by_age: BTreeSet<(i64, String)>,
+    dirty: bool,
+}
+
+fn cache_path(app: &AppHandle, file: &str) -> Result<PathBuf, ApiError> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join(file))
+        .map_err(|err| ApiError::Storage(format!("could not resolve cache dir: {err}")))
+}
+
+pub(crate) fn index_path(app: &AppHandle) -> Result<PathBuf, ApiError> {
+    cache_path(app, INDEX_FILE)
+}
+
+pub(crate) fn legacy_index_path(app: &AppHandle) -> Result<PathBuf, ApiError> {
+    cache_path(app, LEGACY_INDEX_FILE)
+}
+
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    let folded = search::fold(text);
+    let mut tokens: Vec<String> = folded
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(ToString::to_string)
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
+
+impl SearchIndex {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            docs: HashMap::new(),
+            postings: BTreeMap::new(),
+            by_age: BTreeSet::new(),
+            dirty: false,
+        }
+    }
+
+    /// Rebuilds the index from a snapshot from `take_snapshot`; a missing or
+    /// unreadable snapshot yields an empty index.
+    pub(crate) fn from_snapshot(raw: Option<&[u8]>, capacity: usize) -> Self {
+        let mut index = Self::new(capacity);
+        let docs: Vec<IndexedMessage> = raw
+            .and_then(|raw| serde_json::from_slice(raw).ok())
+            .unwrap_or_default();
+        for doc in docs {
+            index.insert(doc);
+        }
+        index.evict();
+        index.dirty = false;
+        index
+    }
+
+    /// Serialized documents when something changed since the last call.
+    pub(crate) fn take_snapshot(&mut self) -> Option<Vec<u8>> {
+        if !self.dirty {
+            return None;
+        }
+        let docs: Vec<&IndexedMessage> = self.docs.values().collect();
+        let raw = serde_json::to_vec(&docs).ok()?;
+        self.dirty = false;
+        Some(raw)
+    }
+
+    /// Indexes `message`, replacing the tokens of a previous version. Deleted
+    /// messages are dropped from the index.
+    pub(crate) fn upsert(&mut self, message: &MessageDto) {
+        if message.deleted_at.is_some() {
+            self.remove(&message.id);
+            return;
+        }
+        if self
+            .docs
+            .get(&message.id)
+            .is_some_and(|doc| doc.body_md == message.body_md)
+        {
+            return;
+        }
+
+        self.remove(&message.id);
+        self.insert(IndexedMessage {
+            id: message.id.clone(),
+            channel_id: message.channel_id.clone(),
+            sender_id: message.sender_id.clone(),
+            thread_root_id: message.thread_root_id.clone(),
+            created_at: message.created_at,
+            body_md: message.body_md.clone(),
+        });
+        self.evict();
+    }
+
+    pub(crate) fn remove(&mut self, message_id: &str) {
+        let Some(doc) = self.docs.remove(message_id) else {
+            return;
+        };
+        self.by_age.remove(&(doc.created_at, doc.id.clone()));
+        for token in tokenize(&doc.body_md) {
+            if let Some(ids) = self.postings.get_mut(&token) {
+                ids.remove(message_id);
+                if ids.is_empty() {
+                    self.postings.remove(&token);
+                }
+            }
+        }
+        self.dirty = true;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.dirty = !self.docs.is_empty();
+        self.docs.clear();
+        self.postings.clear();
+        self.by_age.clear();
+    }
+
+    /// Messages containing a token starting with every query term, newest
+    /// first.
+    pub(crate) fn search(
+        &self,
+        query: &str,
+        channel_id: Option<&str>,
+        limit: usize,
+    ) -> Vec<LocalSearchHitDto> {
+        let terms = tokenize(query);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matched: Option<HashSet<&str>> = None;
+        for term in &terms {
+            let ids: HashSet<&str> = self
+                .postings
+                .range::<str, _>((
+                    std::ops::Bound::Included(term.as_str()),
+                    std::ops::Bound::Unbounded,
+                ))
+                .take_while(|(token, _)| token.starts_with(term.as_str()))
+                .flat_map(|(_, ids)| ids.iter().map(String::as_str))
+                .collect();
+            matched = Some(match matched {
+                Some(previous) => previous.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+
+        let mut docs: Vec<&IndexedMessage> = matched
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|id| self.docs.get(id))
+            .filter(|doc| channel_id.map_or(true, |channel_id| doc.channel_id == channel_id))
+            .collect();
+        docs.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| b.id.cmp(&a.id))
+        });
+
+        docs.into_iter()
+            .take(limit)
+            .map(|doc| LocalSearchHitDto {
+                message_id: doc.id.clone(),
+                channel_id: doc.channel_id.clone(),
+                sender_id: doc.sender_id.clone(),
+                thread_root_id: doc.thread_root_id.clone(),
+                created_at: doc.created_at,
+                body_md: doc.body_md.clone(),
+                highlights: search::highlight_ranges(&doc.body_md, &terms).unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    fn insert(&mut self, doc: IndexedMessage) {
+        for token in tokenize(&doc.body_md) {
+            self.postings
+                .entry(token)
+                .or_default()
+                .insert(doc.id.clone());
+        }
+        self.by_age.insert((doc.created_at, doc.id.clone()));
+        self.docs.insert(doc.id.clone(), doc);
+        self.dirty = true;
+    }
+
+    fn evict(&mut self) {
+        while self.docs.len() > self.capacity {
+            let Some((_, oldest)) = self.by_age.first().cloned() else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, body: &str, created_at: i64) -> MessageDto {
+        MessageDto {
+            id: id.to_string(),
+            workspace_id: "ws".to_string(),
+            channel_id: "general".to_string(),
+            sender_id: "u1".to_string(),
+            body_md: body.to_string(),
+            thread_root_id: None,
+            created_at,
+            edited_at: None,
+            deleted_at: None,
+            attachments: Vec::new(),
+            reactions: None,
+            sender: None,
+            body_html: None,
+        }
+    }
+
+    fn ids(hits: &[LocalSearchHitDto]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.message_id.as_str()).collect()
+    }
+
+    #[test]
+    fn tokenizer_folds_spanish_accents() {
+        assert_eq!(
+            tokenize("¿Reunión MAÑANA? ¡Sí, reunión!"),
+            vec!["manana", "reunion", "si"]
+        );
+    }
+
+    #[test]
+    fn prefix_terms_rank_by_recency() {
+        let mut index = SearchIndex::new(10);
+        index.upsert(&message("m1", "Publicación del informe", 1));
+        index.upsert(&message("m2", "informes pendientes", 3));
+        index.upsert(&message("m3", "publicar mañana", 2));
+
+        assert_eq!(ids(&index.search("info", None, 10)), vec!["m2", "m1"]);
+        assert_eq!(ids(&index.search("publ", None, 10)), vec!["m3", "m1"]);
+        assert_eq!(ids(&index.search("PUBLICACION inf", None, 10)), vec!["m1"]);
+        assert!(index.search("info", Some("random"), 10).is_empty());
+    }
+
+    #[test]
+    fn edits_and_deletes_update_postings() {
+        let mut index = SearchIndex::new(10);
+        let mut edited = message("m1", "borrador viejo", 1);
+        index.upsert(&edited);
+        edited.body_md = "versión nueva".to_string();
+        index.upsert(&edited);
+        assert!(index.search("borrador", None, 10).is_empty());
+        assert_eq!(ids(&index.search("version", None, 10)), vec!["m1"]);
+
+        edited.deleted_at = Some(2);
+        index.upsert(&edited);
+        assert!(index.search("nueva", None, 10).is_empty());
+        assert!(index.postings.is_empty());
+    }
+
+    #[test]
+    fn snapshot_round_trips_and_evicts_oldest() {
+        let mut index = SearchIndex::new(2);
+        index.upsert(&message("m1", "uno", 1));
+        index.upsert(&message("m2", "dos", 2));
+        index.upsert(&message("m3", "tres", 3));
+        assert_eq!(index.docs.len(), 2);
+
+        let snapshot = index.take_snapshot().unwrap();
+        assert!(index.take_snapshot().is_none());
+
+        let reloaded = SearchIndex::from_snapshot(Some(&snapshot), 10);
+        assert_eq!(ids(&reloaded.search("tres", None, 10)), vec!["m3"]);
+        assert!(reloaded.search("uno", None, 10).is_empty());
+        assert!(SearchIndex::from_snapshot(Some(b"not json"), 10)
+            .docs
+            .is_empty());
+    }
+
+    #[test]
+    fn eviction_follows_creation_time_not_insertion_order() {
+        let mut index = SearchIndex::new(3);
+        index.upsert(&message("m3", "tres", 30));
+        index.upsert(&message("m1", "uno", 10));
+        index.upsert(&message("m2", "dos", 20));
+        index.upsert(&message("m1", "uno editado", 10));
+        index.upsert(&message("m4", "cuatro", 40));
+
+        assert!(index.search("uno", None, 10).is_empty());
+        assert_eq!(index.docs.len(), 3);
+        assert_eq!(index.by_age.len(), 3);
+        assert_eq!(index.by_age.first(), Some(&(20, "m2".to_string())));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::ApiError;
+use serde::{Deserialize, Serialize};
+
+pub(crate) const DEVICE_NAME_MAX_CHARS: usize = 64;
+
+/// How this install identifies itself at login. The id is generated once and
+/// kept in the store so the server can tell its sessions apart from those of
+/// other devices of the same user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct DeviceDto {
+    pub(crate) device_id: String,
+    pub(crate) device_name: String,
+}
+
+impl DeviceDto {
+    pub(crate) fn generate() -> Result<Self, ApiError> {
+        let mut bytes = [0_u8; 16];
+        getrandom::fill(&mut bytes)
+            .map_err(|err| ApiError::Storage(format!("could not generate a device id: {err}")))?;
+        let device_id = format!("{:032x}", u128::from_be_bytes(bytes));
+        // Asked from the OS: GUI launches rarely inherit `HOSTNAME`.
+        let hostname = gethostname::gethostname().into_string().ok();
+        Ok(Self {
+            device_id,
+            device_name: default_device_name(hostname.as_deref(), std::env::consts::OS),
+        })
+    }
+}
+
+fn os_label(os: &str) -> &str {
+    match os {
+        "macos" => "macOS",
+        "windows" => "Windows",
+        "linux" => "Linux",
+        "ios" => "iOS",
+        "android" => "Android",
+        other => other,
+    }
+}
+
+pub(crate) fn default_device_name(hostname: Option<&str>, os: &str) -> String {
+    match hostname.map(str::trim).filter(|host| !host.is_empty()) {
+        Some(host) => format!("{host} ({})", os_label(os)),
+        None => format!("Galynx on {}", os_label(os)),
+    }
+}
+
+/// A name picked by the user at login; blank keeps the current one.
+pub(crate) fn normalize_device_name(name: Option<&str>) -> Option<String> {
+    let name = name.map(str::trim).filter(|name| !name.is_empty())?;
+    Some(name.chars().take(DEVICE_NAME_MAX_CHARS).collect())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SessionDto {
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) device_id: Option<String>,
+    #[serde(default)]
+    pub(crate) device_name: Option<String>,
+    #[serde(default)]
+    pub(crate) ip: Option<String>,
+    #[serde(default)]
+    pub(crate) user_agent: Option<String>,
+    pub(crate) created_at: i64,
+    #[serde(default)]
+    pub(crate) last_used_at: Option<i64>,
+    #[serde(default)]
+    pub(crate) current: bool,
+}
+
+/// Marks the session of this device when the server did not, then puts it
+/// first and the rest by most recent use.
+pub(crate) fn arrange(mut sessions: Vec<SessionDto>, device_id: &str) -> Vec<SessionDto> {
+    if !sessions.iter().any(|session| session.current) {
+        if let Some(session) = sessions
+            .iter_mut()
+            .filter(|session| session.device_id.as_deref() == Some(device_id))
+            .max_by_key(|session| session.last_used_at.unwrap_or(session.created_at))
+        {
+            session.current = true;
+        }
+    }
+    sessions.sort_by(|a, b| {
+        b.current.cmp(&a.current).then_with(|| {
+            let used = |session: &SessionDto| session.last_used_at.unwrap_or(session.created_at);
+            used(b).cmp(&used(a))
+        })
+    });
+    sessions
+}
+
+/// The current session is ended with `auth_logout`, which also clears the
+/// local tokens.
+pub(crate) fn ensure_not_current(
+    sessions: &[SessionDto],
+    session_id: &str,
+) -> Result<(), ApiError> {
+    if sessions
+        .iter()
+        .any(|session| session.id == session_id && session.current)
+    {
+        return Err(ApiError::validation(
+            "current_session",
+            "sign out to end the session of this device",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, device_id: &str, last_used_at: Option<i64>) -> SessionDto {
+        SessionDto {
+            id: id.to_string(),
+            device_id: Some(device_id.to_string()),
+            device_name: None,
+            ip: None,
+            user_agent: None,
+            created_at: 1,
+            last_used_at,
+            current: false,
+        }
+    }
+
+    #[test]
+    fn marks_current_device_and_orders_by_use() {
+        let sessions = arrange(
+            vec![
+                session("s1", "laptop", Some(10)),
+                session("s2", "me", Some(5)),
+                session("s3", "phone", Some(30)),
+                session("s4", "me", Some(2)),
+            ],
+            "me",
+        );
+        let ids: Vec<&str> = sessions.iter().map(|session| session.id.as_str()).collect();
+        assert_eq!(ids, vec!["s2", "s3", "s1", "s4"]);
+        assert!(sessions[0].current);
+        assert_eq!(sessions.iter().filter(|session| session.current).count(), 1);
+
+        let mut from_server = session("s1", "other", None);
+        from_server.current = true;
+        let sessions = arrange(vec![session("s2", "me", Some(5)), from_server], "me");
+        assert_eq!(sessions[0].id, "s1");
+        assert!(!sessions[1].current);
+    }
+
+    #[test]
+    fn refuses_to_revoke_current_session() {
+        let sessions = arrange(
+            vec![session("s1", "me", None), session("s2", "x", None)],
+            "me",
+        );
+        assert!(matches!(
+            ensure_not_current(&sessions, "s1"),
+            Err(ApiError::Validation {
+                code: "current_session",
+                ..
+            })
+        ));
+        assert!(ensure_not_current(&sessions, "s2").is_ok());
+        assert!(ensure_not_current(&sessions, "missing").is_ok());
+    }
+
+    #[test]
+    fn names_devices() {
+        assert_eq!(
+            default_device_name(Some(" oficina "), "macos"),
+            "oficina (macOS)"
+        );
+        assert_eq!(default_device_name(Some(""), "linux"), "Galynx on Linux");
+        assert_eq!(normalize_device_name(Some("  ")), None);
+        assert_eq!(
+            normalize_device_name(Some(&"x".repeat(100))).map(|name| name.len()),
+            Some(DEVICE_NAME_MAX_CHARS)
+        );
+        let device = DeviceDto::generate().unwrap();
+        assert_eq!(device.device_id.len(), 32);
+        assert_ne!(device.device_id, DeviceDto::generate().unwrap().device_id);
+    }
+}
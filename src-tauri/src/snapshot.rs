@@ -0,0 +1,33 @@
+use serde::Serialize;
+
+/// Local state written whole to the secure store, and only after it changed.
+/// Implementors keep a `#[serde(skip)]` dirty flag that their mutations set.
+pub(crate) trait Snapshot: Serialize {
+    fn dirty_flag(&mut self) -> &mut bool;
+
+    /// The whole value as JSON when something changed since the last call.
+    fn take_snapshot(&mut self) -> Option<serde_json::Value> {
+        if !*self.dirty_flag() {
+            return None;
+        }
+        let value = serde_json::to_value(&*self).ok()?;
+        *self.dirty_flag() = false;
+        Some(value)
+    }
+}
+
+/// Checks that `value` only produces a snapshot after `change`, exactly once,
+/// and returns what that snapshot restores to.
+#[cfg(test)]
+pub(crate) fn assert_round_trips<T>(value: &mut T, change: impl FnOnce(&mut T)) -> T
+where
+    T: Snapshot + serde::de::DeserializeOwned,
+{
+    assert!(value.take_snapshot().is_none());
+    change(value);
+    let snapshot = value
+        .take_snapshot()
+        .expect("a change must mark the value dirty");
+    assert!(value.take_snapshot().is_none());
+    serde_json::from_value(snapshot).expect("snapshot must deserialize")
+}
@@ -0,0 +1,234 @@
+use crate::ApiError;
+
+/// Server-side attachment limit documented in `api_frontend.md`.
+pub(crate) const MAX_ATTACHMENT_BYTES: i64 = 100 * 1024 * 1024;
+const GENERIC_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Entries containing `/` match MIME types, everything else matches file extensions.
+pub(crate) fn default_attachment_denylist() -> Vec<String> {
+    [
+        "application/vnd.microsoft.portable-executable",
+        "application/x-msdownload",
+        "application/x-executable",
+        "application/x-mach-binary",
+        "application/x-sharedlib",
+        "application/x-msi",
+        "text/x-shellscript",
+        "exe",
+        "dll",
+        "msi",
+        "bat",
+        "cmd",
+        "com",
+        "scr",
+        "ps1",
+        "vbs",
+        "sh",
+        "jar",
+        "apk",
+    ]
+    .iter()
+    .map(ToString::to_string)
+    .collect()
+}
+
+pub(crate) fn normalize_denylist(entries: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = entries
+        .into_iter()
+        .map(|entry| entry.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ValidatedUpload {
+    pub(crate) content_type: String,
+}
+
+pub(crate) fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    infer::get(bytes).map(|kind| kind.mime_type())
+}
+
+fn essence(content_type: &str) -> String {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match essence.as_str() {
+        "image/jpg" | "image/pjpeg" => "image/jpeg".to_string(),
+        _ => essence,
+    }
+}
+
+fn top_level_type(content_type: &str) -> &str {
+    content_type.split('/').next().unwrap_or_default()
+}
+
+fn file_extension(filename: &str) -> Option<String> {
+    let (_, extension) = filename.rsplit_once('.')?;
+    if extension.is_empty() {
+        return None;
+    }
+    Some(extension.to_ascii_lowercase())
+}
+
+/// Runs every client-side check before an upload is presigned and returns the
+/// content type that should be sent to the API.
+pub(crate) fn validate_upload(
+    filename: &str,
+    declared_type: &str,
+    declared_size: i64,
+    bytes: &[u8],
+    denylist: &[String],
+) -> Result<ValidatedUpload, ApiError> {
+    if declared_size > MAX_ATTACHMENT_BYTES {
+        return Err(ApiError::AttachmentTooLarge {
+            size_bytes: declared_size,
+            max_bytes: MAX_ATTACHMENT_BYTES,
+        });
+    }
+
+    let actual_size = bytes.len() as i64;
+    if actual_size != declared_size {
+        return Err(ApiError::AttachmentSizeMismatch {
+            declared: declared_size,
+            actual: actual_size,
+        });
+    }
+
+    let declared = essence(declared_type);
+    let sniffed = sniff_content_type(bytes).map(essence);
+    let extension = file_extension(filename);
+
+    let blocked = [Some(&declared), sniffed.as_ref(), extension.as_ref()]
+        .into_iter()
+        .flatten()
+        .find(|candidate| denylist.iter().any(|entry| entry == *candidate));
+    if let Some(blocked) = blocked {
+        return Err(ApiError::AttachmentBlocked(blocked.clone()));
+    }
+
+    let content_type = match sniffed {
+        Some(sniffed) => {
+            let declared_is_generic = declared.is_empty() || declared == GENERIC_CONTENT_TYPE;
+            if !declared_is_generic && top_level_type(&declared) != top_level_type(&sniffed) {
+                return Err(ApiError::AttachmentTypeMismatch {
+                    declared,
+                    detected: sniffed,
+                });
+            }
+            sniffed
+        }
+        None if declared.is_empty() => GENERIC_CONTENT_TYPE.to_string(),
+        None => declared,
+    };
+
+    Ok(ValidatedUpload { content_type })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_HEADER: &[u8] = &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0];
+
+    #[test]
+    fn rejects_declared_size_over_limit() {
+        let err = validate_upload(
+            "big.bin",
+            "application/octet-stream",
+            MAX_ATTACHMENT_BYTES + 1,
+            b"tiny",
+            &default_attachment_denylist(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ApiError::AttachmentTooLarge { .. }));
+    }
+
+    #[test]
+    fn rejects_size_mismatch() {
+        let err = validate_upload("a.txt", "text/plain", 10, b"hello", &[]).unwrap_err();
+        assert!(matches!(
+            err,
+            ApiError::AttachmentSizeMismatch {
+                declared: 10,
+                actual: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn sniffed_type_wins_over_generic_or_sibling_declaration() {
+        let generic = validate_upload(
+            "shot",
+            "application/octet-stream",
+            PNG_HEADER.len() as i64,
+            PNG_HEADER,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(generic.content_type, "image/png");
+
+        let sibling = validate_upload(
+            "shot.jpg",
+            "image/jpg",
+            PNG_HEADER.len() as i64,
+            PNG_HEADER,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(sibling.content_type, "image/png");
+
+        let text = validate_upload("notes.md", "text/markdown", 5, b"# hey", &[]).unwrap();
+        assert_eq!(text.content_type, "text/markdown");
+    }
+
+    #[test]
+    fn rejects_type_family_mismatch() {
+        let err = validate_upload(
+            "notes.txt",
+            "text/plain",
+            PNG_HEADER.len() as i64,
+            PNG_HEADER,
+            &[],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ApiError::AttachmentTypeMismatch { .. }));
+    }
+
+    #[test]
+    fn denylist_matches_sniffed_type_and_extension() {
+        let denylist = default_attachment_denylist();
+        let exe = b"MZ\x90\x00\x03\x00";
+        let err = validate_upload(
+            "invoice.pdf",
+            "application/pdf",
+            exe.len() as i64,
+            exe,
+            &denylist,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, ApiError::AttachmentBlocked(ref kind) if kind.contains("executable"))
+        );
+
+        let err = validate_upload("setup.BAT", "text/plain", 4, b"echo", &denylist).unwrap_err();
+        assert!(matches!(err, ApiError::AttachmentBlocked(ref kind) if kind == "bat"));
+    }
+
+    #[test]
+    fn denylist_normalization_trims_dots_and_dedupes() {
+        let normalized = normalize_denylist(vec![
+            " .EXE ".to_string(),
+            "exe".to_string(),
+            String::new(),
+            "Application/X-Msi".to_string(),
+        ]);
+        assert_eq!(normalized, vec!["application/x-msi", "exe"]);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::UserDto;
+use serde_json::Value as JsonValue;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+pub(crate) const USER_DIRECTORY_TTL: Duration = Duration::from_secs(300);
+/// Unknown ids trigger an early refresh at most this often.
+pub(crate) const USER_DIRECTORY_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+struct WorkspaceDirectory {
+    users: HashMap<String, UserDto>,
+    refreshed_at: Option<Instant>,
+}
+
+/// Known users per workspace, used to resolve `sender_id`s without a request
+/// per message.
+#[derive(Debug, Default)]
+pub(crate) struct UserDirectory {
+    workspaces: HashMap<String, WorkspaceDirectory>,
+}
+
+/// Reads a `/workspaces/:id/members` entry, which may use either `user_id` or
+/// `id` and omit fields the caller already knows.
+pub(crate) fn user_from_member(workspace_id: &str, member: &JsonValue) -> Option<UserDto> {
+    let field = |key: &str| member.get(key).and_then(JsonValue::as_str);
+    let id = field("user_id").or_else(|| field("id"))?;
+    Some(UserDto {
+        id: id.to_string(),
+        email: field("email").unwrap_or_default().to_string(),
+        name: field("name").unwrap_or_default().to_string(),
+        workspace_id: field("workspace_id").unwrap_or(workspace_id).to_string(),
+        role: field("role").unwrap_or("member").to_string(),
+        avatar_url: field("avatar_url").map(ToString::to_string),
+        status: member
+            .get("status")
+            .and_then(|status| serde_json::from_value(status.clone()).ok()),
+    })
+}
+
+impl UserDirectory {
+    /// Whether `ids` can be resolved from what is cached, or a refresh is due.
+    pub(crate) fn needs_refresh(&self, workspace_id: &str, ids: &[String], now: Instant) -> bool {
+        let Some(directory) = self.workspaces.get(workspace_id) else {
+            return true;
+        };
+        let Some(refreshed_at) = directory.refreshed_at else {
+            return true;
+        };
+        let age = now.saturating_duration_since(refreshed_at);
+        if age >= USER_DIRECTORY_TTL {
+            return true;
+        }
+        age >= USER_DIRECTORY_MIN_REFRESH && ids.iter().any(|id| !directory.users.contains_key(id))
+    }
+
+    /// Records a refresh attempt, successful or not, so a failing endpoint is
+    /// not retried on every lookup.
+    pub(crate) fn mark_refreshed(&mut self, workspace_id: &str, now: Instant) {
+        self.workspaces
+            .entry(workspace_id.to_string())
+            .or_default()
+            .refreshed_at = Some(now);
+    }
+
+    /// Merges `users` into the directory. Empty fields never overwrite values
+    /// learned from a richer source.
+    pub(crate) fn merge(&mut self, workspace_id: &str, users: impl IntoIterator<Item = UserDto>) {
+        let directory = self.workspaces.entry(workspace_id.to_string()).or_default();
+        for user in users {
+            match directory.users.get_mut(&user.id) {
+                Some(existing) => {
+                    if !user.name.is_empty() {
+                        existing.name = user.name;
+                    }
+                    if !user.email.is_empty() {
+                        existing.email = user.email;
+                    }
+                    if !user.role.is_empty() {
+                        existing.role = user.role;
+                    }
+                    if user.avatar_url.is_some() {
+                        existing.avatar_url = user.avatar_url;
+                    }
+                    if user.status.is_some() {
+                        existing.status = user.status;
+                    }
+                }
+                None => {
+                    directory.users.insert(user.id.clone(), user);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, workspace_id: &str, user_id: &str) -> Option<&UserDto> {
+        self.workspaces.get(workspace_id)?.users.get(user_id)
+    }
+
+    pub(crate) fn get_mut(&mut self, workspace_id: &str, user_id: &str) -> Option<&mut UserDto> {
+        self.workspaces
+            .get_mut(workspace_id)?
+            .users
+            .get_mut(user_id)
+    }
+
+    pub(crate) fn remove(&mut self, workspace_id: &str, user_id: &str) {
+        if let Some(directory) = self.workspaces.get_mut(workspace_id) {
+            directory.users.remove(user_id);
+        }
+    }
+
+    pub(crate) fn resolve(&self, workspace_id: &str, ids: &[String]) -> Vec<UserDto> {
+        ids.iter()
+            .filter_map(|id| self.get(workspace_id, id).cloned())
+            .collect()
+    }
+
+    pub(crate) fn users(&self, workspace_id: &str) -> Vec<UserDto> {
+        self.workspaces
+            .get(workspace_id)
+            .map(|directory| directory.users.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.workspaces.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user(id: &str, name: &str) -> UserDto {
+        UserDto {
+            id: id.to_string(),
+            email: format!("{id}@galynx.local"),
+            name: name.to_string(),
+            workspace_id: "ws".to_string(),
+            role: "member".to_string(),
+            avatar_url: None,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn refresh_follows_ttl_and_unknown_ids() {
+        let start = Instant::now();
+        let mut directory = UserDirectory::default();
+        let ids = vec!["u1".to_string()];
+        assert!(directory.needs_refresh("ws", &ids, start));
+
+        directory.merge("ws", [user("u1", "Ana")]);
+        directory.mark_refreshed("ws", start);
+        assert!(!directory.needs_refresh("ws", &ids, start));
+
+        let unknown = vec!["u2".to_string()];
+        assert!(!directory.needs_refresh("ws", &unknown, start + Duration::from_secs(5)));
+        assert!(directory.needs_refresh("ws", &unknown, start + USER_DIRECTORY_MIN_REFRESH));
+        assert!(directory.needs_refresh("ws", &ids, start + USER_DIRECTORY_TTL));
+    }
+
+    #[test]
+    fn member_entries_merge_without_erasing_known_fields() {
+        let mut directory = UserDirectory::default();
+        directory.merge("ws", [user("u1", "Ana")]);
+        let member = user_from_member("ws", &json!({ "user_id": "u1", "role": "admin" })).unwrap();
+        directory.merge("ws", [member]);
+
+        let resolved = directory.resolve("ws", &["u1".to_string(), "u9".to_string()]);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].name, "Ana");
+        assert_eq!(resolved[0].role, "admin");
+        assert!(directory.get("other", "u1").is_none());
+    }
+}
@@ -0,0 +1,382 @@
+use crate::{passwords, roles::Role, ApiError, ApiErrorDto, UserDto};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub(crate) const USER_IMPORT_MAX_ROWS: usize = 1000;
+pub(crate) const USER_IMPORT_DEFAULT_CONCURRENCY: usize = 3;
+pub(crate) const USER_IMPORT_MAX_CONCURRENCY: usize = 6;
+pub(crate) const TEMPORARY_PASSWORD_CHARS: usize = 16;
+
+/// No `0/O/o`, `1/l/I` so passwords can be read out or copied by hand.
+const PASSWORD_ALPHABET: &[u8] =
+    b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnpqrstuvwxyz23456789!#%+-=?@";
+
+const COLUMNS: [&str; 4] = ["email", "name", "role", "password"];
+
+/// A row that passed validation. `line` is where the record starts in the
+/// file, counting the header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ImportRow {
+    pub(crate) line: usize,
+    pub(crate) email: String,
+    pub(crate) name: String,
+    pub(crate) role: Role,
+    pub(crate) password: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InvalidRow {
+    pub(crate) line: usize,
+    pub(crate) email: Option<String>,
+    pub(crate) reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ImportAction {
+    Create,
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ImportRowStatus {
+    Invalid,
+    /// Valid but not written: a dry run, or a file with invalid rows.
+    Ready,
+    Imported,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ImportRowResultDto {
+    pub(crate) line: usize,
+    pub(crate) email: Option<String>,
+    pub(crate) status: ImportRowStatus,
+    pub(crate) action: Option<ImportAction>,
+    pub(crate) user: Option<UserDto>,
+    /// Only set for accounts created with a generated password.
+    pub(crate) temporary_password: Option<String>,
+    pub(crate) error: Option<ApiErrorDto>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ImportReportDto {
+    pub(crate) dry_run: bool,
+    /// Whether anything was sent to the server. Files with invalid rows are
+    /// never applied, so a fixed file can be imported again from scratch.
+    pub(crate) applied: bool,
+    pub(crate) total: usize,
+    pub(crate) invalid: usize,
+    pub(crate) created: usize,
+    pub(crate) updated: usize,
+    pub(crate) failed: usize,
+    pub(crate) rows: Vec<ImportRowResultDto>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ImportProgressDto {
+    pub(crate) total: usize,
+    pub(crate) completed: usize,
+    pub(crate) failed: usize,
+}
+
+impl ImportReportDto {
+    pub(crate) fn new(dry_run: bool, applied: bool, mut rows: Vec<ImportRowResultDto>) -> Self {
+        rows.sort_by_key(|row| row.line);
+        let count = |status: ImportRowStatus, action: Option<ImportAction>| {
+            rows.iter()
+                .filter(|row| row.status == status && (action.is_none() || row.action == action))
+                .count()
+        };
+        Self {
+            dry_run,
+            applied,
+            total: rows.len(),
+            invalid: count(ImportRowStatus::Invalid, None),
+            created: count(ImportRowStatus::Imported, Some(ImportAction::Create)),
+            updated: count(ImportRowStatus::Imported, Some(ImportAction::Update)),
+            failed: count(ImportRowStatus::Failed, None),
+            rows,
+        }
+    }
+}
+
+impl ImportRowResultDto {
+    pub(crate) fn invalid(row: InvalidRow) -> Self {
+        Self {
+            line: row.line,
+            email: row.email,
+            status: ImportRowStatus::Invalid,
+            action: None,
+            user: None,
+            temporary_password: None,
+            error: Some(ApiErrorDto::from(ApiError::validation(
+                "invalid_row",
+                row.reason,
+            ))),
+        }
+    }
+
+    pub(crate) fn ready(row: &ImportRow, action: ImportAction) -> Self {
+        Self {
+            line: row.line,
+            email: Some(row.email.clone()),
+            status: ImportRowStatus::Ready,
+            action: Some(action),
+            user: None,
+            temporary_password: None,
+            error: None,
+        }
+    }
+}
+
+/// Splits CSV text into records with the line each one starts on. Handles
+/// quoted fields with `""` escapes and line breaks, CRLF and a UTF-8 BOM.
+fn records(text: &str) -> Result<Vec<(usize, Vec<String>)>, ApiError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            '\n' if quoted => {
+                line += 1;
+                field.push('\n');
+            }
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(ch),
+        }
+    }
+    if quoted {
+        return Err(ApiError::validation(
+            "invalid_csv",
+            format!("unterminated quoted field starting on line {record_line}"),
+        ));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+    records.retain(|(_, record)| record.iter().any(|field| !field.trim().is_empty()));
+    Ok(records)
+}
+
+pub(crate) fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain.contains('.')
+        && !email.chars().any(char::is_whitespace)
+}
+
+fn validate(
+    line: usize,
+    field: impl Fn(&str) -> Option<String>,
+    seen: &mut HashSet<String>,
+) -> Result<ImportRow, InvalidRow> {
+    let email = field("email").map(|email| email.to_lowercase());
+    let invalid = |reason: &str| InvalidRow {
+        line,
+        email: email.clone(),
+        reason: reason.to_string(),
+    };
+
+    let Some(address) = email.clone() else {
+        return Err(invalid("email is required"));
+    };
+    if !is_valid_email(&address) {
+        return Err(invalid("email is not a valid address"));
+    }
+    let Some(name) = field("name") else {
+        return Err(invalid("name is required"));
+    };
+    let role = match field("role") {
+        None => Role::Member,
+        Some(role) => match Role::parse(&role) {
+            Some(Role::Owner) => return Err(invalid("owners cannot be imported")),
+            Some(role) => role,
+            None => return Err(invalid("role must be admin or member")),
+        },
+    };
+    let password = field("password");
+    if let Some(password) = &password {
+        if let Err(ApiError::Validation { message, .. }) =
+            passwords::check_strength(password, &[&address, &name])
+        {
+            return Err(invalid(&message));
+        }
+    }
+    if !seen.insert(address.clone()) {
+        return Err(invalid("email appears more than once in the file"));
+    }
+    Ok(ImportRow {
+        line,
+        email: address,
+        name,
+        role,
+        password,
+    })
+}
+
+/// Parses an `email,name,role[,password]` CSV. A first record that names the
+/// columns is read as a header and may list them in any order; otherwise
+/// columns are positional. Every row is validated; errors in the file as a
+/// whole (bad quoting, no rows, too many rows) fail the parse.
+pub(crate) fn parse(text: &str) -> Result<(Vec<ImportRow>, Vec<InvalidRow>), ApiError> {
+    let mut records = records(text)?;
+    let mut columns: Vec<Option<usize>> = (0..COLUMNS.len()).map(Some).collect();
+    if let Some((_, first)) = records.first() {
+        let header: Vec<String> = first
+            .iter()
+            .map(|cell| cell.trim().to_ascii_lowercase())
+            .collect();
+        if header.iter().any(|cell| cell == "email") {
+            columns = COLUMNS
+                .iter()
+                .map(|column| header.iter().position(|cell| cell == column))
+                .collect();
+            records.remove(0);
+        }
+    }
+    if records.is_empty() {
+        return Err(ApiError::validation(
+            "invalid_csv",
+            "the file has no users to import",
+        ));
+    }
+    if records.len() > USER_IMPORT_MAX_ROWS {
+        return Err(ApiError::validation(
+            "invalid_csv",
+            format!(
+                "the file has {} rows, the limit is {USER_IMPORT_MAX_ROWS}",
+                records.len()
+            ),
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    let mut rows = Vec::new();
+    let mut invalid = Vec::new();
+    for (line, record) in records {
+        let field = |name: &str| {
+            let index = COLUMNS.iter().position(|column| *column == name)?;
+            columns[index]
+                .and_then(|column| record.get(column))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        match validate(line, field, &mut seen) {
+            Ok(row) => rows.push(row),
+            Err(row) => invalid.push(row),
+        }
+    }
+    Ok((rows, invalid))
+}
+
+/// A random password with upper and lower case letters and a digit.
+pub(crate) fn temporary_password() -> Result<String, ApiError> {
+    loop {
+        let mut bytes = [0_u8; TEMPORARY_PASSWORD_CHARS];
+        getrandom::fill(&mut bytes)
+            .map_err(|err| ApiError::Storage(format!("could not generate a password: {err}")))?;
+        // 256 is a multiple of the alphabet size, so `%` does not skew the draw.
+        let password: String = bytes
+            .iter()
+            .map(|byte| char::from(PASSWORD_ALPHABET[usize::from(*byte) % PASSWORD_ALPHABET.len()]))
+            .collect();
+        if password.chars().any(|ch| ch.is_ascii_uppercase())
+            && password.chars().any(|ch| ch.is_ascii_lowercase())
+            && password.chars().any(|ch| ch.is_ascii_digit())
+        {
+            return Ok(password);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_header_in_any_order_and_quoted_fields() {
+        let csv = "\u{feff}Name,Email,Role\r\n\
+                   \"Pérez, Ana\",Ana@Galynx.local,admin\r\n\
+                   \"Bruno \"\"B\"\"\",bruno@galynx.local,\r\n\
+                   \r\n";
+        let (rows, invalid) = parse(csv).unwrap();
+        assert!(invalid.is_empty());
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].email, "ana@galynx.local");
+        assert_eq!(rows[0].name, "Pérez, Ana");
+        assert_eq!(rows[0].role, Role::Admin);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[1].name, "Bruno \"B\"");
+        assert_eq!(rows[1].role, Role::Member);
+        assert_eq!(rows[1].password, None);
+    }
+
+    #[test]
+    fn reports_every_invalid_row() {
+        let csv = "ana@galynx.local,Ana,member,Secreto-123\n\
+                   no-es-email,Bruno,member\n\
+                   carla@galynx.local,,member\n\
+                   dario@galynx.local,Darío,owner\n\
+                   eva@galynx.local,Eva,guest\n\
+                   fede@galynx.local,Fede,member,corta\n\
+                   ANA@galynx.local,Ana otra vez,admin\n";
+        let (rows, invalid) = parse(csv).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].password.as_deref(), Some("Secreto-123"));
+        let lines: Vec<usize> = invalid.iter().map(|row| row.line).collect();
+        assert_eq!(lines, vec![2, 3, 4, 5, 6, 7]);
+        assert!(invalid[5].reason.contains("more than once"));
+    }
+
+    #[test]
+    fn rejects_broken_or_empty_files() {
+        assert!(parse("email,name\n").is_err());
+        assert!(parse("a@b.co,\"Ana\n").is_err());
+        let many = "a@b.co,Ana,member\n".repeat(USER_IMPORT_MAX_ROWS + 1);
+        assert!(parse(&many).is_err());
+    }
+
+    #[test]
+    fn quoted_line_breaks_keep_line_numbers() {
+        let (rows, invalid) = parse("a@b.co,\"Ana\nMaría\",member\nmal,Bruno,member\n").unwrap();
+        assert_eq!(rows[0].name, "Ana\nMaría");
+        assert_eq!(invalid[0].line, 3);
+    }
+
+    #[test]
+    fn temporary_passwords_meet_the_policy() {
+        let a = temporary_password().unwrap();
+        let b = temporary_password().unwrap();
+        assert_eq!(a.chars().count(), TEMPORARY_PASSWORD_CHARS);
+        assert_ne!(a, b);
+        assert!(a.bytes().all(|byte| PASSWORD_ALPHABET.contains(&byte)));
+        assert!(passwords::check_strength(&a, &[]).is_ok());
+    }
+}